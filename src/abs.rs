@@ -9,8 +9,10 @@
 //! (except for `PathArc`).
 use std::env;
 use std::fmt;
+use std::fs;
 use std::io;
 use std::path::{Component, PrefixComponent};
+use std::time::SystemTime;
 use std_prelude::*;
 
 use super::{PathArc, PathDir, PathFile, Error, Result};
@@ -174,6 +176,221 @@ impl PathAbs {
         PathDir::from_abs(self)
     }
 
+    /// Changes the permissions of the file or directory.
+    ///
+    /// This function is identical to [std::fs::set_permissions][0] except it has error
+    /// messages which include the action and the path.
+    ///
+    /// [0]: https://doc.rust-lang.org/std/fs/fn.set_permissions.html
+    pub fn set_permissions(&self, perm: fs::Permissions) -> Result<()> {
+        fs::set_permissions(self, perm)
+            .map_err(|err| Error::new(err, "setting permisions for", self.clone().into()))
+    }
+
+    /// Set or clear the readonly flag of the file or directory.
+    ///
+    /// > On unix, setting readonly removes all write bits, while clearing it only adds the
+    /// > write bit for the owner.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// file.set_readonly(true)?;
+    /// assert!(file.metadata()?.permissions().readonly());
+    ///
+    /// file.set_readonly(false)?;
+    /// assert!(!file.metadata()?.permissions().readonly());
+    /// file.write_str("foo")?;
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn set_readonly(&self, readonly: bool) -> Result<()> {
+        let mut perms = self.metadata()?.permissions();
+        set_readonly_perms(&mut perms, readonly);
+        self.set_permissions(perms)
+    }
+
+    #[cfg(unix)]
+    /// Get the unix permission bits (i.e. `0o755`) of the file or directory.
+    ///
+    /// The returned value also contains the file type bits, mask it with `0o7777` to get only
+    /// the permissions.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.sh";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// file.set_mode(0o755)?;
+    /// assert_eq!(0o755, file.mode()? & 0o7777);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn mode(&self) -> Result<u32> {
+        use std::os::unix::fs::PermissionsExt;
+        Ok(self.metadata()?.permissions().mode())
+    }
+
+    #[cfg(unix)]
+    /// Set the unix permission bits (i.e. `0o755`) of the file or directory.
+    ///
+    /// See [`mode`](struct.PathAbs.html#method.mode) for an example.
+    pub fn set_mode(&self, mode: u32) -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+        self.set_permissions(fs::Permissions::from_mode(mode))
+    }
+
+    #[cfg(unix)]
+    /// Get the `(uid, gid)` of the owner of the file or directory.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// // Files are owned by the user of the process which created them
+    /// let (uid, gid) = file.owner()?;
+    /// assert_eq!((uid, gid), file.parent_dir().unwrap().owner()?);
+    ///
+    /// // Changing to the current owner is always allowed
+    /// file.chown(Some(uid), None)?;
+    /// file.chown(None, Some(gid))?;
+    /// assert_eq!((uid, gid), file.owner()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn owner(&self) -> Result<(u32, u32)> {
+        use std::os::unix::fs::MetadataExt;
+        let meta = self.metadata()?;
+        Ok((meta.uid(), meta.gid()))
+    }
+
+    #[cfg(unix)]
+    /// Change the owner and/or group of the file or directory. Passing `None` leaves that id
+    /// unchanged.
+    ///
+    /// This follows symlinks. Changing the owner typically requires elevated privileges.
+    ///
+    /// See [`owner`](struct.PathAbs.html#method.owner) for an example.
+    pub fn chown(&self, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+        ::std::os::unix::fs::chown(self, uid, gid)
+            .map_err(|err| Error::new(err, "changing owner of", self.clone().into()))
+    }
+
+    /// Get the last modification time of the file or directory.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::time::SystemTime;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// assert!(file.modified()? <= SystemTime::now());
+    /// assert!(file.accessed()? <= SystemTime::now());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn modified(&self) -> Result<SystemTime> {
+        self.metadata()?
+            .modified()
+            .map_err(|err| Error::new(err, "getting modified time of", self.clone().into()))
+    }
+
+    /// Get the creation time of the file or directory.
+    ///
+    /// Returns `io::ErrorKind::Unsupported` if the platform or filesystem does not record
+    /// creation times.
+    pub fn created(&self) -> Result<SystemTime> {
+        self.metadata()?.created().map_err(|err| {
+            let err = if err.kind() == io::ErrorKind::Unsupported {
+                io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "creation time is not supported on this platform or filesystem",
+                )
+            } else {
+                err
+            };
+            Error::new(err, "getting creation time of", self.clone().into())
+        })
+    }
+
+    /// Get the last access time of the file or directory.
+    ///
+    /// > Many systems do not update the access time on every access (i.e. the `noatime` or
+    /// > `relatime` mount options), so it may be older than expected.
+    pub fn accessed(&self) -> Result<SystemTime> {
+        self.metadata()?
+            .accessed()
+            .map_err(|err| Error::new(err, "getting access time of", self.clone().into()))
+    }
+
+    /// Set the last modification time of the file or directory.
+    ///
+    /// The precision which is kept depends on the filesystem. On unix the file is not opened, so
+    /// only ownership or write permission is needed and FIFOs do not block.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::time::{Duration, UNIX_EPOCH};
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// let time = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    /// file.set_modified(time)?;
+    /// file.set_accessed(time)?;
+    /// assert_eq!(time, file.modified()?);
+    ///
+    /// let dir = file.parent_dir().unwrap();
+    /// dir.set_modified(time)?;
+    /// assert_eq!(time, dir.modified()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn set_modified(&self, time: SystemTime) -> Result<()> {
+        set_times(self, None, Some(time))
+            .map_err(|err| Error::new(err, "setting modified time for", self.clone().into()))
+    }
+
+    /// Set the last access time of the file or directory.
+    ///
+    /// See [`set_modified`](struct.PathAbs.html#method.set_modified) for an example.
+    pub fn set_accessed(&self, time: SystemTime) -> Result<()> {
+        set_times(self, Some(time), None)
+            .map_err(|err| Error::new(err, "setting access time for", self.clone().into()))
+    }
+
     /// Return a reference to a basic `std::path::Path`
     pub fn as_path(&self) -> &Path {
         self.as_ref()
@@ -298,3 +515,87 @@ impl From<PathAbs> for PathBuf {
         arc.into()
     }
 }
+
+#[cfg(unix)]
+fn set_readonly_perms(perms: &mut fs::Permissions, readonly: bool) {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = perms.mode();
+    if readonly {
+        perms.set_mode(mode & !0o222);
+    } else {
+        perms.set_mode(mode | 0o200);
+    }
+}
+
+#[cfg(windows)]
+fn set_readonly_perms(perms: &mut fs::Permissions, readonly: bool) {
+    perms.set_readonly(readonly);
+}
+
+#[cfg(unix)]
+/// Set the access and modification times of a file or directory with `utimensat`, leaving a
+/// `None` time unchanged. This does not open the file, so it neither needs read permission nor
+/// blocks on FIFOs.
+fn set_times(
+    path: &Path,
+    accessed: Option<SystemTime>,
+    modified: Option<SystemTime>,
+) -> io::Result<()> {
+    use libc;
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::time::UNIX_EPOCH;
+
+    let to_timespec = |time: Option<SystemTime>| {
+        let (sec, nsec) = match time.map(|t| t.duration_since(UNIX_EPOCH)) {
+            None => (0, libc::UTIME_OMIT),
+            Some(Ok(d)) => (d.as_secs() as i64, i64::from(d.subsec_nanos())),
+            // Before the epoch: the seconds are negative and the nanoseconds positive.
+            Some(Err(err)) => {
+                let d = err.duration();
+                match d.subsec_nanos() {
+                    0 => (-(d.as_secs() as i64), 0),
+                    n => (-(d.as_secs() as i64) - 1, 1_000_000_000 - i64::from(n)),
+                }
+            }
+        };
+        libc::timespec {
+            tv_sec: sec as libc::time_t,
+            tv_nsec: nsec as _,
+        }
+    };
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains a null byte"))?;
+    let times = [to_timespec(accessed), to_timespec(modified)];
+    if unsafe { libc::utimensat(libc::AT_FDCWD, c_path.as_ptr(), times.as_ptr(), 0) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(windows)]
+/// Set the access and modification times of a file or directory, leaving a `None` time
+/// unchanged.
+fn set_times(
+    path: &Path,
+    accessed: Option<SystemTime>,
+    modified: Option<SystemTime>,
+) -> io::Result<()> {
+    use std::os::windows::fs::OpenOptionsExt;
+    const FILE_WRITE_ATTRIBUTES: u32 = 0x100;
+    // Required to open directories.
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+    let file = fs::OpenOptions::new()
+        .access_mode(FILE_WRITE_ATTRIBUTES)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)?;
+    let mut times = fs::FileTimes::new();
+    if let Some(time) = accessed {
+        times = times.set_accessed(time);
+    }
+    if let Some(time) = modified {
+        times = times.set_modified(time);
+    }
+    file.set_times(times)
+}
@@ -0,0 +1,441 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Checksums of file contents, enabled with the `digest` feature.
+
+use sha2::{Digest, Sha256};
+use sha2::digest::Output;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std_prelude::*;
+
+use super::{Error, Result};
+use super::{PathAbs, PathArc, PathDir, PathFile};
+use file::{read_chunk, CHUNK_SIZE};
+use mirror::read_dir;
+
+impl PathFile {
+    /// Compute the digest of the file's contents using any hash implementing
+    /// [`sha2::Digest`](https://docs.rs/sha2).
+    ///
+    /// The file is streamed through the digest, so it is never entirely read into memory.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate sha2;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    /// use sha2::Sha512;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("abc")?;
+    ///
+    /// let digest = file.digest::<Sha512>()?;
+    /// assert_eq!(64, digest.len());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn digest<D: Digest>(&self) -> Result<Output<D>> {
+        let mut f = self.read()?;
+        let mut hasher = D::new();
+        let mut buf = vec![0; CHUNK_SIZE];
+        loop {
+            match read_chunk(&mut f, &mut buf)? {
+                0 => break,
+                n => hasher.update(&buf[..n]),
+            }
+        }
+        Ok(hasher.finalize())
+    }
+
+    /// Compute the SHA-256 digest of the file's contents.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("abc")?;
+    ///
+    /// assert_eq!(
+    ///     "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+    ///     file.sha256_hex()?,
+    /// );
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn sha256(&self) -> Result<[u8; 32]> {
+        let mut out = [0; 32];
+        out.copy_from_slice(&self.digest::<Sha256>()?);
+        Ok(out)
+    }
+
+    /// Compute the SHA-256 digest of the file's contents as a lowercase hex string.
+    ///
+    /// See [`sha256`](struct.PathFile.html#method.sha256) for an example.
+    pub fn sha256_hex(&self) -> Result<String> {
+        Ok(to_hex(&self.sha256()?))
+    }
+}
+
+/// The error returned by `PathFile::verify_sha256`.
+pub enum VerifyError {
+    /// The digest of the file did not match the expected one. Both digests are lowercase hex.
+    Mismatch {
+        /// The path of the file.
+        path: PathFile,
+        /// The expected digest.
+        expected: String,
+        /// The digest of the file's contents.
+        actual: String,
+    },
+    /// The expected digest was not 64 hex characters.
+    InvalidExpected(String),
+    /// An IO error occurred while reading the file.
+    Io(Error),
+}
+
+impl PathFile {
+    /// Verify that the SHA-256 digest of the file's contents matches `expected_hex`.
+    ///
+    /// The comparison is case-insensitive. `VerifyError::InvalidExpected` is returned without
+    /// reading the file if `expected_hex` is not a SHA-256 hex digest.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathFile, VerifyError};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("abc")?;
+    ///
+    /// let abc = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+    /// file.verify_sha256(abc)?;
+    /// file.verify_sha256(&abc.to_uppercase())?;
+    ///
+    /// file.write_str("abd")?;
+    /// match file.verify_sha256(abc) {
+    ///     Err(VerifyError::Mismatch { actual, .. }) => println!("got {}", actual),
+    ///     _ => panic!("expected a mismatch"),
+    /// }
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn verify_sha256(&self, expected_hex: &str) -> ::std::result::Result<(), VerifyError> {
+        if expected_hex.len() != 64 || !expected_hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(VerifyError::InvalidExpected(expected_hex.to_string()));
+        }
+        let expected = expected_hex.to_ascii_lowercase();
+        let actual = self.sha256_hex()?;
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(VerifyError::Mismatch {
+                path: self.clone(),
+                expected,
+                actual,
+            })
+        }
+    }
+}
+
+impl fmt::Debug for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "VerifyError<{}>", self)
+    }
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VerifyError::Mismatch {
+                ref path,
+                ref expected,
+                ref actual,
+            } => write!(
+                f,
+                "SHA-256 mismatch (expected {}, actual {}) when verifying {}",
+                expected,
+                actual,
+                path.display()
+            ),
+            VerifyError::InvalidExpected(ref expected) => {
+                write!(f, "invalid SHA-256 hex digest {:?}", expected)
+            }
+            VerifyError::Io(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for VerifyError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            VerifyError::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for VerifyError {
+    fn from(err: Error) -> VerifyError {
+        VerifyError::Io(err)
+    }
+}
+
+impl From<VerifyError> for io::Error {
+    fn from(err: VerifyError) -> io::Error {
+        match err {
+            VerifyError::Io(err) => err.into(),
+            VerifyError::InvalidExpected(_) => io::Error::new(io::ErrorKind::InvalidInput, err),
+            VerifyError::Mismatch { .. } => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+impl PathDir {
+    /// Compute a SHA-256 digest of the whole directory tree, which only depends on the names,
+    /// types and contents of the entries.
+    ///
+    /// The entries are hashed in sorted order with their paths relative to the directory, so
+    /// two trees with the same contents have the same digest no matter how or where they were
+    /// created. Files are hashed by their contents (which are streamed) and symlinks by their
+    /// target, without following them. Modification times, permissions and the name of the
+    /// directory itself are not included.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// let example_copy = "example_copy";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// # let example_copy = &tmp.path().join(example_copy);
+    /// let dir = PathDir::create(example)?;
+    /// PathFile::create_all(dir.join("src/lib.rs"))?.write_str("fn main() {}")?;
+    /// let copy = dir.copy_recursive(example_copy)?;
+    /// assert_eq!(dir.hash_contents()?, copy.hash_contents()?);
+    ///
+    /// PathFile::new(copy.join("src/lib.rs"))?.write_str("fn changed() {}")?;
+    /// assert_ne!(dir.hash_contents()?, copy.hash_contents()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn hash_contents(&self) -> Result<[u8; 32]> {
+        let mut hasher = Sha256::new();
+        hash_tree(&mut hasher, self, "")?;
+        let mut out = [0; 32];
+        out.copy_from_slice(&hasher.finalize());
+        Ok(out)
+    }
+}
+
+/// Hash the entries of the directory at `path`, at `rel` in the tree.
+///
+/// Each entry is hashed as a type tag, its length-prefixed relative path (joined with `/` on
+/// all platforms) and then its contents, so different trees can't hash the same bytes.
+fn hash_tree(hasher: &mut Sha256, path: &Path, rel: &str) -> Result<()> {
+    for (name, meta) in read_dir(path, fs::symlink_metadata)? {
+        let path = path.join(&name);
+        let rel = format!("{}{}", rel, name.to_string_lossy());
+        let ty = meta.file_type();
+        let tag: &[u8] = if ty.is_dir() {
+            b"d"
+        } else if ty.is_file() {
+            b"f"
+        } else if ty.is_symlink() {
+            b"l"
+        } else {
+            b"o"
+        };
+        hasher.update(tag);
+        hash_bytes(hasher, rel.as_bytes());
+        if ty.is_dir() {
+            hash_tree(hasher, &path, &format!("{}/", rel))?;
+        } else if ty.is_file() {
+            hasher.update(PathFile(PathAbs(PathArc::new(path))).sha256()?);
+        } else if ty.is_symlink() {
+            let target = fs::read_link(&path)
+                .map_err(|err| Error::new(err, "reading link", PathArc::new(&path)))?;
+            hash_bytes(hasher, target.to_string_lossy().as_bytes());
+        }
+    }
+    Ok(())
+}
+
+/// Hash `bytes` prefixed with their length.
+fn hash_bytes(hasher: &mut Sha256, bytes: &[u8]) {
+    hasher.update((bytes.len() as u64).to_le_bytes());
+    hasher.update(bytes);
+}
+
+/// Encode the bytes as a lowercase hex string.
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        write!(s, "{:02x}", b).expect("writing to a String");
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+    use super::super::{PathDir, PathFile, VerifyError};
+
+    #[test]
+    fn sanity_sha256() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+
+        assert_eq!(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            file.sha256_hex().unwrap()
+        );
+
+        // Larger than the buffer
+        file.write_str(&"a".repeat(1000000)).unwrap();
+        assert_eq!(
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0",
+            file.sha256_hex().unwrap()
+        );
+    }
+
+    #[test]
+    fn sanity_verify_sha256() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("artifact")).unwrap();
+        file.write_str("abc").unwrap();
+        let good = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+        file.verify_sha256(good).unwrap();
+        file.verify_sha256(&good.to_uppercase()).unwrap();
+
+        file.write_str("abd").unwrap();
+        let err = file.verify_sha256(good).unwrap_err();
+        let msg = err.to_string();
+        match err {
+            VerifyError::Mismatch {
+                path,
+                expected,
+                actual,
+            } => {
+                assert_eq!(file, path);
+                assert_eq!(good, expected);
+                assert_eq!(file.sha256_hex().unwrap(), actual);
+                assert!(msg.contains(&expected) && msg.contains(&actual));
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+
+        for bad in &["", "abc", &good[1..], &good.replace("a", "g")] {
+            match file.verify_sha256(bad) {
+                Err(VerifyError::InvalidExpected(ref s)) => assert_eq!(bad, s),
+                res => panic!("unexpected result: {:?}", res),
+            }
+        }
+
+        let missing = PathFile::mock(tmp_abs.join("missing"));
+        match missing.verify_sha256(good) {
+            Err(VerifyError::Io(ref err)) => assert_eq!(missing.as_path(), err.path()),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn sanity_hash_contents() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let files = [("a.txt", "a"), ("b/c.txt", "c"), ("b/d/e.txt", "e"), ("f", "")];
+
+        // The same tree created in a different order
+        let a = PathDir::create(tmp_abs.join("a")).unwrap();
+        for &(path, contents) in files.iter() {
+            PathFile::create_all(a.join(path)).unwrap().write_str(contents).unwrap();
+        }
+        PathDir::create(a.join("empty")).unwrap();
+        let b = PathDir::create(tmp_abs.join("b")).unwrap();
+        PathDir::create(b.join("empty")).unwrap();
+        for &(path, contents) in files.iter().rev() {
+            PathFile::create_all(b.join(path)).unwrap().write_str(contents).unwrap();
+        }
+        let hash = a.hash_contents().unwrap();
+        assert_eq!(hash, b.hash_contents().unwrap());
+        assert_ne!(hash, PathDir::create(tmp_abs.join("c")).unwrap().hash_contents().unwrap());
+
+        // Renaming a file or directory changes the hash
+        let renamed = PathFile::new(b.join("b/c.txt")).unwrap().rename(b.join("b/x.txt")).unwrap();
+        assert_ne!(hash, b.hash_contents().unwrap());
+        renamed.rename(b.join("b/c.txt")).unwrap();
+        assert_eq!(hash, b.hash_contents().unwrap());
+        PathDir::new(b.join("empty")).unwrap().rename(b.join("other")).unwrap();
+        assert_ne!(hash, b.hash_contents().unwrap());
+        PathDir::new(b.join("other")).unwrap().rename(b.join("empty")).unwrap();
+
+        // Moving contents between files changes the hash
+        PathFile::new(b.join("f")).unwrap().write_str("a").unwrap();
+        PathFile::new(b.join("a.txt")).unwrap().write_str("").unwrap();
+        assert_ne!(hash, b.hash_contents().unwrap());
+        PathFile::new(b.join("f")).unwrap().write_str("").unwrap();
+        PathFile::new(b.join("a.txt")).unwrap().write_str("a").unwrap();
+
+        // Modification times and permissions are excluded
+        let file = PathFile::new(b.join("b/d/e.txt")).unwrap();
+        file.set_modified(::std::time::UNIX_EPOCH).unwrap();
+        file.set_readonly(true).unwrap();
+        assert_eq!(hash, b.hash_contents().unwrap());
+        file.set_readonly(false).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sanity_hash_contents_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let a = PathDir::create(tmp_abs.join("a")).unwrap();
+        let b = PathDir::create(tmp_abs.join("b")).unwrap();
+        for dir in &[&a, &b] {
+            PathFile::create(dir.join("target")).unwrap().write_str("target").unwrap();
+            symlink("missing", dir.join("broken")).unwrap();
+        }
+        symlink("target", a.join("link")).unwrap();
+        symlink("target", b.join("link")).unwrap();
+        assert_eq!(a.hash_contents().unwrap(), b.hash_contents().unwrap());
+
+        // A link is not the same as a copy of its target
+        let c = PathDir::create(tmp_abs.join("c")).unwrap();
+        PathFile::create(c.join("target")).unwrap().write_str("target").unwrap();
+        symlink("missing", c.join("broken")).unwrap();
+        PathFile::create(c.join("link")).unwrap().write_str("target").unwrap();
+        assert_ne!(a.hash_contents().unwrap(), c.hash_contents().unwrap());
+
+        ::std::fs::remove_file(b.join("link")).unwrap();
+        symlink("broken", b.join("link")).unwrap();
+        assert_ne!(a.hash_contents().unwrap(), b.hash_contents().unwrap());
+    }
+}
@@ -0,0 +1,444 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Configurable file copies.
+
+use std::fs;
+use std::io;
+use std_prelude::*;
+
+use super::file::{is_same_file, read_chunk, CHUNK_SIZE};
+use super::open::FileOpen;
+use super::{Error, Result};
+use super::{FileWrite, PathAbs, PathFile};
+
+/// Options which configure how `PathFile::copy_with` copies a file.
+///
+/// The defaults match `PathFile::copy` (and [std::fs::copy][0]): the permissions are copied and
+/// an existing destination is overwritten, but the modification time is not preserved.
+///
+/// [0]: https://doc.rust-lang.org/std/fs/fn.copy.html
+///
+/// # Examples
+/// ```rust
+/// # extern crate path_abs;
+/// # extern crate tempdir;
+/// use path_abs::{CopyOptions, PathFile};
+///
+/// # fn try_main() -> ::std::io::Result<()> {
+/// let example = "example.txt";
+/// let example_bk = "example.txt.bk";
+/// # let tmp = tempdir::TempDir::new("ex")?;
+/// # let example = &tmp.path().join(example);
+/// # let example_bk = &tmp.path().join(example_bk);
+/// let file = PathFile::create(example)?;
+/// file.write_str("This is some contents")?;
+///
+/// let options = CopyOptions::new().preserve_mtime(true).overwrite(false).clone();
+/// let file_bk = file.copy_with(example_bk, &options)?;
+/// assert_eq!("This is some contents", file_bk.read_string()?);
+/// assert_eq!(file.modified()?, file_bk.modified()?);
+///
+/// // The backup can't be overwritten
+/// assert!(file.copy_with(example_bk, &options).is_err());
+/// # Ok(()) } fn main() { try_main().unwrap() }
+/// ```
+#[derive(Clone, Debug)]
+pub struct CopyOptions {
+    preserve_mtime: bool,
+    preserve_permissions: bool,
+    overwrite: bool,
+}
+
+impl CopyOptions {
+    /// Creates the default options, see the type documentation.
+    pub fn new() -> CopyOptions {
+        CopyOptions {
+            preserve_mtime: false,
+            preserve_permissions: true,
+            overwrite: true,
+        }
+    }
+
+    /// Sets whether the modification time of the source is copied to the destination.
+    pub fn preserve_mtime(&mut self, preserve_mtime: bool) -> &mut CopyOptions {
+        self.preserve_mtime = preserve_mtime;
+        self
+    }
+
+    /// Sets whether the permissions of the source are copied to the destination.
+    ///
+    /// If not, a new destination gets the default permissions for new files and an existing
+    /// destination keeps its own.
+    pub fn preserve_permissions(&mut self, preserve_permissions: bool) -> &mut CopyOptions {
+        self.preserve_permissions = preserve_permissions;
+        self
+    }
+
+    /// Sets whether an existing destination is overwritten. If not, the copy fails with
+    /// `io::ErrorKind::AlreadyExists`.
+    pub fn overwrite(&mut self, overwrite: bool) -> &mut CopyOptions {
+        self.overwrite = overwrite;
+        self
+    }
+}
+
+impl Default for CopyOptions {
+    fn default() -> CopyOptions {
+        CopyOptions::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+/// What `PathFile::backup_with` does when the backup file already exists.
+pub enum BackupPolicy {
+    /// Replace the existing backup.
+    Overwrite,
+    /// Keep existing backups and number the new one, i.e. `foo.bak.1`, `foo.bak.2`, etc.
+    Numbered,
+}
+
+impl PathFile {
+    /// Copy the file to `path` with the given `CopyOptions`, returning the new `PathFile`.
+    ///
+    /// When `overwrite` is false the destination is created atomically, so an existing file is
+    /// never replaced even if it is created concurrently. Copying a file onto itself returns
+    /// `io::ErrorKind::InvalidInput`.
+    ///
+    /// See [`CopyOptions`](struct.CopyOptions.html) for an example.
+    pub fn copy_with<P: AsRef<Path>>(&self, path: P, options: &CopyOptions) -> Result<PathFile> {
+        self.copy_impl(path.as_ref(), options, &mut |_, _| {})
+    }
+
+    /// Copy the file to `path` like `copy`, calling `progress` with the number of bytes copied
+    /// so far and the total size of the file after every chunk is copied.
+    ///
+    /// `progress` is always called at least once, when the copy is complete. Copying a file onto
+    /// itself returns `io::ErrorKind::InvalidInput` like `copy_with`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// let example_bk = "example.txt.bk";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// # let example_bk = &tmp.path().join(example_bk);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("This is some contents")?;
+    ///
+    /// let file_bk = file.copy_with_progress(example_bk, |copied, total| {
+    ///     // Empty files have a total size of 0.
+    ///     if total > 0 {
+    ///         println!("{}%", copied * 100 / total);
+    ///     }
+    /// })?;
+    /// assert_eq!("This is some contents", file_bk.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn copy_with_progress<P, F>(&self, path: P, mut progress: F) -> Result<PathFile>
+    where
+        P: AsRef<Path>,
+        F: FnMut(u64, u64),
+    {
+        self.copy_impl(path.as_ref(), &CopyOptions::new(), &mut progress)
+    }
+
+    fn copy_impl(
+        &self,
+        dest: &Path,
+        options: &CopyOptions,
+        progress: &mut dyn FnMut(u64, u64),
+    ) -> Result<PathFile> {
+        let err_action = || format!("copying {} from", dest.display());
+        // Opening the destination would truncate the source.
+        if is_same_file(self, dest) {
+            return Err(Error::new(
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "source and destination are the same file",
+                ),
+                &err_action(),
+                self.clone().into(),
+            ));
+        }
+
+        let mut read = self.read()?;
+        let meta = read.metadata()?;
+
+        let mut open = fs::OpenOptions::new();
+        open.write(true);
+        if options.overwrite {
+            open.create(true).truncate(true);
+        } else {
+            open.create_new(true);
+        }
+        let file = open.open(dest).map_err(|err| {
+            let err = if err.kind() == io::ErrorKind::AlreadyExists {
+                io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("destination {} already exists", dest.display()),
+                )
+            } else {
+                err
+            };
+            Error::new(err, &err_action(), self.clone().into())
+        })?;
+        let dest = PathFile::from_abs_unchecked(PathAbs::new(dest)?);
+        let mut write = FileWrite(FileOpen {
+            path: dest.clone(),
+            file,
+        });
+
+        let total = meta.len();
+        let mut copied = 0;
+        let mut buf = vec![0; CHUNK_SIZE];
+        loop {
+            match read_chunk(&mut read, &mut buf)? {
+                0 => break,
+                n => {
+                    write.write_bytes(&buf[..n])?;
+                    copied += n as u64;
+                    progress(copied, total);
+                }
+            }
+        }
+        if copied == 0 {
+            progress(0, total);
+        }
+        write.flush()?;
+        if options.preserve_permissions {
+            write.set_permissions(meta.permissions())?;
+        }
+        if options.preserve_mtime {
+            let modified = meta
+                .modified()
+                .map_err(|err| Error::new(err, "getting modified time of", self.clone().into()))?;
+            AsRef::<File>::as_ref(&write)
+                .set_modified(modified)
+                .map_err(|err| Error::new(err, "setting modified time for", dest.clone().into()))?;
+        }
+        Ok(dest)
+    }
+}
+
+impl PathFile {
+    /// Copy the file to a sibling with the `.bak` suffix (i.e. `foo.txt.bak`), replacing any
+    /// previous backup, and return the backup.
+    ///
+    /// The permissions are copied like `copy`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{BackupPolicy, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("v1")?;
+    ///
+    /// let bak = file.backup()?;
+    /// assert!(bak.ends_with("example.txt.bak"));
+    ///
+    /// file.write_str("v2")?;
+    /// let orig = file.backup_with(".orig", BackupPolicy::Numbered)?;
+    /// let orig1 = file.backup_with(".orig", BackupPolicy::Numbered)?;
+    /// assert!(orig.ends_with("example.txt.orig"));
+    /// assert!(orig1.ends_with("example.txt.orig.1"));
+    /// assert_eq!("v1", bak.read_string()?);
+    /// assert_eq!("v2", orig1.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn backup(&self) -> Result<PathFile> {
+        self.backup_with(".bak", BackupPolicy::Overwrite)
+    }
+
+    /// Copy the file to a sibling with `suffix` appended to its name and return the backup.
+    ///
+    /// See [`backup`](struct.PathFile.html#method.backup) for an example.
+    pub fn backup_with(&self, suffix: &str, policy: BackupPolicy) -> Result<PathFile> {
+        let mut name = self.as_os_str().to_os_string();
+        name.push(suffix);
+        match policy {
+            BackupPolicy::Overwrite => self.copy(name),
+            BackupPolicy::Numbered => {
+                let mut options = CopyOptions::new();
+                options.overwrite(false);
+                let mut n = 0;
+                loop {
+                    let mut numbered = name.clone();
+                    if n > 0 {
+                        numbered.push(format!(".{}", n));
+                    }
+                    match self.copy_with(numbered, &options) {
+                        Err(ref err) if err.io_error().kind() == io::ErrorKind::AlreadyExists => {
+                            n += 1;
+                        }
+                        res => return res,
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{BackupPolicy, CopyOptions, PathDir, PathFile};
+    use std::fs;
+    use std::io;
+    use std::time::{Duration, SystemTime};
+    use tempdir::TempDir;
+
+    fn setup() -> (TempDir, PathFile, SystemTime) {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo")).unwrap();
+        file.write_str("foo").unwrap();
+        let past = SystemTime::now() - Duration::from_secs(60 * 60);
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(past)
+            .unwrap();
+        file.set_readonly(true).unwrap();
+        (tmp_dir, file, past)
+    }
+
+    #[test]
+    fn sanity_copy_with_defaults() {
+        let (tmp_dir, file, past) = setup();
+        let copied = file
+            .copy_with(tmp_dir.path().join("bar"), &CopyOptions::new())
+            .unwrap();
+        assert_eq!("foo", copied.read_string().unwrap());
+        assert!(copied.metadata().unwrap().permissions().readonly());
+        assert_ne!(past, copied.modified().unwrap());
+    }
+
+    #[test]
+    fn sanity_copy_with_mtime() {
+        let (tmp_dir, file, past) = setup();
+        let copied = file
+            .copy_with(
+                tmp_dir.path().join("bar"),
+                CopyOptions::new().preserve_mtime(true),
+            )
+            .unwrap();
+        assert_eq!(past, copied.modified().unwrap());
+    }
+
+    #[test]
+    fn sanity_copy_with_permissions() {
+        let (tmp_dir, file, _) = setup();
+        let copied = file
+            .copy_with(
+                tmp_dir.path().join("bar"),
+                CopyOptions::new().preserve_permissions(false),
+            )
+            .unwrap();
+        assert!(!copied.metadata().unwrap().permissions().readonly());
+        copied.write_str("bar").unwrap();
+    }
+
+    #[test]
+    fn sanity_copy_with_overwrite() {
+        let (tmp_dir, file, _) = setup();
+        let existing = PathFile::create(tmp_dir.path().join("bar")).unwrap();
+        existing.write_str("existing").unwrap();
+
+        let err = file
+            .copy_with(&existing, CopyOptions::new().overwrite(false))
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+        assert!(err.to_string().contains(&existing.display().to_string()));
+        assert_eq!("existing", existing.read_string().unwrap());
+
+        file.copy_with(&existing, &CopyOptions::new()).unwrap();
+        assert_eq!("foo", existing.read_string().unwrap());
+    }
+
+    #[test]
+    fn sanity_copy_with_same_file() {
+        let (tmp_dir, file, _) = setup();
+        for dest in &[file.to_path_buf(), tmp_dir.path().join(".").join("foo")] {
+            let err = file.copy_with(dest, &CopyOptions::new()).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidInput, err.io_error().kind());
+            assert_eq!(file.as_path(), err.path());
+        }
+        assert_eq!("foo", file.read_string().unwrap());
+    }
+
+    #[test]
+    fn sanity_backup() {
+        let (tmp_dir, file, _) = setup();
+        let bak = file.backup().unwrap();
+        assert_eq!(tmp_dir.path().join("foo.bak"), bak.as_path());
+        assert_eq!("foo", bak.read_string().unwrap());
+        assert!(bak.metadata().unwrap().permissions().readonly());
+        bak.set_readonly(false).unwrap();
+        assert_eq!(bak, file.backup().unwrap());
+
+        let backups: Vec<_> = (0..3)
+            .map(|_| file.backup_with(".bak", BackupPolicy::Numbered).unwrap())
+            .collect();
+        let names: Vec<_> = backups
+            .iter()
+            .map(|b| b.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(vec!["foo.bak.1", "foo.bak.2", "foo.bak.3"], names);
+        for b in &backups {
+            assert_eq!("foo", b.read_string().unwrap());
+        }
+    }
+
+    #[test]
+    fn sanity_copy_with_progress() {
+        let (tmp_dir, file, _) = setup();
+        file.set_readonly(false).unwrap();
+        let bytes: Vec<u8> = (0..3_000_000u32).map(|i| (i % 251) as u8).collect();
+        file.write_bytes(&bytes).unwrap();
+
+        let mut calls = Vec::new();
+        let copied = file
+            .copy_with_progress(tmp_dir.path().join("bar"), |copied, total| {
+                calls.push((copied, total))
+            })
+            .unwrap();
+        assert_eq!(bytes, copied.read_bytes().unwrap());
+        assert!(calls.len() > 1);
+        assert_eq!(Some(&(3_000_000, 3_000_000)), calls.last());
+        assert!(calls.windows(2).all(|w| w[0].0 < w[1].0));
+
+        let empty = PathFile::create(tmp_dir.path().join("empty")).unwrap();
+        let mut calls = Vec::new();
+        empty
+            .copy_with_progress(tmp_dir.path().join("empty2"), |copied, total| {
+                calls.push((copied, total))
+            })
+            .unwrap();
+        assert_eq!(vec![(0, 0)], calls);
+
+        // Copying onto itself fails without calling `progress` or truncating the file
+        let mut calls = Vec::new();
+        let err = file
+            .copy_with_progress(&file, |copied, total| calls.push((copied, total)))
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.io_error().kind());
+        assert!(calls.is_empty());
+        assert_eq!(bytes, file.read_bytes().unwrap());
+    }
+}
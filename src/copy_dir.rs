@@ -0,0 +1,682 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Recursive directory copies.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std_prelude::*;
+
+use super::dir::{canonicalize_existing, symlink_dir};
+use super::file::symlink_file;
+use super::{CopyOptions, Error, Result};
+use super::{PathAbs, PathArc, PathDir, PathFile, PathType};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+/// How `PathDir::copy_recursive_with` copies symlinks.
+pub enum SymlinkPolicy {
+    /// Copy the file or directory the symlink points to.
+    Follow,
+    /// Create a symlink with the same (possibly relative) target.
+    Preserve,
+    /// Don't copy symlinks.
+    Skip,
+}
+
+/// Options which configure how `PathDir::copy_recursive_with` copies a directory.
+///
+/// The defaults match `PathDir::copy_recursive`: existing files are overwritten, symlinks are
+/// followed, every entry is copied and modification times are not preserved.
+///
+/// # Examples
+/// ```rust
+/// # extern crate path_abs;
+/// # extern crate tempdir;
+/// use path_abs::{DirCopyOptions, PathDir, PathFile, SymlinkPolicy};
+///
+/// # fn try_main() -> ::std::io::Result<()> {
+/// let example = "example";
+/// let example_copy = "example_copy";
+/// # let tmp = tempdir::TempDir::new("ex")?;
+/// # let example = &tmp.path().join(example);
+/// # let example_copy = &tmp.path().join(example_copy);
+/// let project = PathDir::create(example)?;
+/// PathFile::create(project.join("Cargo.toml"))?;
+/// PathFile::create_all(project.join("target/debug/build.log"))?;
+///
+/// let options = DirCopyOptions::new()
+///     .symlinks(SymlinkPolicy::Preserve)
+///     .filter(|entry| !entry.ends_with("target"))
+///     .clone();
+/// let copy = project.copy_recursive_with(example_copy, &options)?;
+/// assert!(copy.join("Cargo.toml").exists());
+/// assert!(!copy.join("target").exists());
+/// # Ok(()) } fn main() { try_main().unwrap() }
+/// ```
+#[derive(Clone)]
+pub struct DirCopyOptions {
+    overwrite: bool,
+    symlinks: SymlinkPolicy,
+    preserve_mtime: bool,
+    filter: Option<Arc<EntryFilter>>,
+}
+
+/// The filter of `DirCopyOptions`.
+type EntryFilter = dyn Fn(&PathType) -> bool;
+
+impl DirCopyOptions {
+    /// Creates the default options, see the type documentation.
+    pub fn new() -> DirCopyOptions {
+        DirCopyOptions {
+            overwrite: true,
+            symlinks: SymlinkPolicy::Follow,
+            preserve_mtime: false,
+            filter: None,
+        }
+    }
+
+    /// Sets whether existing files (and symlinks) in the destination are overwritten. If not,
+    /// the copy fails with `io::ErrorKind::AlreadyExists`. Existing directories are always
+    /// merged into.
+    pub fn overwrite(&mut self, overwrite: bool) -> &mut DirCopyOptions {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Sets how symlinks are copied.
+    pub fn symlinks(&mut self, symlinks: SymlinkPolicy) -> &mut DirCopyOptions {
+        self.symlinks = symlinks;
+        self
+    }
+
+    /// Sets whether the modification times of files and directories are copied.
+    pub fn preserve_mtime(&mut self, preserve_mtime: bool) -> &mut DirCopyOptions {
+        self.preserve_mtime = preserve_mtime;
+        self
+    }
+
+    /// Only copy the entries for which `filter` returns true. Excluded directories are not
+    /// descended into.
+    ///
+    /// The filter is called with the source path of each entry. It is not called for symlinks
+    /// which are skipped or broken.
+    pub fn filter<F: Fn(&PathType) -> bool + 'static>(&mut self, filter: F) -> &mut DirCopyOptions {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+}
+
+impl Default for DirCopyOptions {
+    fn default() -> DirCopyOptions {
+        DirCopyOptions::new()
+    }
+}
+
+impl fmt::Debug for DirCopyOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DirCopyOptions")
+            .field("overwrite", &self.overwrite)
+            .field("symlinks", &self.symlinks)
+            .field("preserve_mtime", &self.preserve_mtime)
+            .field("filter", &self.filter.is_some())
+            .finish()
+    }
+}
+
+impl PathDir {
+    /// Recursively copy the directory and its contents to `dest`, returning the new `PathDir`.
+    ///
+    /// `dest` and its missing parents are created. If it already exists the contents are merged
+    /// into it, replacing existing files. Symlinks are followed, so their targets are copied.
+    ///
+    /// The error for an entry which could not be copied has the path of the entry and the
+    /// destination in its action, i.e. `copying {dest} from {entry}`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// let example_copy = "example_copy";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// # let example_copy = &tmp.path().join(example_copy);
+    /// let dir = PathDir::create_all(example)?;
+    /// PathFile::create_all(dir.join("a/b.txt"))?.write_str("b")?;
+    ///
+    /// let copy = dir.copy_recursive(example_copy)?;
+    /// assert_eq!("b", PathFile::new(copy.join("a/b.txt"))?.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn copy_recursive<P: AsRef<Path>>(&self, dest: P) -> Result<PathDir> {
+        self.copy_recursive_with(dest, &DirCopyOptions::new())
+    }
+
+    /// Recursively copy the directory to `dest` like `copy_recursive`, with the given
+    /// `DirCopyOptions`.
+    ///
+    /// With `preserve_mtime` the modification time of `dest` itself is also set.
+    ///
+    /// See [`DirCopyOptions`](struct.DirCopyOptions.html) for an example.
+    pub fn copy_recursive_with<P: AsRef<Path>>(
+        &self,
+        dest: P,
+        options: &DirCopyOptions,
+    ) -> Result<PathDir> {
+        let dest = PathAbs::new(dest)?;
+        // Compare the resolved paths, since either can be reached through a symlink.
+        if canonicalize_existing(&dest)?.starts_with(self.canonicalize()?) {
+            return Err(Error::new(
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "destination is inside of the source directory",
+                ),
+                &format!("copying {} from", dest.display()),
+                self.clone().into(),
+            ));
+        }
+        let dest = PathDir::create_all(dest)?;
+        let mut copy = DirCopy {
+            options,
+            files: CopyOptions::new()
+                .overwrite(options.overwrite)
+                .preserve_mtime(options.preserve_mtime)
+                .clone(),
+            ancestors: vec![self.canonicalize()?.to_path_buf()],
+        };
+        copy.tree(self, &dest)?;
+        if options.preserve_mtime {
+            dest.set_modified(self.modified()?)?;
+        }
+        Ok(dest)
+    }
+
+    /// Recursively copy the directory into `dest_parent`, keeping its name, and return the new
+    /// `PathDir`. This is like `PathFile::copy_to_dir` for directories.
+    ///
+    /// If `dest_parent` already has an entry with the same name this returns
+    /// `io::ErrorKind::AlreadyExists`, use `copy_into_opts` to merge into it instead.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::io;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let project = PathDir::create_all(example.join("project"))?;
+    /// PathFile::create(project.join("README.md"))?;
+    /// let backups = PathDir::create(example.join("backups"))?;
+    ///
+    /// let copy = project.copy_into(&backups)?;
+    /// assert_eq!(backups.join("project").as_path(), copy.as_path());
+    /// assert!(copy.join("README.md").exists());
+    ///
+    /// let err = project.copy_into(&backups).unwrap_err();
+    /// assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn copy_into(&self, dest_parent: &PathDir) -> Result<PathDir> {
+        self.copy_into_opts(dest_parent, false)
+    }
+
+    /// Recursively copy the directory into `dest_parent` like `copy_into`.
+    ///
+    /// If `merge` is true and `dest_parent` already has a directory with the same name, the
+    /// contents are merged into it like `copy_recursive`, replacing existing files.
+    pub fn copy_into_opts(&self, dest_parent: &PathDir, merge: bool) -> Result<PathDir> {
+        let name = match self.file_name() {
+            Some(name) => name,
+            None => {
+                return Err(Error::new(
+                    io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"),
+                    &format!("copying into {} from", dest_parent.display()),
+                    self.clone().into(),
+                ))
+            }
+        };
+        let dest = dest_parent.join(name);
+        if !merge && fs::symlink_metadata(&dest).is_ok() {
+            return Err(Error::new(
+                io::Error::new(io::ErrorKind::AlreadyExists, "destination already exists"),
+                &format!("copying {} from", dest.display()),
+                self.clone().into(),
+            ));
+        }
+        self.copy_recursive(dest)
+    }
+}
+
+/// The state of a recursive copy.
+struct DirCopy<'a> {
+    options: &'a DirCopyOptions,
+    files: CopyOptions,
+    /// The canonical paths of the directory being copied and the directories containing it,
+    /// which are used to detect symlink loops.
+    ancestors: Vec<PathBuf>,
+}
+
+impl<'a> DirCopy<'a> {
+    /// Copy the contents of `src` into the existing directory `dest`.
+    fn tree(&mut self, src: &Path, dest: &Path) -> Result<()> {
+        let entries =
+            fs::read_dir(src).map_err(|err| Error::new(err, "reading dir", PathArc::new(src)))?;
+        for entry in entries {
+            let entry = entry.map_err(|err| Error::new(err, "reading dir", PathArc::new(src)))?;
+            self.entry(&entry.path(), &dest.join(entry.file_name()))?;
+        }
+        Ok(())
+    }
+
+    /// Copy the entry at `from` to `to`.
+    fn entry(&mut self, from: &Path, to: &Path) -> Result<()> {
+        let err_map = |err| {
+            Error::new(
+                err,
+                &format!("copying {} from", to.display()),
+                PathArc::new(from),
+            )
+        };
+
+        let link = fs::symlink_metadata(from).map_err(err_map)?;
+        let is_symlink = link.file_type().is_symlink();
+        if is_symlink && self.options.symlinks == SymlinkPolicy::Skip {
+            return Ok(());
+        }
+        // A broken symlink has no type to filter on.
+        let meta = match fs::metadata(from) {
+            Ok(meta) => Some(meta),
+            Err(ref err) if is_symlink && err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err_map(err)),
+        };
+        if let (Some(filter), Some(meta)) = (self.options.filter.as_ref(), meta.as_ref()) {
+            let abs = PathAbs(PathArc::new(from));
+            let ty = if meta.is_dir() {
+                PathType::Dir(PathDir(abs))
+            } else {
+                PathType::File(PathFile(abs))
+            };
+            if !filter(&ty) {
+                return Ok(());
+            }
+        }
+
+        if is_symlink && self.options.symlinks == SymlinkPolicy::Preserve {
+            return self.symlink(from, to, meta.map(|m| m.is_dir()).unwrap_or(false));
+        }
+        match meta {
+            Some(ref meta) if meta.is_dir() => self.dir(from, to, meta),
+            Some(_) => {
+                PathFile(PathAbs(PathArc::new(from))).copy_with(to, &self.files)?;
+                Ok(())
+            }
+            None => Err(err_map(io::Error::new(
+                io::ErrorKind::NotFound,
+                "symlink target does not exist",
+            ))),
+        }
+    }
+
+    /// Copy the directory at `from` to `to`.
+    fn dir(&mut self, from: &Path, to: &Path, meta: &fs::Metadata) -> Result<()> {
+        let err_map = |err| {
+            Error::new(
+                err,
+                &format!("copying {} from", to.display()),
+                PathArc::new(from),
+            )
+        };
+        let canonical = from.canonicalize().map_err(err_map)?;
+        if self.ancestors.contains(&canonical) {
+            return Err(err_map(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "symlink loop detected",
+            )));
+        }
+        match fs::create_dir(to) {
+            Ok(()) => {}
+            Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists && to.is_dir() => {}
+            Err(err) => return Err(err_map(err)),
+        }
+        self.ancestors.push(canonical);
+        let copied = self.tree(from, to);
+        self.ancestors.pop();
+        copied?;
+        if self.options.preserve_mtime {
+            let modified = meta.modified().map_err(err_map)?;
+            PathAbs(PathArc::new(to)).set_modified(modified)?;
+        }
+        Ok(())
+    }
+
+    /// Recreate the symlink at `from` at `to`, with the same target.
+    fn symlink(&self, from: &Path, to: &Path, is_dir: bool) -> Result<()> {
+        let err_map = |err| {
+            Error::new(
+                err,
+                &format!("copying {} from", to.display()),
+                PathArc::new(from),
+            )
+        };
+        let target = fs::read_link(from).map_err(err_map)?;
+        if fs::symlink_metadata(to).is_ok() {
+            if !self.options.overwrite {
+                return Err(err_map(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("destination {} already exists", to.display()),
+                )));
+            }
+            match fs::remove_file(to) {
+                // Symlinks to directories are directories on windows.
+                Err(_) if cfg!(windows) => fs::remove_dir(to),
+                result => result,
+            }.map_err(err_map)?;
+        }
+        if is_dir {
+            symlink_dir(&target, to)
+        } else {
+            symlink_file(&target, to)
+        }.map_err(err_map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{DirCopyOptions, PathDir, PathFile, PathType, SymlinkPolicy};
+    use std::cell::RefCell;
+    use std::fs;
+    use std::io;
+    use std::rc::Rc;
+    use std::time::{Duration, SystemTime};
+    use std::path::{Path, PathBuf};
+    use tempdir::TempDir;
+
+    /// The relative paths in `dir` with the contents of files.
+    fn contents(dir: &PathDir) -> Vec<(String, Option<String>)> {
+        fn walk(root: &PathDir, dir: &PathDir, out: &mut Vec<(String, Option<String>)>) {
+            for entry in dir.list().unwrap() {
+                let entry = entry.unwrap();
+                let rel = entry.strip_prefix(root).unwrap().display().to_string();
+                match entry {
+                    PathType::File(file) => out.push((rel, Some(file.read_string().unwrap()))),
+                    PathType::Dir(sub) => {
+                        out.push((rel, None));
+                        walk(root, &sub, out);
+                    }
+                }
+            }
+        }
+        let mut out = Vec::new();
+        walk(dir, dir, &mut out);
+        out.sort();
+        out
+    }
+
+    #[test]
+    fn sanity_copy_recursive() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let src = PathDir::create(tmp_abs.join("src")).unwrap();
+        PathFile::create_with(src.join("top.txt"), b"top").unwrap();
+        PathFile::create_all(src.join("a/mid.txt"))
+            .unwrap()
+            .write_str("mid")
+            .unwrap();
+        PathFile::create_all(src.join("a/b/deep.txt"))
+            .unwrap()
+            .write_str("deep")
+            .unwrap();
+        PathDir::create(src.join("a/empty")).unwrap();
+
+        let dest = src.copy_recursive(tmp_abs.join("out/dest")).unwrap();
+        assert_eq!(tmp_abs.join("out/dest").as_path(), dest.as_path());
+        assert_eq!(6, contents(&src).len());
+        assert_eq!(contents(&src), contents(&dest));
+
+        // Copying again merges into the existing destination
+        PathFile::new(src.join("a/mid.txt"))
+            .unwrap()
+            .write_str("changed")
+            .unwrap();
+        src.copy_recursive(&dest).unwrap();
+        assert_eq!(contents(&src), contents(&dest));
+
+        let err = src.copy_recursive(src.join("a/inside")).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.io_error().kind());
+        assert!(!src.join("a/inside").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sanity_copy_recursive_through_symlink() {
+        use std::os::unix::fs::symlink;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let src = PathDir::create_all(tmp_abs.join("a/src")).unwrap();
+        PathFile::create_with(src.join("file.txt"), b"file").unwrap();
+        symlink(&tmp_abs, tmp_abs.join("alias")).unwrap();
+
+        // `alias/a/src/inside` is inside of the source, even though it doesn't look like it
+        let err = src
+            .copy_recursive(tmp_abs.join("alias/a/src/inside"))
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.io_error().kind());
+        assert!(!src.join("inside").exists());
+        assert_eq!(1, contents(&src).len());
+    }
+
+    #[test]
+    fn sanity_copy_into() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let project = PathDir::create(tmp_abs.join("project")).unwrap();
+        PathFile::create_with(project.join("Cargo.toml"), b"toml").unwrap();
+        PathFile::create_all(project.join("src/lib.rs"))
+            .unwrap()
+            .write_str("lib")
+            .unwrap();
+        let backups = PathDir::create(tmp_abs.join("backups")).unwrap();
+
+        let copy = project.copy_into(&backups).unwrap();
+        assert_eq!(backups.join("project").as_path(), copy.as_path());
+        assert_eq!(3, contents(&copy).len());
+        assert_eq!(contents(&project), contents(&copy));
+
+        // The existing copy is kept by default
+        PathFile::new(project.join("src/lib.rs"))
+            .unwrap()
+            .write_str("changed")
+            .unwrap();
+        let err = project.copy_into(&backups).unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+        assert_eq!(project.as_path(), err.path());
+        let lib = PathFile::new(copy.join("src/lib.rs")).unwrap();
+        assert_eq!("lib", lib.read_string().unwrap());
+
+        let merged = project.copy_into_opts(&backups, true).unwrap();
+        assert_eq!(copy, merged);
+        assert_eq!(contents(&project), contents(&merged));
+
+        let other = PathDir::create(tmp_abs.join("other")).unwrap();
+        PathFile::create(other.join("project")).unwrap();
+        let err = project.copy_into(&other).unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+    }
+
+    #[test]
+    fn sanity_copy_recursive_symlinks() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let outside = PathDir::create(tmp_abs.join("outside")).unwrap();
+        PathFile::create_with(outside.join("target.txt"), b"target").unwrap();
+        let src = PathDir::create(tmp_abs.join("src")).unwrap();
+        outside.symlink(src.join("link")).unwrap();
+
+        let dest = src.copy_recursive(tmp_abs.join("dest")).unwrap();
+        let copied = PathFile::new(dest.join("link/target.txt")).unwrap();
+        assert_eq!("target", copied.read_string().unwrap());
+        assert!(!dest
+            .join("link")
+            .symlink_metadata()
+            .unwrap()
+            .file_type()
+            .is_symlink());
+
+        // A loop is an error naming the entry, instead of recursing forever
+        src.symlink(src.join("loop")).unwrap();
+        let err = src.copy_recursive(tmp_abs.join("dest2")).unwrap_err();
+        assert_eq!(src.join("loop").as_path(), err.path());
+        assert_eq!(
+            format!("copying {} from", tmp_abs.join("dest2/loop").display()),
+            err.action()
+        );
+    }
+
+    #[test]
+    fn sanity_copy_recursive_overwrite() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let src = PathDir::create(tmp_abs.join("src")).unwrap();
+        PathFile::create_with(src.join("new.txt"), b"new").unwrap();
+        PathFile::create_with(src.join("both.txt"), b"src").unwrap();
+        let dest = PathDir::create(tmp_abs.join("dest")).unwrap();
+        let both = PathFile::create_with(dest.join("both.txt"), b"dest").unwrap();
+
+        let mut options = DirCopyOptions::new();
+        options.overwrite(false);
+        let err = src.copy_recursive_with(&dest, &options).unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+        assert_eq!(src.join("both.txt").as_path(), err.path());
+        assert_eq!("dest", both.read_string().unwrap());
+
+        options.overwrite(true);
+        src.copy_recursive_with(&dest, &options).unwrap();
+        assert_eq!("src", both.read_string().unwrap());
+    }
+
+    #[test]
+    fn sanity_copy_recursive_preserve_symlinks() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let src = PathDir::create(tmp_abs.join("src")).unwrap();
+        let sub = PathDir::create(src.join("sub")).unwrap();
+        PathFile::create_with(sub.join("file.txt"), b"file").unwrap();
+        PathFile::new(sub.join("file.txt"))
+            .unwrap()
+            .symlink(src.join("absolute"))
+            .unwrap();
+        #[cfg(unix)]
+        {
+            ::std::os::unix::fs::symlink("sub", src.join("relative")).unwrap();
+            ::std::os::unix::fs::symlink("missing", src.join("broken")).unwrap();
+        }
+
+        let mut options = DirCopyOptions::new();
+        options.symlinks(SymlinkPolicy::Preserve);
+        let dest = src.copy_recursive_with(tmp_abs.join("dest"), &options).unwrap();
+        let target = fs::read_link(dest.join("absolute")).unwrap();
+        assert_eq!(sub.join("file.txt").as_path(), target.as_path());
+        #[cfg(unix)]
+        {
+            assert_eq!(Path::new("sub"), fs::read_link(dest.join("relative")).unwrap());
+            assert_eq!(Path::new("missing"), fs::read_link(dest.join("broken")).unwrap());
+            let copied = PathFile::new(dest.join("relative/file.txt")).unwrap();
+            assert!(copied.starts_with(dest.as_path()));
+        }
+
+        // A loop is recreated instead of being an error
+        src.symlink(src.join("loop")).unwrap();
+        let dest = src.copy_recursive_with(tmp_abs.join("dest2"), &options).unwrap();
+        assert_eq!(src.as_path(), fs::read_link(dest.join("loop")).unwrap());
+
+        // Existing symlinks are replaced
+        src.copy_recursive_with(&dest, &options).unwrap();
+        options.overwrite(false);
+        let err = src.copy_recursive_with(&dest, &options).unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+    }
+
+    #[test]
+    fn sanity_copy_recursive_skip_symlinks() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let src = PathDir::create(tmp_abs.join("src")).unwrap();
+        let file = PathFile::create_with(src.join("file.txt"), b"file").unwrap();
+        file.symlink(src.join("link.txt")).unwrap();
+        src.symlink(src.join("loop")).unwrap();
+
+        let mut options = DirCopyOptions::new();
+        options.symlinks(SymlinkPolicy::Skip);
+        let dest = src.copy_recursive_with(tmp_abs.join("dest"), &options).unwrap();
+        assert_eq!(vec![("file.txt".to_string(), Some("file".to_string()))], contents(&dest));
+    }
+
+    #[test]
+    fn sanity_copy_recursive_preserve_mtime() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let src = PathDir::create(tmp_abs.join("src")).unwrap();
+        let sub = PathDir::create(src.join("sub")).unwrap();
+        let file = PathFile::create(sub.join("file.txt")).unwrap();
+        let past = SystemTime::now() - Duration::from_secs(60 * 60);
+        let older = past - Duration::from_secs(60 * 60);
+        file.set_modified(older).unwrap();
+        sub.set_modified(past).unwrap();
+        src.set_modified(past).unwrap();
+
+        let dest = src.copy_recursive(tmp_abs.join("dest")).unwrap();
+        assert_ne!(older, PathFile::new(dest.join("sub/file.txt")).unwrap().modified().unwrap());
+
+        let mut options = DirCopyOptions::new();
+        options.preserve_mtime(true);
+        let dest = src.copy_recursive_with(tmp_abs.join("dest2"), &options).unwrap();
+        let copied = PathFile::new(dest.join("sub/file.txt")).unwrap();
+        assert_eq!(older, copied.modified().unwrap());
+        assert_eq!(past, PathDir::new(dest.join("sub")).unwrap().modified().unwrap());
+        assert_eq!(past, dest.modified().unwrap());
+    }
+
+    #[test]
+    fn sanity_copy_recursive_filter() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let src = PathDir::create(tmp_abs.join("src")).unwrap();
+        PathFile::create(src.join("Cargo.toml")).unwrap();
+        PathFile::create_all(src.join("src/lib.rs")).unwrap();
+        PathFile::create_all(src.join("target/debug/out")).unwrap();
+        PathFile::create(src.join("src/lib.rs.orig")).unwrap();
+
+        let seen: Rc<RefCell<Vec<PathBuf>>> = Rc::new(RefCell::new(Vec::new()));
+        let mut options = DirCopyOptions::new();
+        {
+            let seen = seen.clone();
+            options.filter(move |entry| {
+                seen.borrow_mut().push(entry.to_path_buf());
+                let name = entry.file_name().unwrap().to_string_lossy().into_owned();
+                name != "target" && !name.ends_with(".orig")
+            });
+        }
+        let dest = src.copy_recursive_with(tmp_abs.join("dest"), &options).unwrap();
+        let names: Vec<_> = contents(&dest).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(vec!["Cargo.toml", "src", "src/lib.rs"], names);
+
+        // The excluded directory was not descended into
+        let seen = seen.borrow();
+        assert_eq!(5, seen.len());
+        assert!(seen.contains(&src.join("target")));
+        assert!(!seen.iter().any(|p| p.starts_with(src.join("target/debug"))));
+    }
+}
@@ -0,0 +1,194 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Comparing directory trees.
+
+use std::fs;
+use std_prelude::*;
+
+use super::mirror::read_dir;
+use super::{Error, Result};
+use super::{PathAbs, PathArc, PathDir, PathFile};
+
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+/// The differences between two directory trees, returned by `PathDir::diff`.
+///
+/// The paths are relative to both directories, in sorted order.
+pub struct DirDiff {
+    /// Entries which are only in the directory being compared. The contents of directories
+    /// are not listed.
+    pub only_in_self: Vec<PathBuf>,
+    /// Entries which are only in the other directory. The contents of directories are not
+    /// listed.
+    pub only_in_other: Vec<PathBuf>,
+    /// Entries which are in both directories but are of a different type, or are files with
+    /// different contents, or are symlinks with different targets.
+    pub changed: Vec<PathBuf>,
+}
+
+impl DirDiff {
+    /// Return whether the directory trees are the same.
+    pub fn is_empty(&self) -> bool {
+        self.only_in_self.is_empty() && self.only_in_other.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl PathDir {
+    /// Compare the directory tree with `other`.
+    ///
+    /// Files are compared with `PathFile::content_eq`, so their sizes are compared first and
+    /// then their contents are streamed. Symlinks are not followed, they are compared by their
+    /// target. Metadata such as modification times and permissions is ignored.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::path::PathBuf;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// let example_copy = "example_copy";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// # let example_copy = &tmp.path().join(example_copy);
+    /// let dir = PathDir::create(example)?;
+    /// PathFile::create_all(dir.join("a/b.txt"))?.write_str("b")?;
+    /// let copy = dir.copy_recursive(example_copy)?;
+    /// assert!(dir.diff(&copy)?.is_empty());
+    ///
+    /// PathFile::new(copy.join("a/b.txt"))?.write_str("changed")?;
+    /// PathFile::create(copy.join("c.txt"))?;
+    /// let diff = dir.diff(&copy)?;
+    /// assert_eq!(vec![PathBuf::from("a/b.txt")], diff.changed);
+    /// assert_eq!(vec![PathBuf::from("c.txt")], diff.only_in_other);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn diff(&self, other: &PathDir) -> Result<DirDiff> {
+        let mut diff = DirDiff::default();
+        diff_tree(self, other, Path::new(""), &mut diff)?;
+        diff.only_in_self.sort();
+        diff.only_in_other.sort();
+        diff.changed.sort();
+        Ok(diff)
+    }
+}
+
+/// Compare the contents of the directories `a` and `b`, at `rel` in the trees.
+fn diff_tree(a: &Path, b: &Path, rel: &Path, diff: &mut DirDiff) -> Result<()> {
+    let a_entries = read_dir(a, fs::symlink_metadata)?;
+    let mut b_entries = read_dir(b, fs::symlink_metadata)?;
+    for (name, a_meta) in a_entries {
+        let rel = rel.join(&name);
+        let b_meta = match b_entries.remove(&name) {
+            Some(meta) => meta,
+            None => {
+                diff.only_in_self.push(rel);
+                continue;
+            }
+        };
+        let (a, b) = (a.join(&name), b.join(&name));
+        let (a_ty, b_ty) = (a_meta.file_type(), b_meta.file_type());
+        let same = if a_ty.is_dir() && b_ty.is_dir() {
+            diff_tree(&a, &b, &rel, diff)?;
+            true
+        } else if a_ty.is_file() && b_ty.is_file() {
+            let a = PathFile(PathAbs(PathArc::new(a)));
+            a.content_eq(&PathFile(PathAbs(PathArc::new(b))))?
+        } else if a_ty.is_symlink() && b_ty.is_symlink() {
+            read_link(&a)? == read_link(&b)?
+        } else {
+            false
+        };
+        if !same {
+            diff.changed.push(rel);
+        }
+    }
+    for name in b_entries.keys() {
+        diff.only_in_other.push(rel.join(name));
+    }
+    Ok(())
+}
+
+fn read_link(path: &Path) -> Result<PathBuf> {
+    fs::read_link(path).map_err(|err| Error::new(err, "reading link", PathArc::new(path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{PathDir, PathFile};
+    use std::path::PathBuf;
+    use tempdir::TempDir;
+
+    fn paths(paths: &[&str]) -> Vec<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn sanity_diff() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let a = PathDir::create(tmp_abs.join("a")).unwrap();
+        PathFile::create_all(a.join("sub/same.txt")).unwrap().write_str("same").unwrap();
+        PathFile::create(a.join("changed.txt")).unwrap().write_str("a").unwrap();
+        PathFile::create(a.join("longer.txt")).unwrap().write_str("a").unwrap();
+        PathFile::create(a.join("type")).unwrap();
+        PathDir::create(a.join("empty")).unwrap();
+
+        // Identical trees
+        let copy = a.copy_recursive(tmp_abs.join("copy")).unwrap();
+        assert!(a.diff(&copy).unwrap().is_empty());
+        let mirror = tmp_abs.join("mirror");
+        a.mirror(&mirror).unwrap();
+        assert!(a.diff(&PathDir::new(mirror).unwrap()).unwrap().is_empty());
+        assert!(a.diff(&a).unwrap().is_empty());
+
+        let b = PathDir::create(tmp_abs.join("b")).unwrap();
+        PathFile::create_all(b.join("sub/same.txt")).unwrap().write_str("same").unwrap();
+        PathFile::create(b.join("sub/other.txt")).unwrap();
+        PathFile::create(b.join("changed.txt")).unwrap().write_str("b").unwrap();
+        PathFile::create(b.join("longer.txt")).unwrap().write_str("ab").unwrap();
+        PathDir::create(b.join("type")).unwrap();
+        PathFile::create_all(b.join("only/nested.txt")).unwrap();
+
+        let diff = a.diff(&b).unwrap();
+        assert_eq!(paths(&["empty"]), diff.only_in_self);
+        assert_eq!(paths(&["only", "sub/other.txt"]), diff.only_in_other);
+        assert_eq!(paths(&["changed.txt", "longer.txt", "type"]), diff.changed);
+
+        let reverse = b.diff(&a).unwrap();
+        assert_eq!(diff.only_in_self, reverse.only_in_other);
+        assert_eq!(diff.only_in_other, reverse.only_in_self);
+        assert_eq!(diff.changed, reverse.changed);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sanity_diff_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let a = PathDir::create(tmp_abs.join("a")).unwrap();
+        let b = PathDir::create(tmp_abs.join("b")).unwrap();
+        for dir in &[&a, &b] {
+            PathFile::create(dir.join("target")).unwrap();
+            symlink("target", dir.join("same")).unwrap();
+            symlink("missing", dir.join("broken")).unwrap();
+        }
+        symlink("target", a.join("changed")).unwrap();
+        symlink("broken", b.join("changed")).unwrap();
+        symlink("target", a.join("type")).unwrap();
+        PathFile::create(b.join("type")).unwrap();
+
+        let diff = a.diff(&b).unwrap();
+        assert!(diff.only_in_self.is_empty());
+        assert!(diff.only_in_other.is_empty());
+        assert_eq!(paths(&["changed", "type"]), diff.changed);
+    }
+}
@@ -6,22 +6,50 @@
  * copied, modified, or distributed except according to those terms.
  */
 //! Paths to Directories and associated methods.
+use std::error;
+use std::ffi::OsStr;
 use std::fs;
 use std::fmt;
 use std::io;
+use std::path::Component;
 use std_prelude::*;
 
-use super::{Error, Result};
-use super::{PathAbs, PathArc, PathType};
+use super::{DirCopyOptions, Error, Result, SymlinkPolicy};
+use super::{PathAbs, PathArc, PathFile, PathType};
+use super::file::{is_a_directory, is_cross_device, temp_name};
 
 #[derive(Clone, Eq, Hash, PartialEq, PartialOrd, Ord)]
 /// A `PathAbs` that is guaranteed to be a directory, with associated methods.
 pub struct PathDir(pub(crate) PathAbs);
 
+/// The error returned by `PathDir::list_vec` and `PathDir::walk_vec` when some entries could
+/// not be read, which also gives back the entries which were read.
+pub struct ListError {
+    entries: Vec<PathType>,
+    errors: Vec<Error>,
+}
+
+/// The error returned by recursive operations which continue after an entry fails (i.e.
+/// `PathDir::chown_recursive`), with the errors of all of the entries which failed.
+pub struct RecursiveError {
+    errors: Vec<Error>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+/// What `PathDir::list_opts` does with an entry it fails to read or resolve.
+pub enum ErrorPolicy {
+    /// Yield the error, like `PathDir::list`.
+    Fail,
+    /// Silently skip the entry.
+    Skip,
+    /// Skip the entry, keeping the error so it can be reported with `ListDir::errors`.
+    Collect,
+}
+
 impl PathDir {
     /// Instantiate a new `PathDir`. The directory must exist or `io::Error` will be returned.
     ///
-    /// Returns `io::ErrorKind::InvalidInput` if the path exists but is not a directory.
+    /// Returns `io::ErrorKind::NotADirectory` if the path exists but is not a directory.
     ///
     /// # Examples
     /// ```rust
@@ -59,7 +87,7 @@ impl PathDir {
     /// Consume the `PathAbs` validating that the path is a directory and returning `PathDir`. The
     /// directory must exist or `io::Error` will be returned.
     ///
-    /// If the path is actually a file returns `io::ErrorKind::InvalidInput`.
+    /// If the path is actually a file returns `io::ErrorKind::NotADirectory`.
     ///
     /// # Examples
     /// ```rust
@@ -74,6 +102,8 @@ impl PathDir {
     pub fn from_abs(abs: PathAbs) -> Result<PathDir> {
         if abs.is_dir() {
             Ok(PathDir::from_abs_unchecked(abs))
+        } else if abs.exists() {
+            Err(Error::new(not_a_directory(), "resolving", abs.into()))
         } else {
             Err(Error::new(
                 io::Error::new(io::ErrorKind::InvalidInput, "path is not a dir"),
@@ -94,6 +124,8 @@ impl PathDir {
 
     /// Instantiate a new `PathDir` to a directory, creating the directory if it doesn't exist.
     ///
+    /// Returns `io::ErrorKind::NotADirectory` if the path exists but is not a directory.
+    ///
     /// # Examples
     /// ```rust
     /// # extern crate path_abs;
@@ -114,6 +146,9 @@ impl PathDir {
     pub fn create<P: AsRef<Path>>(path: P) -> Result<PathDir> {
         if let Err(err) = fs::create_dir(&path) {
             match err.kind() {
+                io::ErrorKind::AlreadyExists if !path.as_ref().is_dir() => {
+                    return Err(Error::new(not_a_directory(), "creating", PathArc::new(path)))
+                }
                 io::ErrorKind::AlreadyExists => {}
                 _ => return Err(Error::new(err, "creating", PathArc::new(path))),
             }
@@ -147,8 +182,137 @@ impl PathDir {
         PathDir::new(path)
     }
 
+    /// Instantiate a new `PathDir` to an empty directory, creating it and its parents like
+    /// `create_all` if they are missing.
+    ///
+    /// If the directory already has entries they are removed like `remove_contents` when
+    /// `wipe` is true. Otherwise this returns `io::ErrorKind::DirectoryNotEmpty` with the names
+    /// of the first few entries in the message.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::io;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example/out";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    ///
+    /// let out = PathDir::ensure_empty(example, false)?;
+    /// PathFile::create(out.join("old.txt"))?;
+    ///
+    /// let err = PathDir::ensure_empty(example, false).unwrap_err();
+    /// assert_eq!(io::ErrorKind::DirectoryNotEmpty, err.io_error().kind());
+    /// assert!(err.to_string().contains("old.txt"));
+    ///
+    /// let out = PathDir::ensure_empty(example, true)?;
+    /// assert!(out.is_empty()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn ensure_empty<P: AsRef<Path>>(path: P, wipe: bool) -> Result<PathDir> {
+        const SHOWN: usize = 3;
+        let dir = PathDir::create_all(path)?;
+        if wipe {
+            dir.remove_contents()?;
+            return Ok(dir);
+        }
+        let mut names = fs::read_dir(&dir)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| entry.map(|e| e.file_name()))
+                    .collect::<io::Result<Vec<_>>>()
+            })
+            .map_err(|err| Error::new(err, "reading dir", dir.clone().into()))?;
+        if names.is_empty() {
+            return Ok(dir);
+        }
+        names.sort();
+        let shown: Vec<_> = names.iter().take(SHOWN).map(|n| n.to_string_lossy()).collect();
+        let mut msg = format!("directory is not empty, it contains {}", shown.join(", "));
+        if names.len() > SHOWN {
+            msg.push_str(&format!(" and {} more", names.len() - SHOWN));
+        }
+        let err = io::Error::new(io::ErrorKind::DirectoryNotEmpty, msg);
+        Err(Error::new(err, "ensuring empty", dir.into()))
+    }
+
+    #[cfg(unix)]
+    /// Instantiate a new `PathDir` like `create`, creating the directory with the unix
+    /// permission bits `mode` (i.e. `0o700`), masked by the process's umask.
+    ///
+    /// The mode is set when the directory is created, so there is no moment at which it has
+    /// other permissions. An existing directory is not changed.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathDir;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    ///
+    /// let dir = PathDir::create_with_mode(example, 0o700)?;
+    /// assert_eq!(0o700, dir.mode()? & 0o7777);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn create_with_mode<P: AsRef<Path>>(path: P, mode: u32) -> Result<PathDir> {
+        use std::os::unix::fs::DirBuilderExt;
+        if let Err(err) = fs::DirBuilder::new().mode(mode).create(&path) {
+            match err.kind() {
+                io::ErrorKind::AlreadyExists if !path.as_ref().is_dir() => {
+                    return Err(Error::new(not_a_directory(), "creating", PathArc::new(path)))
+                }
+                io::ErrorKind::AlreadyExists => {}
+                _ => return Err(Error::new(err, "creating", PathArc::new(path))),
+            }
+        }
+        PathDir::new(path)
+    }
+
+    #[cfg(unix)]
+    /// Instantiate a new `PathDir` like `create_all`, creating the missing directories with the
+    /// unix permission bits `mode`, masked by the process's umask.
+    ///
+    /// Every missing parent is also created with `mode`, so they are no more accessible than
+    /// the directory itself. Existing directories are not changed.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathDir;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example/private/state";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    ///
+    /// let dir = PathDir::create_all_with_mode(example, 0o700)?;
+    /// assert_eq!(0o700, dir.mode()? & 0o7777);
+    /// assert_eq!(0o700, dir.parent_dir().unwrap().mode()? & 0o7777);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn create_all_with_mode<P: AsRef<Path>>(path: P, mode: u32) -> Result<PathDir> {
+        use std::os::unix::fs::DirBuilderExt;
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(mode)
+            .create(&path)
+            .map_err(|err| Error::new(err, "creating-all", PathArc::new(&path)))?;
+        PathDir::new(path)
+    }
+
     /// Join a path onto the `PathDir`, expecting it to exist. Returns the resulting `PathType`.
     ///
+    /// The path may be absolute or contain `..`, so the result is not necessarily inside of the
+    /// directory. Use `join_contained` to resolve untrusted paths.
+    ///
     /// # Examples
     /// ```rust
     /// # extern crate path_abs;
@@ -165,365 +329,2764 @@ impl PathDir {
         PathType::new(joined)
     }
 
-    /// List the contents of the directory, returning an iterator of `PathType`s.
+    /// Join a path onto the `PathDir` like `join_abs`, failing if the result is not inside of
+    /// the directory.
+    ///
+    /// The joined path is canonicalized, so it must exist and `..` and symlinks are resolved
+    /// before checking that it is inside of (or is) the directory. This makes it suitable for
+    /// resolving user provided paths against a root. Returns `io::ErrorKind::PermissionDenied`
+    /// for the path of `self` if the result escapes the directory. The returned `PathType` is
+    /// canonical.
     ///
     /// # Examples
     /// ```rust
     /// # extern crate path_abs;
     /// # extern crate tempdir;
-    /// use std::collections::HashSet;
-    /// use path_abs::{PathDir, PathFile, PathType};
+    /// use std::io;
+    /// use path_abs::{PathDir, PathFile};
     ///
     /// # fn try_main() -> ::std::io::Result<()> {
     /// let example = "example";
     /// # let tmp = tempdir::TempDir::new("ex")?;
-    /// # let example = &tmp.path().join("example");
+    /// # let example = &tmp.path().join(example);
+    /// let project = PathDir::create(example)?;
+    /// PathFile::create(project.join("Cargo.toml"))?;
+    /// let src = PathDir::create(project.join("src"))?;
+    /// PathFile::create(src.join("lib.rs"))?;
     ///
-    /// let example_dir = PathDir::create(example)?;
-    /// let foo_dir = PathDir::create(example_dir.join("foo"))?;
-    /// let bar_file = PathFile::create(example_dir.join("bar.txt"))?;
+    /// let lib = src.join_contained("../src/lib.rs")?.unwrap_file();
+    /// assert!(lib.ends_with("src/lib.rs"));
     ///
-    /// let mut result = HashSet::new();
-    /// for p in example_dir.list()? {
-    ///     result.insert(p?);
-    /// }
+    /// let err = src.join_contained("../Cargo.toml").unwrap_err();
+    /// assert_eq!(io::ErrorKind::PermissionDenied, err.io_error().kind());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn join_contained<P: AsRef<Path>>(&self, path: P) -> Result<PathType> {
+        let path = path.as_ref();
+        let action = format!("resolving {} in", path.display());
+        let joined = self.join(path);
+        let resolved =
+            fs::canonicalize(&joined).map_err(|err| Error::new(err, &action, joined.clone()))?;
+        let root = self.canonicalize()?;
+        if !resolved.starts_with(&root) {
+            return Err(Error::new(
+                io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "path resolves outside of the directory",
+                ),
+                &action,
+                self.clone().into(),
+            ));
+        }
+        PathType::new(resolved)
+    }
+
+    /// Return whether `path` is the directory or is inside of it.
     ///
-    /// let mut expected = HashSet::new();
-    /// expected.insert(PathType::Dir(foo_dir));
-    /// expected.insert(PathType::File(bar_file));
+    /// Both paths are canonicalized, so `path` must exist and `..` and symlinks can't make a
+    /// path which is outside appear to be inside. Paths are compared by component, so
+    /// `/foo/barbaz` is not inside of `/foo/bar`. Relative paths are relative to the current
+    /// directory, like `PathAbs::new`.
     ///
-    /// assert_eq!(expected, result);
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let project = PathDir::create(example)?;
+    /// PathFile::create(project.join("Cargo.toml"))?;
+    /// let src = PathDir::create(project.join("src"))?;
+    /// let lib = PathFile::create(src.join("lib.rs"))?;
+    ///
+    /// assert!(src.contains(&lib)?);
+    /// assert!(src.contains(&src)?);
+    /// assert!(!src.contains(src.join("../Cargo.toml"))?);
     /// # Ok(()) } fn main() { try_main().unwrap() }
-    pub fn list(&self) -> Result<ListDir> {
-        let fsread =
-            fs::read_dir(self).map_err(|err| Error::new(err, "reading dir", self.clone().into()))?;
-        Ok(ListDir {
-            dir: self.clone(),
-            fsread: fsread,
-        })
+    /// ```
+    pub fn contains<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        let path = PathArc::new(path).canonicalize()?;
+        Ok(path.starts_with(self.canonicalize()?))
     }
 
-    /// Remove (delete) the _empty_ directory from the filesystem, consuming self.
+    /// Return whether `path` is the directory or is inside of it, without accessing the
+    /// filesystem.
+    ///
+    /// `path` is made absolute with `PathAbs::new`, which resolves `..` without following
+    /// symlinks, so it doesn't have to exist. Unlike `contains`, symlinks inside of the
+    /// directory can point outside of it.
     ///
     /// # Examples
     /// ```rust
     /// # extern crate path_abs;
     /// # extern crate tempdir;
-    /// use std::path::Path;
     /// use path_abs::PathDir;
     ///
     /// # fn try_main() -> ::std::io::Result<()> {
-    /// let example = Path::new("example/long/path");
+    /// let example = "example/src";
     /// # let tmp = tempdir::TempDir::new("ex")?;
     /// # let example = &tmp.path().join(example);
+    /// let src = PathDir::create_all(example)?;
     ///
-    /// let dir = PathDir::create_all(example)?;
-    /// let parent = dir.parent_dir().unwrap();
-    ///
-    /// assert!(example.exists());
-    /// dir.remove()?;
-    /// // assert!(dir.exists());  <--- COMPILE ERROR
-    /// assert!(!example.exists());
-    /// parent.remove()?;
+    /// assert!(src.contains_lexical(src.join("new/file.rs"))?);
+    /// assert!(!src.contains_lexical(src.join("../new.rs"))?);
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    pub fn remove(self) -> Result<()> {
-        fs::remove_dir(&self).map_err(|err| Error::new(err, "removing", self.into()))
+    pub fn contains_lexical<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        Ok(PathAbs::new(path)?.starts_with(self))
     }
 
-    /// Remove (delete) the directory, after recursively removing its contents. Use carefully!
+    /// Get the file `name` inside of the directory.
+    ///
+    /// `name` must be a single path component, otherwise `io::ErrorKind::InvalidInput` is
+    /// returned. Returns `io::ErrorKind::NotFound` if the file doesn't exist and
+    /// `io::ErrorKind::IsADirectory` if it is a directory. Errors are for the path of `self`,
+    /// with `name` in their action.
     ///
     /// # Examples
     /// ```rust
     /// # extern crate path_abs;
     /// # extern crate tempdir;
-    /// use std::path::Path;
-    /// use path_abs::PathDir;
+    /// use std::io;
+    /// use path_abs::{PathDir, PathFile};
     ///
     /// # fn try_main() -> ::std::io::Result<()> {
-    /// let example = Path::new("example/long/path");
+    /// let example = "example";
     /// # let tmp = tempdir::TempDir::new("ex")?;
     /// # let example = &tmp.path().join(example);
+    /// let src = PathDir::create(example)?;
+    /// PathFile::create(src.join("lib.rs"))?;
     ///
-    /// let dir = PathDir::create_all(example)?;
-    /// let parent = dir.parent_dir().unwrap();
+    /// let lib = src.get_file("lib.rs")?;
+    /// assert!(lib.is_file());
     ///
-    /// assert!(example.exists());
-    /// parent.remove_all()?;
-    /// assert!(!example.exists());
+    /// let err = src.get_file("missing.rs").unwrap_err();
+    /// assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    pub fn remove_all(self) -> Result<()> {
-        fs::remove_dir_all(&self).map_err(|err| Error::new(err, "removing-all", self.into()))
+    pub fn get_file<S: AsRef<OsStr>>(&self, name: S) -> Result<PathFile> {
+        let name = Path::new(name.as_ref());
+        let action = format!("getting file {} in", name.display());
+        let path = self.child(name, &action)?;
+        match fs::metadata(&path) {
+            Ok(ref meta) if meta.is_dir() => {
+                Err(Error::new(is_a_directory(), &action, self.clone().into()))
+            }
+            Ok(_) => Ok(PathFile(PathAbs(path))),
+            Err(err) => Err(Error::new(err, &action, self.clone().into())),
+        }
     }
 
-    /// Creates a new symbolic link on the filesystem to the dst.
+    /// Get the directory `name` inside of the directory.
     ///
-    /// This handles platform specific behavior correctly.
+    /// `name` must be a single path component, otherwise `io::ErrorKind::InvalidInput` is
+    /// returned. Returns `io::ErrorKind::NotFound` if the directory doesn't exist and
+    /// `io::ErrorKind::NotADirectory` if it is not a directory. Errors are for the path of
+    /// `self`, with `name` in their action.
     ///
     /// # Examples
-    ///
     /// ```rust
     /// # extern crate path_abs;
     /// # extern crate tempdir;
+    /// use std::io;
     /// use path_abs::{PathDir, PathFile};
-    /// use std::path::Path;
     ///
     /// # fn try_main() -> ::std::io::Result<()> {
     /// let example = "example";
-    /// let example_sym = "example_sym";
     /// # let tmp = tempdir::TempDir::new("ex")?;
     /// # let example = &tmp.path().join(example);
-    /// # let example_sym = &tmp.path().join(example_sym);
-    /// let dir = PathDir::create(example)?;
-    /// let file = PathFile::create(dir.join("example.txt"))?;
-    ///
-    /// let dir_sym = dir.symlink(example_sym)?;
-    ///
-    /// // They have a different "absolute path"
-    /// assert_ne!(dir, dir_sym);
+    /// let project = PathDir::create(example)?;
+    /// PathDir::create(project.join("src"))?;
+    /// PathFile::create(project.join("Cargo.toml"))?;
     ///
-    /// // But they can be canonicalized to the same file.
-    /// let dir_can = dir_sym.canonicalize()?;
-    /// assert_eq!(dir, dir_can);
+    /// let src = project.get_dir("src")?;
+    /// assert!(src.is_dir());
     ///
+    /// let err = project.get_dir("Cargo.toml").unwrap_err();
+    /// assert_eq!(io::ErrorKind::NotADirectory, err.io_error().kind());
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    pub fn symlink<P: AsRef<Path>>(&self, dst: P) -> Result<PathDir> {
-        symlink_dir(&self, &dst).map_err(|err| {
-            Error::new(
-                err,
-                &format!("linking from {} to", dst.as_ref().display()),
-                self.clone().into(),
-            )
-        })?;
-        PathDir::new(dst)
-    }
-
-    /// Return a reference to a basic `std::path::Path`
-    pub fn as_path(&self) -> &Path {
-        self.as_ref()
+    pub fn get_dir<S: AsRef<OsStr>>(&self, name: S) -> Result<PathDir> {
+        let name = Path::new(name.as_ref());
+        let action = format!("getting directory {} in", name.display());
+        let path = self.child(name, &action)?;
+        match fs::metadata(&path) {
+            Ok(ref meta) if meta.is_dir() => Ok(PathDir(PathAbs(path))),
+            Ok(_) => Err(Error::new(not_a_directory(), &action, self.clone().into())),
+            Err(err) => Err(Error::new(err, &action, self.clone().into())),
+        }
     }
 
-    /// Returns the canonical form of the path with all intermediate components normalized and
-    /// symbolic links resolved.
+    /// Create the file `name` inside of the directory like `PathFile::create`.
     ///
-    /// See [`PathAbs::canonicalize`]
+    /// `name` is a relative path which can't be absolute or contain `..`, so the file can't be
+    /// created outside of the directory by accident. Otherwise `io::ErrorKind::InvalidInput` is
+    /// returned for the path of `self`. Symlinks inside the directory are still followed.
     ///
-    /// [`PathAbs::canonicalize`]: struct.PathAbs.html#method.canonicalize
-    pub fn canonicalize(&self) -> Result<PathDir> {
-        Ok(PathDir(self.0.canonicalize()?))
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::io;
+    /// use path_abs::PathDir;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let project = PathDir::create(example)?;
+    /// let src = project.create_dir("src")?;
+    /// let lib = src.create_file("lib.rs")?;
+    /// assert_eq!(project.join("src/lib.rs").as_path(), lib.as_path());
+    ///
+    /// let err = src.create_file("../main.rs").unwrap_err();
+    /// assert_eq!(io::ErrorKind::InvalidInput, err.io_error().kind());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn create_file<S: AsRef<OsStr>>(&self, name: S) -> Result<PathFile> {
+        let name = Path::new(name.as_ref());
+        let path = self.contained(name, &format!("creating file {} in", name.display()))?;
+        PathFile::create(path)
     }
 
-    /// Get the parent directory of this directory as a `PathDir`.
+    /// Create the directory `name` inside of the directory like `PathDir::create`.
     ///
-    /// > This does not make aditional syscalls, as the parent by definition must be a directory
-    /// > and exist.
+    /// `name` is checked like for `create_file`.
     ///
     /// # Examples
     /// ```rust
     /// # extern crate path_abs;
+    /// # extern crate tempdir;
     /// use path_abs::PathDir;
     ///
     /// # fn try_main() -> ::std::io::Result<()> {
-    /// let src = PathDir::new("src")?;
-    /// let proj = src.parent_dir().unwrap();
-    /// assert_eq!(PathDir::new("src/..")?, proj);
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let project = PathDir::create(example)?;
+    /// let src = project.create_dir("src")?;
+    /// assert_eq!(project.join("src").as_path(), src.as_path());
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    pub fn parent_dir(&self) -> Option<PathDir> {
-        match self.parent() {
-            Some(path) => Some(PathDir(PathAbs(PathArc::new(path)))),
-            None => None,
-        }
+    pub fn create_dir<S: AsRef<OsStr>>(&self, name: S) -> Result<PathDir> {
+        let name = Path::new(name.as_ref());
+        let path = self.contained(name, &format!("creating directory {} in", name.display()))?;
+        PathDir::create(path)
     }
 
-    /// Create a mock dir type. *For use in tests only*.
+    /// Create the directory `name` and all of its missing parents inside of the directory like
+    /// `PathDir::create_all`.
     ///
-    /// See the docs for [`PathAbs::mock`](struct.PathAbs.html#method.mock)
-    pub fn mock<P: AsRef<Path>>(path: P) -> PathDir {
-        PathDir(PathAbs::mock(path))
+    /// `name` is checked like for `create_file`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathDir;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let project = PathDir::create(example)?;
+    /// let tests = project.create_dir_all("src/tests")?;
+    /// assert_eq!(project.join("src/tests").as_path(), tests.as_path());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn create_dir_all<S: AsRef<OsStr>>(&self, name: S) -> Result<PathDir> {
+        let name = Path::new(name.as_ref());
+        let path = self.contained(name, &format!("creating-all {} in", name.display()))?;
+        PathDir::create_all(path)
     }
-}
-
-/// An iterator over `PathType` objects, returned by `PathDir::list`.
-pub struct ListDir {
-    // TODO: this should be a reference...?
-    // Or is this a good excuse to use Arc under the hood everywhere?
-    dir: PathDir,
-    fsread: fs::ReadDir,
-}
 
-impl ::std::iter::Iterator for ListDir {
-    type Item = Result<PathType>;
-    fn next(&mut self) -> Option<Result<PathType>> {
-        let entry = match self.fsread.next() {
-            Some(r) => match r {
-                Ok(e) => e,
-                Err(err) => {
-                    return Some(Err(Error::new(
-                        err,
-                        "iterating over",
-                        self.dir.clone().into(),
-                    )))
-                }
-            },
-            None => return None,
-        };
-        Some(PathType::new(entry.path()))
+    /// Join `name` onto the directory, checking that it is a single path component.
+    fn child(&self, name: &Path, action: &str) -> Result<PathArc> {
+        let mut components = name.components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => Ok(self.join(name)),
+            _ => Err(Error::new(
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "name is not a single path component",
+                ),
+                action,
+                self.clone().into(),
+            )),
+        }
     }
-}
 
-impl fmt::Debug for PathDir {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.0.fmt(f)
+    /// Join the relative `name` onto the directory, checking that it stays inside of it.
+    fn contained(&self, name: &Path, action: &str) -> Result<PathArc> {
+        let contained = name
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+        if contained && name.file_name().is_some() {
+            Ok(self.join(name))
+        } else {
+            Err(Error::new(
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "name is not a path inside of the directory",
+                ),
+                action,
+                self.clone().into(),
+            ))
+        }
     }
-}
 
-impl AsRef<PathAbs> for PathDir {
-    fn as_ref(&self) -> &PathAbs {
-        &self.0
+    /// List the contents of the directory, returning an iterator of `PathType`s.
+    ///
+    /// The type of each entry is read along with the listing, only symlinks are followed
+    /// (with another syscall) to find the type of their target. A symlink keeps the path of
+    /// the link.
+    ///
+    /// # Errors
+    ///
+    /// The errors have the path of the directory if it can't be read (i.e. it was removed or
+    /// is not readable), or the path of the entry if the entry can't be resolved. The
+    /// `io::ErrorKind` is kept, also when the error is converted into an `io::Error`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::collections::HashSet;
+    /// use path_abs::{PathDir, PathFile, PathType};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join("example");
+    ///
+    /// let example_dir = PathDir::create(example)?;
+    /// let foo_dir = PathDir::create(example_dir.join("foo"))?;
+    /// let bar_file = PathFile::create(example_dir.join("bar.txt"))?;
+    ///
+    /// let mut result = HashSet::new();
+    /// for p in example_dir.list()? {
+    ///     result.insert(p?);
+    /// }
+    ///
+    /// let mut expected = HashSet::new();
+    /// expected.insert(PathType::Dir(foo_dir));
+    /// expected.insert(PathType::File(bar_file));
+    ///
+    /// assert_eq!(expected, result);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    pub fn list(&self) -> Result<ListDir> {
+        self.list_opts(ErrorPolicy::Fail)
+    }
+
+    /// List the contents of the directory like `list`, handling per-entry errors according to
+    /// `policy`.
+    ///
+    /// An entry can fail if it is removed while listing or if it can't be resolved, e.g. a
+    /// broken symlink. `ErrorPolicy::Skip` and `ErrorPolicy::Collect` keep going past such
+    /// entries, which is useful when scanning directories that other processes are changing.
+    /// Failing to open the directory is always an error.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{ErrorPolicy, PathDir, PathFile, PathType};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// let file = PathFile::create(dir.join("file.txt"))?;
+    ///
+    /// let mut list = dir.list_opts(ErrorPolicy::Collect)?;
+    /// let entries = list.by_ref().collect::<Result<Vec<_>, _>>()?;
+    /// assert_eq!(vec![PathType::File(file)], entries);
+    /// assert!(list.errors().is_empty());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn list_opts(&self, policy: ErrorPolicy) -> Result<ListDir> {
+        let fsread =
+            fs::read_dir(self).map_err(|err| Error::new(err, "reading dir", self.clone().into()))?;
+        Ok(ListDir {
+            dir: self.clone(),
+            fsread: fsread,
+            policy,
+            errors: Vec::new(),
+            skip_hidden: false,
+        })
+    }
+
+    /// List the contents of the directory like `list`, skipping hidden entries.
+    ///
+    /// An entry is hidden if its file name starts with a `.` or, on Windows, if it has the
+    /// hidden attribute.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile, PathType};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let project = PathDir::create(example)?;
+    /// let readme = PathFile::create(project.join("README.md"))?;
+    /// PathDir::create(project.join(".git"))?;
+    ///
+    /// let entries = project.list_visible()?.collect::<Result<Vec<_>, _>>()?;
+    /// assert_eq!(vec![PathType::File(readme)], entries);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn list_visible(&self) -> Result<ListDir> {
+        let mut list = self.list()?;
+        list.skip_hidden = true;
+        Ok(list)
+    }
+
+    /// List the contents of the directory like `list`, sorted by their file names.
+    ///
+    /// The names are compared as `OsStr`, which is byte-wise (so `B` sorts before `a`). The
+    /// first error while listing is returned.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile, PathType};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// let lower = PathFile::create(dir.join("a.txt"))?;
+    /// let upper = PathDir::create(dir.join("B"))?;
+    ///
+    /// assert_eq!(vec![PathType::Dir(upper), PathType::File(lower)], dir.list_sorted()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn list_sorted(&self) -> Result<Vec<PathType>> {
+        let mut entries = self.list()?.collect::<Result<Vec<_>>>()?;
+        entries.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+        Ok(entries)
+    }
+
+    /// List the files in the directory like `list`, skipping directories.
+    ///
+    /// Errors are the same as those of `list`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::collections::HashSet;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let project = PathDir::create(example)?;
+    /// PathDir::create(project.join("src"))?;
+    /// let cargo = PathFile::create(project.join("Cargo.toml"))?;
+    /// let readme = PathFile::create(project.join("README.md"))?;
+    ///
+    /// let files = project.list_files()?.collect::<Result<HashSet<_>, _>>()?;
+    /// assert_eq!(vec![cargo, readme].into_iter().collect::<HashSet<_>>(), files);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn list_files(&self) -> Result<ListFiles> {
+        Ok(ListFiles(self.list()?))
+    }
+
+    /// List the directories in the directory like `list`, skipping files.
+    ///
+    /// Errors are the same as those of `list`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let project = PathDir::create(example)?;
+    /// let src = PathDir::create(project.join("src"))?;
+    /// PathFile::create(project.join("Cargo.toml"))?;
+    ///
+    /// let dirs = project.list_dirs()?.collect::<Result<Vec<_>, _>>()?;
+    /// assert_eq!(vec![src], dirs);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn list_dirs(&self) -> Result<ListDirs> {
+        Ok(ListDirs(self.list()?))
+    }
+
+    /// List the contents of the directory like `list`, along with their paths relative to the
+    /// directory (which are just their file names).
+    ///
+    /// Use `walk_rel` for the relative paths of a whole tree.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::path::PathBuf;
+    /// use path_abs::{PathDir, PathFile, PathType};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let project = PathDir::create(example)?;
+    /// let readme = PathFile::create(project.join("README.md"))?;
+    ///
+    /// let entries = project.list_rel()?.collect::<Result<Vec<_>, _>>()?;
+    /// assert_eq!(PathType::File(readme), entries[0].abs);
+    /// assert_eq!(PathBuf::from("README.md"), entries[0].rel);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn list_rel(&self) -> Result<ListRel> {
+        Ok(ListRel(self.list()?))
+    }
+
+    /// List the contents of the directory like `list`, along with their metadata.
+    ///
+    /// The metadata is read from the directory entry, so it is read once per entry rather than
+    /// again with `metadata()`. For symlinks it is the metadata of their target.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// PathFile::create(dir.join("file.txt"))?.write_str("contents")?;
+    ///
+    /// let entries = dir.list_meta()?.collect::<Result<Vec<_>, _>>()?;
+    /// assert!(entries[0].abs.is_file());
+    /// assert_eq!(8, entries[0].metadata.len());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn list_meta(&self) -> Result<ListMeta> {
+        Ok(ListMeta(self.list()?))
+    }
+
+    /// List the contents of the directory like `list`, collecting them into a `Vec`.
+    ///
+    /// Listing continues after an entry can't be read. If any entry failed (or the directory
+    /// couldn't be read) a `ListError` is returned with all of the errors and the entries which
+    /// were read, so the caller can decide whether to use them.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile, PathType};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// let file = PathFile::create(dir.join("file.txt"))?;
+    ///
+    /// assert_eq!(vec![PathType::File(file)], dir.list_vec()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn list_vec(&self) -> ::std::result::Result<Vec<PathType>, ListError> {
+        let list = match self.list_opts(ErrorPolicy::Fail) {
+            Ok(list) => list,
+            Err(err) => {
+                return Err(ListError::new(Vec::new(), vec![err]));
+            }
+        };
+        collect_vec(list)
+    }
+
+    /// Return whether the directory has no entries.
+    ///
+    /// This reads at most one entry, so it is cheap even for huge directories.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// assert!(dir.is_empty()?);
+    ///
+    /// PathFile::create(dir.join("file.txt"))?;
+    /// assert!(!dir.is_empty()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn is_empty(&self) -> Result<bool> {
+        let mut fsread =
+            fs::read_dir(self).map_err(|err| Error::new(err, "reading dir", self.clone().into()))?;
+        match fsread.next() {
+            None => Ok(true),
+            Some(Ok(_)) => Ok(false),
+            Some(Err(err)) => Err(Error::new(err, "iterating over", self.clone().into())),
+        }
+    }
+
+    /// Remove (delete) the _empty_ directory from the filesystem, consuming self.
+    ///
+    /// If the directory is not empty this returns `io::ErrorKind::DirectoryNotEmpty` with the
+    /// same message on all platforms.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::io;
+    /// use std::path::Path;
+    /// use path_abs::PathDir;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = Path::new("example/long/path");
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    ///
+    /// let dir = PathDir::create_all(example)?;
+    /// let parent = dir.parent_dir().unwrap();
+    ///
+    /// assert!(example.exists());
+    /// dir.remove()?;
+    /// // assert!(dir.exists());  <--- COMPILE ERROR
+    /// assert!(!example.exists());
+    /// assert!(PathDir::new(example).is_err());
+    ///
+    /// // The parent still contains "path"
+    /// PathDir::create(parent.join("path"))?;
+    /// let err = parent.clone().remove().unwrap_err();
+    /// assert_eq!(io::ErrorKind::DirectoryNotEmpty, err.io_error().kind());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn remove(self) -> Result<()> {
+        fs::remove_dir(&self).map_err(|err| {
+            // Depending on the platform this can also be `AlreadyExists` or have an unhelpful
+            // message, so normalize it. Other errors are returned unchanged.
+            let err = if is_not_empty(&err) {
+                io::Error::new(io::ErrorKind::DirectoryNotEmpty, "directory is not empty")
+            } else {
+                err
+            };
+            Error::new(err, "removing", self.into())
+        })
+    }
+
+    /// Remove (delete) the directory, after recursively removing its contents. Use carefully!
+    ///
+    /// Symlinks are removed, not followed. If an entry inside the directory can't be removed
+    /// then the error has the path of that entry.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::path::Path;
+    /// use path_abs::PathDir;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = Path::new("example/long/path");
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    ///
+    /// let dir = PathDir::create_all(example)?;
+    /// let parent = dir.parent_dir().unwrap();
+    ///
+    /// assert!(example.exists());
+    /// parent.remove_all()?;
+    /// assert!(!example.exists());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn remove_all(self) -> Result<()> {
+        self.remove_all_with_progress(|_| {})
+    }
+
+    /// Remove (delete) the directory and its contents like `remove_all`, calling `progress`
+    /// with the path of each entry after it is removed.
+    ///
+    /// The contents of a directory are removed before the directory itself, which is the last
+    /// path `progress` is called with. The first error is returned, with the path of the entry
+    /// which failed.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let cache = PathDir::create(example)?;
+    /// PathFile::create_all(cache.join("a/b.txt"))?;
+    ///
+    /// let mut plan = cache.remove_all_plan()?;
+    /// let mut removed = Vec::new();
+    /// cache.remove_all_with_progress(|path| removed.push(path.to_path_buf()))?;
+    /// assert_eq!(3, removed.len());
+    ///
+    /// plan.sort();
+    /// removed.sort();
+    /// assert_eq!(plan, removed);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn remove_all_with_progress<F: FnMut(&Path)>(self, mut progress: F) -> Result<()> {
+        remove_contents(&self, &mut progress)?;
+        fs::remove_dir(&self).map_err(|err| Error::new(err, "removing-all", self.clone().into()))?;
+        progress(&self);
+        Ok(())
+    }
+
+    /// Return the paths which `remove_all` would remove, without removing anything.
+    ///
+    /// The paths are in removal order: the contents of each directory before the directory
+    /// itself, ending with this directory. Symlinks are listed but not followed. See
+    /// `remove_all_with_progress` for an example.
+    pub fn remove_all_plan(&self) -> Result<Vec<PathBuf>> {
+        let mut plan = Vec::new();
+        plan_contents(self, &mut plan)?;
+        plan.push(self.to_path_buf());
+        Ok(plan)
+    }
+
+    /// Remove (delete) everything inside the directory, keeping the directory itself.
+    ///
+    /// Symlinks are removed, not followed, so nothing outside of the directory is removed.
+    /// Removal continues after an entry can't be removed and the error of the first failing
+    /// entry is returned, with that entry's path.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let cache = PathDir::create(example)?;
+    /// PathFile::create(cache.join("entry"))?;
+    /// PathDir::create(cache.join("nested"))?;
+    ///
+    /// cache.remove_contents()?;
+    /// assert!(cache.exists());
+    /// assert_eq!(0, cache.list()?.count());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn remove_contents(&self) -> Result<()> {
+        remove_contents(self, &mut |_| {})
+    }
+
+    #[cfg(unix)]
+    /// Recursively set the unix permission bits of the directory and everything inside of it,
+    /// using `dir_mode` for directories and `file_mode` for files.
+    ///
+    /// A directory is changed before it is read, so a tree can be made readable again.
+    /// Symlinks are skipped, so nothing outside of the directory is changed. Changing the
+    /// permissions continues after an entry fails, and a `RecursiveError` is returned with the
+    /// errors of all of the failed entries.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// let file = PathFile::create_all(dir.join("nested/file.txt"))?;
+    ///
+    /// dir.set_mode_recursive(0o755, 0o644)?;
+    /// assert_eq!(0o755, file.parent_dir().unwrap().mode()? & 0o7777);
+    /// assert_eq!(0o644, file.mode()? & 0o7777);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn set_mode_recursive(
+        &self,
+        dir_mode: u32,
+        file_mode: u32,
+    ) -> ::std::result::Result<(), RecursiveError> {
+        set_recursive(self, false, &|path, is_dir| {
+            PathAbs(PathArc::new(path)).set_mode(if is_dir { dir_mode } else { file_mode })
+        })
+    }
+
+    /// Recursively set or clear the readonly flag of the directory and everything inside of
+    /// it, like `PathAbs::set_readonly`.
+    ///
+    /// Symlinks are skipped and failures are handled like `set_mode_recursive`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// let file = PathFile::create_all(dir.join("nested/file.txt"))?;
+    ///
+    /// dir.set_readonly_recursive(true)?;
+    /// assert!(file.metadata()?.permissions().readonly());
+    ///
+    /// dir.set_readonly_recursive(false)?;
+    /// assert!(!file.metadata()?.permissions().readonly());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn set_readonly_recursive(
+        &self,
+        readonly: bool,
+    ) -> ::std::result::Result<(), RecursiveError> {
+        set_recursive(self, false, &|path, _| PathAbs(PathArc::new(path)).set_readonly(readonly))
+    }
+
+    #[cfg(unix)]
+    /// Recursively change the owner and/or group of the directory and everything inside of
+    /// it, like `PathAbs::chown`. Passing `None` leaves that id unchanged.
+    ///
+    /// Symlinks inside of the directory are changed with `lchown`, so their targets are not
+    /// changed. Changing the owner continues after an entry fails, and a `RecursiveError` is
+    /// returned with the errors of all of the failed entries.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// let file = PathFile::create_all(dir.join("nested/file.txt"))?;
+    ///
+    /// // Changing to the current owner doesn't require any privileges.
+    /// let (uid, gid) = dir.owner()?;
+    /// dir.chown_recursive(Some(uid), Some(gid))?;
+    /// assert_eq!((uid, gid), file.owner()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn chown_recursive(
+        &self,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> ::std::result::Result<(), RecursiveError> {
+        use std::os::unix::fs::{chown, lchown};
+        // Only the directory itself can be a symlink to a directory, which is followed.
+        set_recursive(self, true, &|path, is_dir| {
+            let changed = if is_dir {
+                chown(path, uid, gid)
+            } else {
+                lchown(path, uid, gid)
+            };
+            changed.map_err(|err| Error::new(err, "changing owner of", PathArc::new(path)))
+        })
+    }
+
+    /// Rename (move) the directory to `to`, consuming self, and return the new `PathDir`.
+    ///
+    /// If `to` is on a different filesystem the directory is copied with
+    /// `copy_recursive_with` (preserving symlinks and modification times) and then removed.
+    /// If the copy fails the source is left untouched and the partial copy is removed.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// let example_moved = "example_moved";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// # let example_moved = &tmp.path().join(example_moved);
+    /// let dir = PathDir::create(example)?;
+    /// PathFile::create(dir.join("file.txt"))?;
+    ///
+    /// let moved = dir.rename(example_moved)?;
+    /// // assert!(dir.exists());  <--- COMPILE ERROR
+    /// assert!(!example.exists());
+    /// assert!(moved.join("file.txt").exists());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn rename<P: AsRef<Path>>(self, to: P) -> Result<PathDir> {
+        let to = to.as_ref();
+        match fs::rename(&self, to) {
+            Ok(()) => PathDir::new(to),
+            Err(ref err) if is_cross_device(err) => self.rename_by_copy(to),
+            Err(err) => Err(Error::new(
+                err,
+                &format!("renaming to {} from", to.display()),
+                self.into(),
+            )),
+        }
+    }
+
+    /// Move the directory to `to` by copying it and then removing it.
+    fn rename_by_copy(self, to: &Path) -> Result<PathDir> {
+        let existed = fs::symlink_metadata(to).is_ok();
+        let mut options = DirCopyOptions::new();
+        options
+            .overwrite(false)
+            .symlinks(SymlinkPolicy::Preserve)
+            .preserve_mtime(true);
+        let copy = match self.copy_recursive_with(to, &options) {
+            Ok(copy) => copy,
+            Err(err) => {
+                if !existed {
+                    let _ = remove_contents(to, &mut |_| {});
+                    let _ = fs::remove_dir(to);
+                }
+                return Err(err);
+            }
+        };
+        self.remove_all()?;
+        Ok(copy)
+    }
+
+    /// Creates a new symbolic link on the filesystem to the dst.
+    ///
+    /// This handles platform specific behavior correctly.
+    ///
+    /// The returned `PathDir` is the path of the link itself, use `read_link` to get the path it
+    /// points to or `canonicalize` to resolve it. Listing or joining through it accesses the
+    /// target's contents.
+    ///
+    /// > On windows, creating symlinks requires the `SeCreateSymbolicLinkPrivilege` (i.e. running
+    /// > as administrator or with developer mode enabled), otherwise
+    /// > `io::ErrorKind::PermissionDenied` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    /// use std::path::Path;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// let example_sym = "example_sym";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// # let example_sym = &tmp.path().join(example_sym);
+    /// let dir = PathDir::create(example)?;
+    /// let file = PathFile::create(dir.join("example.txt"))?;
+    ///
+    /// let dir_sym = dir.symlink(example_sym)?;
+    ///
+    /// // They have a different "absolute path"
+    /// assert_ne!(dir, dir_sym);
+    ///
+    /// // But they can be canonicalized to the same file.
+    /// let dir_can = dir_sym.canonicalize()?;
+    /// assert_eq!(dir, dir_can);
+    ///
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn symlink<P: AsRef<Path>>(&self, dst: P) -> Result<PathDir> {
+        symlink_dir(&self, &dst).map_err(|err| {
+            Error::new(
+                err,
+                &format!("linking from {} to", dst.as_ref().display()),
+                self.clone().into(),
+            )
+        })?;
+        PathDir::new(dst)
+    }
+
+    /// Creates a symbolic link at dst to the directory like `symlink`, replacing dst if it is
+    /// already a symlink.
+    ///
+    /// The new link is created next to dst and then renamed over it, so there is no moment at
+    /// which dst is missing, i.e. when switching a `current` link between releases. If dst
+    /// exists and is not a symlink (i.e. a file or directory) this returns
+    /// `io::ErrorKind::AlreadyExists`.
+    ///
+    /// > On windows an existing link is removed before the new link is renamed into place, so
+    /// > the replacement is not atomic.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathDir;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let releases = PathDir::create(example)?;
+    /// let v1 = PathDir::create(releases.join("v1"))?;
+    /// let v2 = PathDir::create(releases.join("v2"))?;
+    ///
+    /// let current = v1.symlink_replace(releases.join("current"))?;
+    /// assert_eq!(v1, current.canonicalize()?);
+    ///
+    /// let current = v2.symlink_replace(releases.join("current"))?;
+    /// assert_eq!(v2, current.canonicalize()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn symlink_replace<P: AsRef<Path>>(&self, dst: P) -> Result<PathDir> {
+        let dst = dst.as_ref();
+        let (dir, name) = match (dst.parent(), dst.file_name()) {
+            (Some(dir), Some(name)) => (dir, name),
+            _ => {
+                return Err(Error::new(
+                    io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"),
+                    "linking",
+                    PathArc::new(dst),
+                ))
+            }
+        };
+        match fs::symlink_metadata(dst) {
+            Ok(ref meta) if meta.file_type().is_symlink() => {}
+            Ok(_) => {
+                return Err(Error::new(
+                    io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        "path exists and is not a symlink",
+                    ),
+                    &format!("linking from {} to", dst.display()),
+                    self.clone().into(),
+                ))
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(Error::new(err, "getting metadata of", PathArc::new(dst))),
+        }
+        let temp = loop {
+            let temp = PathArc::new(dir.join(temp_name(name)));
+            match symlink_dir(self, &temp) {
+                Ok(()) => break temp,
+                Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => {
+                    return Err(Error::new(
+                        err,
+                        &format!("linking from {} to", temp.display()),
+                        self.clone().into(),
+                    ))
+                }
+            }
+        };
+        if let Err(err) = replace_link(&temp, dst) {
+            // Symlinks to directories are directories on windows.
+            let _ = fs::remove_file(&temp).or_else(|_| fs::remove_dir(&temp));
+            return Err(Error::new(
+                err,
+                &format!("renaming to {} from", dst.display()),
+                temp,
+            ));
+        }
+        PathDir::new(dst)
+    }
+
+    /// Sync the directory entries to disk, making creations, renames and removals of the
+    /// directory's children durable.
+    ///
+    /// > On windows this does nothing, since directories cannot be synced.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    ///
+    /// let file = PathFile::create(dir.join("example.txt"))?;
+    /// file.write_str_atomic("foo")?;
+    /// dir.sync_all()?;
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn sync_all(&self) -> Result<()> {
+        sync_dir(self).map_err(|err| Error::new(err, "syncing", self.clone().into()))
+    }
+
+    /// Return a reference to a basic `std::path::Path`
+    pub fn as_path(&self) -> &Path {
+        self.as_ref()
+    }
+
+    /// Returns the canonical form of the path with all intermediate components normalized and
+    /// symbolic links resolved.
+    ///
+    /// See [`PathAbs::canonicalize`]
+    ///
+    /// [`PathAbs::canonicalize`]: struct.PathAbs.html#method.canonicalize
+    pub fn canonicalize(&self) -> Result<PathDir> {
+        Ok(PathDir(self.0.canonicalize()?))
+    }
+
+    /// Get the parent directory of this directory as a `PathDir`.
+    ///
+    /// > This does not make aditional syscalls, as the parent by definition must be a directory
+    /// > and exist.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// use path_abs::PathDir;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let src = PathDir::new("src")?;
+    /// let proj = src.parent_dir().unwrap();
+    /// assert_eq!(PathDir::new("src/..")?, proj);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn parent_dir(&self) -> Option<PathDir> {
+        match self.parent() {
+            Some(path) => Some(PathDir(PathAbs(PathArc::new(path)))),
+            None => None,
+        }
+    }
+
+    /// Return an iterator over the directory and its ancestors, from the directory up to and
+    /// including the root (i.e. `/`, `C:\` or the share of a UNC path).
+    ///
+    /// Like `parent_dir`, this does not make any syscalls.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let project = PathDir::create(example)?;
+    /// PathFile::create(project.join("Cargo.toml"))?;
+    /// let bin = PathDir::create_all(project.join("src/bin"))?;
+    ///
+    /// let mut ancestors = bin.ancestors();
+    /// assert_eq!(Some(bin.clone()), ancestors.next());
+    /// assert_eq!(bin.parent_dir(), ancestors.next());
+    ///
+    /// // Find the nearest directory containing `Cargo.toml`
+    /// let found = bin.ancestors().find(|dir| dir.join("Cargo.toml").exists());
+    /// assert_eq!(Some(project), found);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn ancestors(&self) -> Ancestors {
+        Ancestors {
+            next: Some(self.clone()),
+        }
+    }
+
+    /// Create a mock dir type. *For use in tests only*.
+    ///
+    /// See the docs for [`PathAbs::mock`](struct.PathAbs.html#method.mock)
+    pub fn mock<P: AsRef<Path>>(path: P) -> PathDir {
+        PathDir(PathAbs::mock(path))
+    }
+}
+
+/// An iterator over `PathType` objects, returned by `PathDir::list`.
+pub struct ListDir {
+    // TODO: this should be a reference...?
+    // Or is this a good excuse to use Arc under the hood everywhere?
+    dir: PathDir,
+    fsread: fs::ReadDir,
+    policy: ErrorPolicy,
+    errors: Vec<Error>,
+    skip_hidden: bool,
+}
+
+impl ListDir {
+    /// The errors skipped so far when listing with `ErrorPolicy::Collect`.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    /// Take the errors skipped so far when listing with `ErrorPolicy::Collect`.
+    pub fn take_errors(&mut self) -> Vec<Error> {
+        ::std::mem::take(&mut self.errors)
+    }
+
+    /// Resolve the next entry with `resolve`, handling errors according to the policy.
+    fn next_with<T, F>(&mut self, resolve: F) -> Option<Result<T>>
+    where
+        F: Fn(&PathDir, &fs::DirEntry) -> Result<T>,
+    {
+        loop {
+            let resolved = match self.fsread.next()? {
+                Ok(ref entry) if self.skip_hidden && is_hidden(entry) => continue,
+                Ok(entry) => resolve(&self.dir, &entry),
+                Err(err) => Err(Error::new(err, "iterating over", self.dir.clone().into())),
+            };
+            match resolved {
+                Err(err) => match self.policy {
+                    ErrorPolicy::Fail => return Some(Err(err)),
+                    ErrorPolicy::Skip => {}
+                    ErrorPolicy::Collect => self.errors.push(err),
+                },
+                ok => return Some(ok),
+            }
+        }
+    }
+}
+
+impl ::std::iter::Iterator for ListDir {
+    type Item = Result<PathType>;
+    fn next(&mut self) -> Option<Result<PathType>> {
+        self.next_with(entry_type)
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// An entry of a directory along with its path relative to the directory, returned by
+/// `PathDir::list_rel` and `PathDir::walk_rel`.
+pub struct Entry {
+    /// The entry itself, with its absolute path.
+    pub abs: PathType,
+    /// The path of the entry relative to the directory, made of the names the entry was found
+    /// by.
+    pub rel: PathBuf,
+}
+
+/// An iterator over the entries of a directory with their relative paths, returned by
+/// `PathDir::list_rel`.
+pub struct ListRel(ListDir);
+
+impl ::std::iter::Iterator for ListRel {
+    type Item = Result<Entry>;
+    fn next(&mut self) -> Option<Result<Entry>> {
+        self.0.next_with(|dir, entry| {
+            entry_type(dir, entry).map(|abs| Entry {
+                abs,
+                rel: PathBuf::from(entry.file_name()),
+            })
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+/// An entry of a directory along with its metadata, returned by `PathDir::list_meta` and
+/// `PathDir::walk_meta`.
+pub struct EntryMeta {
+    /// The entry itself, with its absolute path.
+    pub abs: PathType,
+    /// The metadata of the entry. For a symlink this is the metadata of its target, like the
+    /// type of `abs`.
+    pub metadata: fs::Metadata,
+}
+
+/// An iterator over the entries of a directory with their metadata, returned by
+/// `PathDir::list_meta`.
+pub struct ListMeta(ListDir);
+
+impl ::std::iter::Iterator for ListMeta {
+    type Item = Result<EntryMeta>;
+    fn next(&mut self) -> Option<Result<EntryMeta>> {
+        self.0.next_with(|_, entry| {
+            resolve_entry(entry).map(|(abs, metadata, _)| EntryMeta { abs, metadata })
+        })
+    }
+}
+
+/// Return whether `entry` is hidden: its name starts with a `.` or, on Windows, it has the
+/// hidden attribute.
+pub(crate) fn is_hidden(entry: &fs::DirEntry) -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        let attributes = entry.metadata().map(|meta| meta.file_attributes());
+        if attributes.map(|a| a & FILE_ATTRIBUTE_HIDDEN != 0).unwrap_or(false) {
+            return true;
+        }
+    }
+    entry.file_name().to_string_lossy().starts_with('.')
+}
+
+/// Resolve the `PathType` of `entry` along with its metadata and whether it is a symlink.
+///
+/// The metadata is read from the `DirEntry` (which is free on some platforms) and is only read
+/// again, following the link, if the entry is a symlink.
+pub(crate) fn resolve_entry(entry: &fs::DirEntry) -> Result<(PathType, fs::Metadata, bool)> {
+    let path = PathArc::new(entry.path());
+    let metadata = entry
+        .metadata()
+        .map_err(|err| Error::new(err, "getting metadata of", path.clone()))?;
+    let is_link = metadata.file_type().is_symlink();
+    let metadata = if is_link { path.metadata()? } else { metadata };
+    let ty = with_type(path, metadata.file_type())?;
+    Ok((ty, metadata, is_link))
+}
+
+/// Resolve the `PathType` of `entry` of `dir`.
+///
+/// The path is joined from the directory and the file name rather than resolved again and
+/// the type is that of the `DirEntry`, so only symlinks need another syscall (to follow them).
+fn entry_type(dir: &PathDir, entry: &fs::DirEntry) -> Result<PathType> {
+    let path = dir.join(entry.file_name());
+    let ty = entry
+        .file_type()
+        .map_err(|err| Error::new(err, "getting file type of", path.clone()))?;
+    let ty = if ty.is_symlink() {
+        path.metadata()?.file_type()
+    } else {
+        ty
+    };
+    with_type(path, ty)
+}
+
+/// Return the `PathType` of the absolute `path`, which is of type `ty`.
+fn with_type(path: PathArc, ty: fs::FileType) -> Result<PathType> {
+    let abs = PathAbs(path);
+    if ty.is_dir() {
+        Ok(PathType::Dir(PathDir(abs)))
+    } else if ty.is_file() {
+        Ok(PathType::File(PathFile(abs)))
+    } else {
+        Err(Error::new(
+            io::Error::new(io::ErrorKind::InvalidInput, "path is not a file or directory"),
+            "resolving",
+            abs.into(),
+        ))
+    }
+}
+
+/// Collect the entries of `iter`, returning a `ListError` if any of them failed.
+pub(crate) fn collect_vec<I>(iter: I) -> ::std::result::Result<Vec<PathType>, ListError>
+where
+    I: Iterator<Item = Result<PathType>>,
+{
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    for entry in iter {
+        match entry {
+            Ok(entry) => entries.push(entry),
+            Err(err) => errors.push(err),
+        }
+    }
+    if errors.is_empty() {
+        Ok(entries)
+    } else {
+        Err(ListError::new(entries, errors))
+    }
+}
+
+impl ListError {
+    /// Create the error from the entries which were read and the errors of those which
+    /// weren't.
+    ///
+    /// # Panics
+    /// If `errors` is empty.
+    pub(crate) fn new(entries: Vec<PathType>, errors: Vec<Error>) -> ListError {
+        assert!(!errors.is_empty(), "a ListError has at least one error");
+        ListError { entries, errors }
+    }
+
+    /// The entries which were read successfully.
+    pub fn entries(&self) -> &[PathType] {
+        &self.entries
+    }
+
+    /// Consume the error, returning the entries which were read successfully.
+    pub fn into_entries(self) -> Vec<PathType> {
+        self.entries
+    }
+
+    /// The errors of the entries which could not be read, with their paths. There is always
+    /// at least one.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+}
+
+impl fmt::Debug for ListError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ListError<{}>", self)
+    }
+}
+
+impl fmt::Display for ListError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_errors(&self.errors, f)
+    }
+}
+
+impl error::Error for ListError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.errors[0])
+    }
+}
+
+impl From<ListError> for io::Error {
+    fn from(err: ListError) -> io::Error {
+        let kind = err.errors[0].io_error().kind();
+        io::Error::new(kind, err.to_string())
+    }
+}
+
+impl RecursiveError {
+    /// Create the error from the errors of the entries which failed.
+    ///
+    /// # Panics
+    /// If `errors` is empty.
+    pub(crate) fn new(errors: Vec<Error>) -> RecursiveError {
+        assert!(!errors.is_empty(), "a RecursiveError has at least one error");
+        RecursiveError { errors }
+    }
+
+    /// The errors of the entries which failed, with their paths. There is always at least one.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    /// Consume the error, returning the errors of the entries which failed.
+    pub fn into_errors(self) -> Vec<Error> {
+        self.errors
+    }
+}
+
+impl fmt::Debug for RecursiveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RecursiveError<{}>", self)
+    }
+}
+
+impl fmt::Display for RecursiveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_errors(&self.errors, f)
+    }
+}
+
+impl error::Error for RecursiveError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.errors[0])
+    }
+}
+
+impl From<RecursiveError> for io::Error {
+    fn from(err: RecursiveError) -> io::Error {
+        let kind = err.errors[0].io_error().kind();
+        io::Error::new(kind, err.to_string())
+    }
+}
+
+/// Display the first of `errors` along with how many others there are.
+fn fmt_errors(errors: &[Error], f: &mut fmt::Formatter) -> fmt::Result {
+    match errors.len() {
+        1 => write!(f, "{}", errors[0]),
+        n => write!(f, "{} (and {} other errors)", errors[0], n - 1),
+    }
+}
+
+/// An iterator over a directory and its ancestors, returned by `PathDir::ancestors`.
+#[derive(Clone, Debug)]
+pub struct Ancestors {
+    next: Option<PathDir>,
+}
+
+impl ::std::iter::Iterator for Ancestors {
+    type Item = PathDir;
+    fn next(&mut self) -> Option<PathDir> {
+        let next = self.next.take()?;
+        self.next = next.parent_dir();
+        Some(next)
+    }
+}
+
+/// An iterator over the files of a directory, returned by `PathDir::list_files`.
+pub struct ListFiles(ListDir);
+
+impl ::std::iter::Iterator for ListFiles {
+    type Item = Result<PathFile>;
+    fn next(&mut self) -> Option<Result<PathFile>> {
+        loop {
+            match self.0.next()? {
+                Ok(PathType::File(file)) => return Some(Ok(file)),
+                Ok(PathType::Dir(_)) => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+/// An iterator over the subdirectories of a directory, returned by `PathDir::list_dirs`.
+pub struct ListDirs(ListDir);
+
+impl ::std::iter::Iterator for ListDirs {
+    type Item = Result<PathDir>;
+    fn next(&mut self) -> Option<Result<PathDir>> {
+        loop {
+            match self.0.next()? {
+                Ok(PathType::Dir(dir)) => return Some(Ok(dir)),
+                Ok(PathType::File(_)) => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+impl fmt::Debug for PathDir {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl AsRef<PathAbs> for PathDir {
+    fn as_ref(&self) -> &PathAbs {
+        &self.0
+    }
+}
+
+impl AsRef<PathArc> for PathDir {
+    fn as_ref(&self) -> &PathArc {
+        self.0.as_ref()
+    }
+}
+
+impl AsRef<Path> for PathDir {
+    fn as_ref(&self) -> &Path {
+        self.0.as_ref()
+    }
+}
+
+impl AsRef<PathBuf> for PathDir {
+    fn as_ref(&self) -> &PathBuf {
+        self.0.as_ref()
+    }
+}
+
+impl Borrow<PathAbs> for PathDir {
+    fn borrow(&self) -> &PathAbs {
+        self.as_ref()
+    }
+}
+
+impl Borrow<PathArc> for PathDir {
+    fn borrow(&self) -> &PathArc {
+        self.as_ref()
+    }
+}
+
+impl Borrow<Path> for PathDir {
+    fn borrow(&self) -> &Path {
+        self.as_ref()
+    }
+}
+
+impl Borrow<PathBuf> for PathDir {
+    fn borrow(&self) -> &PathBuf {
+        self.as_ref()
+    }
+}
+
+impl<'a> Borrow<PathAbs> for &'a PathDir {
+    fn borrow(&self) -> &PathAbs {
+        self.as_ref()
+    }
+}
+
+impl<'a> Borrow<PathArc> for &'a PathDir {
+    fn borrow(&self) -> &PathArc {
+        self.as_ref()
+    }
+}
+
+impl<'a> Borrow<Path> for &'a PathDir {
+    fn borrow(&self) -> &Path {
+        self.as_ref()
+    }
+}
+
+impl<'a> Borrow<PathBuf> for &'a PathDir {
+    fn borrow(&self) -> &PathBuf {
+        self.as_ref()
+    }
+}
+
+impl Deref for PathDir {
+    type Target = PathAbs;
+
+    fn deref(&self) -> &PathAbs {
+        &self.0
+    }
+}
+
+impl From<PathDir> for PathAbs {
+    fn from(path: PathDir) -> PathAbs {
+        path.0
+    }
+}
+
+impl From<PathDir> for PathArc {
+    fn from(path: PathDir) -> PathArc {
+        let abs: PathAbs = path.into();
+        abs.0
+    }
+}
+
+impl From<PathDir> for Arc<PathBuf> {
+    fn from(path: PathDir) -> Arc<PathBuf> {
+        let abs: PathAbs = path.into();
+        abs.into()
+    }
+}
+
+impl From<PathDir> for PathBuf {
+    fn from(path: PathDir) -> PathBuf {
+        let abs: PathAbs = path.into();
+        abs.into()
+    }
+}
+
+#[cfg(unix)]
+/// Return whether `err` is the error of removing a directory which is not empty.
+fn is_not_empty(err: &io::Error) -> bool {
+    use libc;
+
+    err.kind() == io::ErrorKind::DirectoryNotEmpty
+        || matches!(err.raw_os_error(), Some(libc::ENOTEMPTY) | Some(libc::EEXIST))
+}
+
+#[cfg(windows)]
+/// Return whether `err` is the error of removing a directory which is not empty.
+fn is_not_empty(err: &io::Error) -> bool {
+    const ERROR_DIR_NOT_EMPTY: i32 = 145;
+    err.kind() == io::ErrorKind::DirectoryNotEmpty
+        || err.raw_os_error() == Some(ERROR_DIR_NOT_EMPTY)
+}
+
+#[cfg(not(any(unix, windows)))]
+/// Return whether `err` is the error of removing a directory which is not empty.
+fn is_not_empty(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::DirectoryNotEmpty
+}
+
+#[cfg(unix)]
+pub(crate) fn symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
+    ::std::os::unix::fs::symlink(src, dst)
+}
+
+#[cfg(windows)]
+pub(crate) fn symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
+    ::std::os::windows::fs::symlink_dir(src, dst)
+}
+
+#[cfg(unix)]
+fn replace_link(link: &Path, dst: &Path) -> io::Result<()> {
+    fs::rename(link, dst)
+}
+
+#[cfg(windows)]
+fn replace_link(link: &Path, dst: &Path) -> io::Result<()> {
+    // Directory symlinks can't be renamed over, so the existing link must be removed first.
+    match fs::symlink_metadata(dst) {
+        Ok(ref meta) if meta.file_type().is_symlink() => fs::remove_dir(dst)?,
+        _ => {}
+    }
+    fs::rename(link, dst)
+}
+
+#[cfg(unix)]
+fn sync_dir<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    fs::File::open(path)?.sync_all()
+}
+
+#[cfg(windows)]
+fn sync_dir<P: AsRef<Path>>(_path: P) -> io::Result<()> {
+    Ok(())
+}
+
+/// Canonicalize `path`, or if it doesn't exist its nearest existing ancestor with the rest of
+/// `path` joined back on, so that symlinks in the existing part are resolved.
+pub(crate) fn canonicalize_existing(path: &PathAbs) -> Result<PathBuf> {
+    let mut existing: &Path = path;
+    let mut rest = Vec::new();
+    loop {
+        match fs::canonicalize(existing) {
+            Ok(mut resolved) => {
+                resolved.extend(rest.iter().rev());
+                return Ok(resolved);
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+                match (existing.parent(), existing.file_name()) {
+                    (Some(parent), Some(name)) => {
+                        rest.push(name);
+                        existing = parent;
+                    }
+                    _ => return Ok(path.to_path_buf()),
+                }
+            }
+            Err(err) => return Err(Error::new(err, "canonicalizing", PathArc::new(existing))),
+        }
+    }
+}
+
+/// The error for a path which should be a directory but is not.
+fn not_a_directory() -> io::Error {
+    io::Error::new(io::ErrorKind::NotADirectory, "path exists but is not a directory")
+}
+
+/// Recursively remove the entries of the directory at `path`, calling `progress` with the path
+/// of each entry after it is removed. Symlinks are removed, not followed.
+///
+/// Removal continues after an entry fails, and the first error is returned.
+fn remove_contents(path: &Path, progress: &mut dyn FnMut(&Path)) -> Result<()> {
+    let entries =
+        fs::read_dir(path).map_err(|err| Error::new(err, "reading dir", PathArc::new(path)))?;
+    let mut result = Ok(());
+    for entry in entries {
+        let removed = entry
+            .map_err(|err| Error::new(err, "reading dir", PathArc::new(path)))
+            .and_then(|entry| {
+                let ty = entry.file_type().map_err(|err| {
+                    Error::new(err, "getting file type of", PathArc::new(entry.path()))
+                })?;
+                remove_entry(&entry.path(), ty, progress)
+            });
+        if result.is_ok() {
+            result = removed;
+        }
+    }
+    result
+}
+
+/// Call `set` with the directory at `path` and then recursively with its entries, along with
+/// whether they are a directory. Special files are skipped, and symlinks are never followed:
+/// they are passed to `set` as a non-directory if `symlinks` is true and skipped otherwise.
+///
+/// This continues after an entry fails, returning the errors of all of the failed entries.
+fn set_recursive(
+    path: &Path,
+    symlinks: bool,
+    set: &dyn Fn(&Path, bool) -> Result<()>,
+) -> ::std::result::Result<(), RecursiveError> {
+    let mut errors = Vec::new();
+    set_recursive_into(path, symlinks, set, &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(RecursiveError::new(errors))
+    }
+}
+
+fn set_recursive_into(
+    path: &Path,
+    symlinks: bool,
+    set: &dyn Fn(&Path, bool) -> Result<()>,
+    errors: &mut Vec<Error>,
+) {
+    if let Err(err) = set(path, true) {
+        errors.push(err);
+    }
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(err) => return errors.push(Error::new(err, "reading dir", PathArc::new(path))),
+    };
+    for entry in entries {
+        let changed = entry
+            .map_err(|err| Error::new(err, "reading dir", PathArc::new(path)))
+            .and_then(|entry| {
+                let ty = entry.file_type().map_err(|err| {
+                    Error::new(err, "getting file type of", PathArc::new(entry.path()))
+                })?;
+                if ty.is_dir() {
+                    set_recursive_into(&entry.path(), symlinks, set, errors);
+                    Ok(())
+                } else if ty.is_file() || (symlinks && ty.is_symlink()) {
+                    set(&entry.path(), false)
+                } else {
+                    Ok(())
+                }
+            });
+        if let Err(err) = changed {
+            errors.push(err);
+        }
+    }
+}
+
+/// Remove the file, symlink or directory tree at `path`, which is of type `ty`.
+pub(crate) fn remove_entry(
+    path: &Path,
+    ty: fs::FileType,
+    progress: &mut dyn FnMut(&Path),
+) -> Result<()> {
+    let result = if ty.is_dir() {
+        remove_contents(path, progress)?;
+        fs::remove_dir(path)
+    } else {
+        match fs::remove_file(path) {
+            // Symlinks to directories are directories on windows.
+            Err(_) if cfg!(windows) && ty.is_symlink() => fs::remove_dir(path),
+            result => result,
+        }
+    };
+    result.map_err(|err| Error::new(err, "removing", PathArc::new(path)))?;
+    progress(path);
+    Ok(())
+}
+
+/// Push the paths in the directory at `path` in the order `remove_contents` removes them.
+fn plan_contents(path: &Path, plan: &mut Vec<PathBuf>) -> Result<()> {
+    let entries =
+        fs::read_dir(path).map_err(|err| Error::new(err, "reading dir", PathArc::new(path)))?;
+    for entry in entries {
+        let entry = entry.map_err(|err| Error::new(err, "reading dir", PathArc::new(path)))?;
+        let entry_path = entry.path();
+        let ty = entry
+            .file_type()
+            .map_err(|err| Error::new(err, "getting file type of", PathArc::new(&entry_path)))?;
+        if ty.is_dir() {
+            plan_contents(&entry_path, plan)?;
+        }
+        plan.push(entry_path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+    use std::collections::HashSet;
+    use std::fs;
+    use std::io;
+    use std::path::PathBuf;
+    use super::super::file::symlink_file;
+    use super::super::{
+        Entry, Error, ErrorPolicy, ListError, PathAbs, PathArc, PathDir, PathFile, PathType,
+    };
+    use super::set_recursive;
+
+    #[test]
+    fn sanity_list() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+
+        let foo_dir = PathDir::create(tmp_abs.join("foo")).unwrap();
+        let bar_file = PathFile::create(tmp_abs.join("bar.txt")).unwrap();
+
+        let mut result = HashSet::new();
+        for p in tmp_abs.list().unwrap() {
+            result.insert(p.unwrap());
+        }
+
+        let mut expected = HashSet::new();
+        expected.insert(PathType::Dir(foo_dir.clone()));
+        expected.insert(PathType::File(bar_file.clone()));
+
+        assert_eq!(expected, result);
+
+        // just ensure that this compiles
+        let _: PathAbs = foo_dir.into();
+        let _: PathAbs = bar_file.into();
+    }
+
+    #[test]
+    fn sanity_list_error_policy() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let dir = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(dir.join("file.txt")).unwrap();
+        let sub = PathDir::create(dir.join("sub")).unwrap();
+        symlink_file(dir.join("missing"), dir.join("broken")).unwrap();
+
+        let strict = dir.list().unwrap().collect::<Vec<_>>();
+        assert_eq!(3, strict.len());
+        let err = strict.into_iter().find(|r| r.is_err()).unwrap().unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+
+        let mut expected = HashSet::new();
+        expected.insert(PathType::File(file.clone()));
+        expected.insert(PathType::Dir(sub.clone()));
+
+        let skipped = dir.list_opts(ErrorPolicy::Skip).unwrap();
+        let result = skipped.collect::<Result<HashSet<_>, _>>().unwrap();
+        assert_eq!(expected, result);
+
+        let mut collect = dir.list_opts(ErrorPolicy::Collect).unwrap();
+        let result = collect.by_ref().collect::<Result<HashSet<_>, _>>().unwrap();
+        assert_eq!(expected, result);
+        assert_eq!(1, collect.errors().len());
+        assert_eq!(dir.join("broken").as_path(), collect.errors()[0].path());
+        assert_eq!(1, collect.take_errors().len());
+        assert!(collect.errors().is_empty());
+
+        // Removing entries while listing can't kill the iteration.
+        for i in 0..100 {
+            PathFile::create(dir.join(format!("{}.tmp", i))).unwrap();
+        }
+        let mut seen = 0;
+        for entry in dir.list_opts(ErrorPolicy::Skip).unwrap() {
+            entry.unwrap();
+            seen += 1;
+            if seen == 1 {
+                for i in 0..100 {
+                    let _ = fs::remove_file(dir.join(format!("{}.tmp", i)));
+                }
+            }
+        }
+        assert!(seen >= 1);
+    }
+
+    #[test]
+    fn sanity_is_empty() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let dir = PathDir::create(tmp_dir.path().join("dir")).unwrap();
+        assert!(dir.is_empty().unwrap());
+
+        let file = PathFile::create(dir.join("file.txt")).unwrap();
+        assert!(!dir.is_empty().unwrap());
+        file.remove().unwrap();
+        PathDir::create(dir.join("sub")).unwrap();
+        assert!(!dir.is_empty().unwrap());
+
+        let missing = PathDir::mock(tmp_dir.path().join("missing"));
+        let err = missing.is_empty().unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert_eq!(missing.as_path(), err.path());
+    }
+
+    #[test]
+    fn sanity_symlink_replace() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let v1 = PathDir::create(tmp_abs.join("v1")).unwrap();
+        let v2 = PathDir::create(tmp_abs.join("v2")).unwrap();
+        PathFile::create(v1.join("one")).unwrap();
+        PathFile::create(v2.join("two")).unwrap();
+
+        let current = match v1.symlink(tmp_abs.join("current")) {
+            Ok(current) => current,
+            // Creating symlinks is not permitted (i.e. windows without privileges).
+            Err(ref err) if err.io_error().kind() == io::ErrorKind::PermissionDenied => return,
+            Err(err) => panic!("{}", err),
+        };
+        assert_eq!(tmp_abs.join("current").as_path(), current.as_path());
+        assert_eq!(v1.as_path(), current.read_link().unwrap().as_path());
+        let names: Vec<_> = current
+            .list()
+            .unwrap()
+            .map(|p| p.unwrap().file_name().unwrap().to_owned())
+            .collect();
+        assert_eq!(vec!["one"], names);
+
+        let current = v2.symlink_replace(&current).unwrap();
+        assert_eq!(v2, current.canonicalize().unwrap());
+        assert!(PathFile::new(current.join("two")).is_ok());
+        // Only the link and the two targets exist, the temporary link was renamed.
+        assert_eq!(3, tmp_abs.list().unwrap().count());
+
+        // Directories are not replaced
+        let err = v1.symlink_replace(&v2).unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+        assert!(!fs::symlink_metadata(&v2).unwrap().file_type().is_symlink());
+        assert!(v2.join("two").exists());
+        assert_eq!(3, tmp_abs.list().unwrap().count());
+
+        // Neither are files
+        let file = PathFile::create(tmp_abs.join("file")).unwrap();
+        file.write_str("contents").unwrap();
+        let err = v1.symlink_replace(&file).unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+        assert_eq!(v1.as_path(), err.path());
+        assert!(!fs::symlink_metadata(&file).unwrap().file_type().is_symlink());
+        assert_eq!("contents", file.read_string().unwrap());
+        assert_eq!(4, tmp_abs.list().unwrap().count());
+    }
+
+    #[test]
+    fn sanity_list_errors() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+
+        // The directory was removed after it was checked
+        let removed = PathDir::create(tmp_abs.join("removed")).unwrap();
+        removed.clone().remove().unwrap();
+        let err = removed.list().map(|_| ()).unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert_eq!(removed.as_path(), err.path());
+        let io_err: io::Error = err.into();
+        assert_eq!(io::ErrorKind::NotFound, io_err.kind());
+        assert!(io_err.to_string().contains(&removed.display().to_string()));
+
+        // An entry which can't be resolved has its own path
+        let dir = PathDir::create(tmp_abs.join("dir")).unwrap();
+        match symlink_file(dir.join("missing"), dir.join("broken")) {
+            // Creating symlinks is not permitted (i.e. windows without privileges).
+            Err(ref err) if err.kind() == io::ErrorKind::PermissionDenied => return,
+            result => result.unwrap(),
+        }
+        let err = dir.list().unwrap().next().unwrap().unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert_eq!(dir.join("broken").as_path(), err.path());
+        let io_err: io::Error = err.into();
+        assert!(io_err.to_string().contains(&dir.join("broken").display().to_string()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sanity_list_permission_denied() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let locked = PathDir::create(tmp_abs.join("locked")).unwrap();
+        PathFile::create(locked.join("file")).unwrap();
+        locked.set_mode(0o000).unwrap();
+        let readable = fs::read_dir(&locked).is_ok();
+        let result = locked.list().map(|_| ());
+        locked.set_mode(0o755).unwrap();
+        if readable {
+            // Running with privileges to read any directory
+            return;
+        }
+
+        let err = result.unwrap_err();
+        assert_eq!(io::ErrorKind::PermissionDenied, err.io_error().kind());
+        assert_eq!(locked.as_path(), err.path());
+        let io_err: io::Error = err.into();
+        assert_eq!(io::ErrorKind::PermissionDenied, io_err.kind());
+        assert!(io_err.to_string().contains(&locked.display().to_string()));
+    }
+
+    #[test]
+    fn sanity_list_files_dirs() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+
+        let foo_dir = PathDir::create(tmp_abs.join("foo")).unwrap();
+        let bar_dir = PathDir::create(tmp_abs.join("bar")).unwrap();
+        let foo_file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+        PathFile::create(foo_dir.join("nested.txt")).unwrap();
+
+        let files: Vec<_> = tmp_abs.list_files().unwrap().map(|p| p.unwrap()).collect();
+        assert_eq!(vec![foo_file], files);
+
+        let dirs: HashSet<_> = tmp_abs.list_dirs().unwrap().map(|p| p.unwrap()).collect();
+        let mut expected = HashSet::new();
+        expected.insert(foo_dir);
+        expected.insert(bar_dir.clone());
+        assert_eq!(expected, dirs);
+
+        assert_eq!(0, bar_dir.list_files().unwrap().count());
+        assert_eq!(0, bar_dir.list_dirs().unwrap().count());
     }
-}
 
-impl AsRef<PathArc> for PathDir {
-    fn as_ref(&self) -> &PathArc {
-        self.0.as_ref()
+    #[test]
+    fn sanity_list_visible() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let src = PathDir::create(tmp_abs.join("src")).unwrap();
+        let readme = PathFile::create(tmp_abs.join("README.md")).unwrap();
+        PathFile::create_all(tmp_abs.join(".git/HEAD")).unwrap();
+        PathFile::create(tmp_abs.join(".hidden")).unwrap();
+        PathFile::create(tmp_abs.join("..double")).unwrap();
+
+        assert_eq!(5, tmp_abs.list().unwrap().count());
+        let visible: HashSet<_> = tmp_abs.list_visible().unwrap().map(|e| e.unwrap()).collect();
+        let mut expected = HashSet::new();
+        expected.insert(PathType::Dir(src));
+        expected.insert(PathType::File(readme));
+        assert_eq!(expected, visible);
     }
-}
 
-impl AsRef<Path> for PathDir {
-    fn as_ref(&self) -> &Path {
-        self.0.as_ref()
+    #[test]
+    fn sanity_list_rel() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let src = PathDir::create(tmp_abs.join("src")).unwrap();
+        let lib = PathFile::create(src.join("lib.rs")).unwrap();
+        PathFile::create(tmp_abs.join("Cargo.toml")).unwrap();
+
+        let mut entries: Vec<_> = tmp_abs.list_rel().unwrap().map(|e| e.unwrap()).collect();
+        entries.sort_by(|a, b| a.rel.cmp(&b.rel));
+        let rel: Vec<_> = entries.iter().map(|e| e.rel.to_str().unwrap()).collect();
+        assert_eq!(vec!["Cargo.toml", "src"], rel);
+        assert_eq!(PathType::Dir(src.clone()), entries[1].abs);
+
+        let entries: Vec<_> = src.list_rel().unwrap().map(|e| e.unwrap()).collect();
+        assert_eq!(
+            vec![Entry {
+                abs: PathType::File(lib),
+                rel: PathBuf::from("lib.rs"),
+            }],
+            entries
+        );
     }
-}
 
-impl AsRef<PathBuf> for PathDir {
-    fn as_ref(&self) -> &PathBuf {
-        self.0.as_ref()
+    #[test]
+    fn sanity_list_many() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        for i in 0..2000 {
+            if i % 10 == 0 {
+                PathDir::create(tmp_abs.join(format!("dir{}", i))).unwrap();
+            } else {
+                PathFile::create(tmp_abs.join(format!("file{}", i))).unwrap();
+            }
+        }
+        let target = PathFile::new(tmp_abs.join("file1")).unwrap();
+        let linked = match symlink_file(&target, tmp_abs.join("link")) {
+            // Creating symlinks is not permitted (i.e. windows without privileges).
+            Err(ref err) if err.kind() == io::ErrorKind::PermissionDenied => false,
+            result => result.map(|_| true).unwrap(),
+        };
+
+        // The same as resolving every entry on its own
+        let listed: HashSet<_> = tmp_abs.list().unwrap().map(|e| e.unwrap()).collect();
+        let resolved: HashSet<_> = fs::read_dir(&tmp_abs)
+            .unwrap()
+            .map(|e| PathType::new(e.unwrap().path()).unwrap())
+            .collect();
+        assert_eq!(if linked { 2001 } else { 2000 }, listed.len());
+        assert_eq!(resolved, listed);
+        if linked {
+            let link = PathType::File(PathFile::mock(tmp_abs.join("link")));
+            assert!(listed.contains(&link));
+        }
     }
-}
 
-impl Borrow<PathAbs> for PathDir {
-    fn borrow(&self) -> &PathAbs {
-        self.as_ref()
+    #[test]
+    fn sanity_list_meta() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let mut files = Vec::new();
+        for i in 0..5 {
+            let file = PathFile::create(tmp_abs.join(format!("{}.txt", i))).unwrap();
+            file.write_str(&"x".repeat(i * 10)).unwrap();
+            files.push(file);
+        }
+        let nested = PathDir::create(tmp_abs.join("nested")).unwrap();
+
+        let entries: Vec<_> = tmp_abs.list_meta().unwrap().map(|e| e.unwrap()).collect();
+        assert_eq!(6, entries.len());
+        for entry in entries {
+            match entry.abs {
+                PathType::File(ref file) => {
+                    assert!(entry.metadata.is_file());
+                    assert!(files.contains(file));
+                    assert_eq!(file.metadata().unwrap().len(), entry.metadata.len());
+                }
+                PathType::Dir(ref dir) => {
+                    assert!(entry.metadata.is_dir());
+                    assert_eq!(&nested, dir);
+                }
+            }
+        }
+
+        match symlink_file(&files[4], nested.join("link")) {
+            // Creating symlinks is not permitted (i.e. windows without privileges).
+            Err(ref err) if err.kind() == io::ErrorKind::PermissionDenied => return,
+            result => result.unwrap(),
+        }
+        let entries: Vec<_> = nested.list_meta().unwrap().map(|e| e.unwrap()).collect();
+        assert_eq!(PathType::File(PathFile::mock(nested.join("link"))), entries[0].abs);
+        assert_eq!(40, entries[0].metadata.len());
     }
-}
 
-impl Borrow<PathArc> for PathDir {
-    fn borrow(&self) -> &PathArc {
-        self.as_ref()
+    #[test]
+    fn sanity_get_file_dir() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let dir = PathDir::create(tmp_abs.join("dir")).unwrap();
+        let file = PathFile::create(tmp_abs.join("file")).unwrap();
+
+        assert_eq!(file, tmp_abs.get_file("file").unwrap());
+        assert_eq!(dir, tmp_abs.get_dir("dir").unwrap());
+
+        let err = tmp_abs.get_file("missing").unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert_eq!(tmp_abs.as_path(), err.path());
+        assert!(err
+            .to_string()
+            .ends_with(&format!("when getting file missing in {}", tmp_abs.display())));
+
+        let err = tmp_abs.get_dir("missing").unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert!(err
+            .to_string()
+            .ends_with(&format!("when getting directory missing in {}", tmp_abs.display())));
+
+        let err = tmp_abs.get_file("dir").unwrap_err();
+        assert_eq!(io::ErrorKind::IsADirectory, err.io_error().kind());
+        assert_eq!(
+            format!(
+                "path exists but is a directory when getting file dir in {}",
+                tmp_abs.display()
+            ),
+            err.to_string()
+        );
+
+        let err = tmp_abs.get_dir("file").unwrap_err();
+        assert_eq!(io::ErrorKind::NotADirectory, err.io_error().kind());
+        assert_eq!(
+            format!(
+                "path exists but is not a directory when getting directory file in {}",
+                tmp_abs.display()
+            ),
+            err.to_string()
+        );
+
+        for name in &["dir/file", "..", "", "/dir"] {
+            let err = tmp_abs.get_file(name).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidInput, err.io_error().kind());
+        }
     }
-}
 
-impl Borrow<Path> for PathDir {
-    fn borrow(&self) -> &Path {
-        self.as_ref()
+    #[test]
+    fn sanity_create_contained() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let dir = tmp_abs.create_dir("dir").unwrap();
+
+        let file = dir.create_file("file").unwrap();
+        assert_eq!(dir.join("file").as_path(), file.as_path());
+        let nested = dir.create_dir_all("a/b").unwrap();
+        assert_eq!(dir.join("a/b").as_path(), nested.as_path());
+        let nested = dir.create_file("./a/b/file").unwrap();
+        assert!(nested.exists());
+
+        for name in &["../escaped", "a/../../escaped", "..", "", "."] {
+            let err = dir.create_file(name).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidInput, err.io_error().kind());
+            assert_eq!(dir.as_path(), err.path());
+            assert!(dir.create_dir(name).is_err());
+            assert!(dir.create_dir_all(name).is_err());
+        }
+        let err = dir.create_dir("../escaped").unwrap_err();
+        assert_eq!(
+            format!(
+                "name is not a path inside of the directory when creating directory ../escaped \
+                 in {}",
+                dir.display()
+            ),
+            err.to_string()
+        );
+        let absolute = tmp_abs.join("absolute");
+        assert!(dir.create_file(absolute.as_os_str()).is_err());
+        assert!(dir.create_dir(absolute.as_os_str()).is_err());
+        assert!(!absolute.exists());
+        assert!(!tmp_abs.join("escaped").exists());
     }
-}
 
-impl Borrow<PathBuf> for PathDir {
-    fn borrow(&self) -> &PathBuf {
-        self.as_ref()
+    #[test]
+    fn sanity_ancestors() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let deep = PathDir::create_all(tmp_abs.join("a/b/c")).unwrap();
+
+        let ancestors: Vec<_> = deep.ancestors().collect();
+        assert_eq!(deep, ancestors[0]);
+        assert_eq!(tmp_abs.join("a/b").as_path(), ancestors[1].as_path());
+        assert_eq!(tmp_abs, ancestors[3]);
+        let root = ancestors.last().unwrap();
+        assert!(root.parent().is_none());
+        assert!(root.exists());
+        assert_eq!(deep.as_path().ancestors().count(), ancestors.len());
+
+        let root_ancestors: Vec<_> = root.ancestors().collect();
+        assert_eq!(vec![root.clone()], root_ancestors);
     }
-}
 
-impl<'a> Borrow<PathAbs> for &'a PathDir {
-    fn borrow(&self) -> &PathAbs {
-        self.as_ref()
+    #[test]
+    fn sanity_join_contained() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let root = PathDir::create(tmp_abs.join("root")).unwrap();
+        let sub = PathDir::create(root.join("sub")).unwrap();
+        let file = PathFile::create(sub.join("file")).unwrap();
+        let outside = PathFile::create(tmp_abs.join("outside")).unwrap();
+
+        assert_eq!(PathType::File(file.clone()), root.join_contained("sub/file").unwrap());
+        assert_eq!(PathType::File(file), root.join_contained("sub/../sub/./file").unwrap());
+        assert_eq!(PathType::Dir(root.clone()), root.join_contained("sub/..").unwrap());
+
+        let escapes = ["..", "../outside", "sub/../../outside", "../../../../../../etc/passwd"];
+        for path in &escapes {
+            let err = root.join_contained(path).unwrap_err();
+            assert_eq!(io::ErrorKind::PermissionDenied, err.io_error().kind(), "{}", path);
+            assert_eq!(root.as_path(), err.path());
+        }
+        let err = root.join_contained(outside.as_path()).unwrap_err();
+        assert_eq!(io::ErrorKind::PermissionDenied, err.io_error().kind());
+        assert_eq!(
+            format!(
+                "path resolves outside of the directory when resolving ../outside in {}",
+                root.display()
+            ),
+            root.join_contained("../outside").unwrap_err().to_string()
+        );
+
+        let err = root.join_contained("missing").unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert_eq!(root.join("missing").as_path(), err.path());
+
+        // Symlinks are resolved before checking
+        match symlink_file(&outside, root.join("link")) {
+            // Creating symlinks is not permitted (i.e. windows without privileges).
+            Err(ref err) if err.kind() == io::ErrorKind::PermissionDenied => return,
+            result => result.unwrap(),
+        }
+        let err = root.join_contained("link").unwrap_err();
+        assert_eq!(io::ErrorKind::PermissionDenied, err.io_error().kind());
     }
-}
 
-impl<'a> Borrow<PathArc> for &'a PathDir {
-    fn borrow(&self) -> &PathArc {
-        self.as_ref()
+    #[cfg(unix)]
+    #[test]
+    fn sanity_set_mode_recursive() {
+        use std::os::unix::fs::symlink;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let outside = PathFile::create(tmp_abs.join("outside")).unwrap();
+        outside.set_mode(0o600).unwrap();
+        let root = PathDir::create(tmp_abs.join("root")).unwrap();
+        let nested = PathDir::create_all(root.join("a/b")).unwrap();
+        let file = PathFile::create(nested.join("file")).unwrap();
+        file.set_mode(0o700).unwrap();
+        symlink(&outside, nested.join("link")).unwrap();
+        // Unreadable directories are fixed before they are read
+        nested.set_mode(0o000).unwrap();
+
+        root.set_mode_recursive(0o750, 0o640).unwrap();
+        assert_eq!(0o750, root.mode().unwrap() & 0o7777);
+        assert_eq!(0o750, nested.mode().unwrap() & 0o7777);
+        assert_eq!(0o640, file.mode().unwrap() & 0o7777);
+        assert_eq!(0o600, outside.mode().unwrap() & 0o7777);
+
+        root.set_readonly_recursive(true).unwrap();
+        assert!(file.metadata().unwrap().permissions().readonly());
+        assert!(!outside.metadata().unwrap().permissions().readonly());
+        root.set_readonly_recursive(false).unwrap();
+        assert_eq!(0o750, nested.mode().unwrap() & 0o7777);
+        assert_eq!(0o640, file.mode().unwrap() & 0o7777);
+
+        // Without privileges a directory which is made unreadable can't be read
+        if root.owner().unwrap().0 != 0 {
+            PathFile::create(root.join("other")).unwrap();
+            let err = root.set_mode_recursive(0o300, 0o600).unwrap_err();
+            assert_eq!(1, err.errors().len());
+            assert_eq!("reading dir", err.errors()[0].action());
+            root.set_mode(0o750).unwrap();
+            root.set_mode_recursive(0o750, 0o640).unwrap();
+            assert_eq!(0o640, file.mode().unwrap() & 0o7777);
+        }
     }
-}
 
-impl<'a> Borrow<Path> for &'a PathDir {
-    fn borrow(&self) -> &Path {
-        self.as_ref()
+    #[cfg(unix)]
+    #[test]
+    fn sanity_chown_recursive() {
+        use std::os::unix::fs::symlink;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let root = PathDir::create(tmp_abs.join("root")).unwrap();
+        let nested = PathDir::create_all(root.join("a/b")).unwrap();
+        let file = PathFile::create(nested.join("file")).unwrap();
+        // Broken symlinks can only be changed with lchown
+        symlink("missing", nested.join("broken")).unwrap();
+        symlink(&file, nested.join("link")).unwrap();
+
+        let (uid, gid) = root.owner().unwrap();
+        root.chown_recursive(Some(uid), Some(gid)).unwrap();
+        root.chown_recursive(None, Some(gid)).unwrap();
+        root.chown_recursive(None, None).unwrap();
+        assert_eq!((uid, gid), nested.owner().unwrap());
+        assert_eq!((uid, gid), file.owner().unwrap());
+
+        // Without privileges every entry fails, and all of the failures are returned
+        if uid != 0 {
+            let err = root.chown_recursive(Some(0), None).unwrap_err();
+            assert_eq!(6, err.errors().len());
+            assert!(err.to_string().contains("(and 5 other errors)"));
+            let io_err: io::Error = err.into();
+            assert_eq!(io::ErrorKind::PermissionDenied, io_err.kind());
+        }
     }
-}
 
-impl<'a> Borrow<PathBuf> for &'a PathDir {
-    fn borrow(&self) -> &PathBuf {
-        self.as_ref()
+    #[test]
+    fn sanity_set_recursive_errors() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let root = PathDir::create(tmp_abs.join("root")).unwrap();
+        let a = PathFile::create_all(root.join("a/file")).unwrap();
+        let b = PathFile::create_all(root.join("b/file")).unwrap();
+        PathFile::create(root.join("ok")).unwrap();
+
+        let err = set_recursive(&root, false, &|path, _| {
+            if path.file_name() == Some("file".as_ref()) {
+                let err = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+                Err(Error::new(err, "changing", PathArc::new(path)))
+            } else {
+                Ok(())
+            }
+        })
+        .unwrap_err();
+        let mut failed: Vec<_> = err.errors().iter().map(|e| e.path().to_path_buf()).collect();
+        failed.sort();
+        assert_eq!([a.to_path_buf(), b.to_path_buf()], &failed[..]);
+        assert!(err.to_string().contains("(and 1 other errors)"));
+
+        assert!(set_recursive(&root, false, &|_, _| Ok(())).is_ok());
     }
-}
 
-impl Deref for PathDir {
-    type Target = PathAbs;
+    #[test]
+    fn sanity_contains() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let bar = PathDir::create(tmp_abs.join("bar")).unwrap();
+        let child = PathFile::create(bar.join("child")).unwrap();
+        let deep = PathFile::create_all(bar.join("a/b/c/deep")).unwrap();
+        let barbaz = PathFile::create_all(tmp_abs.join("barbaz/file")).unwrap();
 
-    fn deref(&self) -> &PathAbs {
-        &self.0
+        for dir in &[&bar, &PathDir::mock(tmp_abs.join("bar/a/.."))] {
+            assert!(dir.contains(&child).unwrap());
+            assert!(dir.contains(&deep).unwrap());
+            assert!(dir.contains(dir).unwrap());
+            assert!(!dir.contains(&barbaz).unwrap());
+            assert!(!dir.contains(barbaz.parent().unwrap()).unwrap());
+            assert!(!dir.contains(bar.join("a/../../barbaz")).unwrap());
+        }
+        let err = bar.contains(bar.join("missing")).unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+
+        assert!(bar.contains_lexical(bar.join("missing/file")).unwrap());
+        assert!(bar.contains_lexical(&deep).unwrap());
+        assert!(!bar.contains_lexical(&barbaz).unwrap());
+        assert!(!bar.contains_lexical(bar.join("a/../../barbaz")).unwrap());
+
+        match symlink_file(&barbaz, bar.join("link")) {
+            // Creating symlinks is not permitted (i.e. windows without privileges).
+            Err(ref err) if err.kind() == io::ErrorKind::PermissionDenied => return,
+            result => result.unwrap(),
+        }
+        assert!(!bar.contains(bar.join("link")).unwrap());
+        assert!(bar.contains_lexical(bar.join("link")).unwrap());
     }
-}
 
-impl From<PathDir> for PathAbs {
-    fn from(path: PathDir) -> PathAbs {
-        path.0
+    #[cfg(unix)]
+    #[test]
+    fn sanity_create_with_mode() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+
+        let private = PathDir::create_with_mode(tmp_abs.join("private"), 0o700).unwrap();
+        assert_eq!(0o700, private.mode().unwrap() & 0o7777);
+        // Existing directories are unchanged
+        let again = PathDir::create_with_mode(&private, 0o755).unwrap();
+        assert_eq!(0o700, again.mode().unwrap() & 0o7777);
+
+        let deep = PathDir::create_all_with_mode(private.join("a/b"), 0o711).unwrap();
+        assert_eq!(0o711, deep.mode().unwrap() & 0o7777);
+        assert_eq!(0o711, deep.parent_dir().unwrap().mode().unwrap() & 0o7777);
+        assert_eq!(0o700, private.mode().unwrap() & 0o7777);
+
+        let file = PathFile::create(tmp_abs.join("file")).unwrap();
+        let err = PathDir::create_with_mode(&file, 0o700).unwrap_err();
+        assert_eq!(io::ErrorKind::NotADirectory, err.io_error().kind());
+        assert!(PathDir::create_all_with_mode(&file, 0o700).is_err());
     }
-}
 
-impl From<PathDir> for PathArc {
-    fn from(path: PathDir) -> PathArc {
-        let abs: PathAbs = path.into();
-        abs.0
+    #[test]
+    fn sanity_list_vec() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let dir = PathDir::create(tmp_abs.join("dir")).unwrap();
+        let file = PathFile::create(tmp_abs.join("file")).unwrap();
+
+        let mut entries = tmp_abs.list_vec().unwrap();
+        entries.sort();
+        assert_eq!(vec![PathType::File(file.clone()), PathType::Dir(dir.clone())], entries);
+
+        let broken = tmp_abs.join("broken");
+        match symlink_file(tmp_abs.join("missing"), &broken) {
+            // Creating symlinks is not permitted (i.e. windows without privileges).
+            Err(ref err) if err.kind() == io::ErrorKind::PermissionDenied => return,
+            result => result.unwrap(),
+        }
+        let err = tmp_abs.list_vec().unwrap_err();
+        assert_eq!(1, err.errors().len());
+        assert_eq!(broken.as_path(), err.errors()[0].path());
+        assert_eq!(err.errors()[0].to_string(), err.to_string());
+        let mut entries = err.into_entries();
+        entries.sort();
+        assert_eq!(vec![PathType::File(file), PathType::Dir(dir)], entries);
+        let io_err: io::Error = tmp_abs.list_vec().unwrap_err().into();
+        assert_eq!(io::ErrorKind::NotFound, io_err.kind());
+
+        let missing = PathDir::mock(tmp_abs.join("missing"));
+        let err = missing.list_vec().unwrap_err();
+        assert!(err.entries().is_empty());
+        assert_eq!(missing.as_path(), err.errors()[0].path());
     }
-}
 
-impl From<PathDir> for Arc<PathBuf> {
-    fn from(path: PathDir) -> Arc<PathBuf> {
-        let abs: PathAbs = path.into();
-        abs.into()
+    #[test]
+    #[should_panic(expected = "at least one error")]
+    fn sanity_list_error_empty() {
+        ListError::new(Vec::new(), Vec::new());
     }
-}
 
-impl From<PathDir> for PathBuf {
-    fn from(path: PathDir) -> PathBuf {
-        let abs: PathAbs = path.into();
-        abs.into()
+    #[test]
+    fn sanity_remove_not_empty() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let dir = PathDir::create(tmp_abs.join("dir")).unwrap();
+        PathFile::create(dir.join("file")).unwrap();
+
+        let err = dir.clone().remove().unwrap_err();
+        assert_eq!(io::ErrorKind::DirectoryNotEmpty, err.io_error().kind());
+        assert_eq!(dir.as_path(), err.path());
+        assert_eq!(
+            format!("directory is not empty when removing {}", dir.display()),
+            err.to_string()
+        );
+        assert!(dir.exists());
+
+        PathFile::new(dir.join("file")).unwrap().remove().unwrap();
+        dir.clone().remove().unwrap();
+        assert!(!dir.exists());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use tempdir::TempDir;
-    use std::collections::HashSet;
-    use super::super::{PathAbs, PathDir, PathFile, PathType};
+    #[test]
+    fn sanity_remove_other_errors() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+
+        // Errors other than not being empty are passed through unchanged
+        let missing = PathDir::mock(tmp_abs.join("missing"));
+        let err = missing.clone().remove().unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert_eq!(missing.as_path(), err.path());
+        assert!(!err.to_string().contains("not empty"));
+
+        let file = PathFile::create(tmp_abs.join("file")).unwrap();
+        let err = PathDir::mock(file.join("dir")).remove().unwrap_err();
+        assert_ne!(io::ErrorKind::DirectoryNotEmpty, err.io_error().kind());
+        #[cfg(unix)]
+        assert_eq!(Some(::libc::ENOTDIR), err.io_error().raw_os_error());
+    }
 
     #[test]
-    fn sanity_list() {
+    fn sanity_ensure_empty() {
         let tmp_dir = TempDir::new("example").expect("create temp dir");
         let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let path = tmp_abs.join("a/out");
 
-        let foo_dir = PathDir::create(tmp_abs.join("foo")).unwrap();
-        let bar_file = PathFile::create(tmp_abs.join("bar.txt")).unwrap();
+        let out = PathDir::ensure_empty(&path, false).unwrap();
+        assert!(out.is_empty().unwrap());
+        assert_eq!(out, PathDir::ensure_empty(&path, false).unwrap());
 
-        let mut result = HashSet::new();
-        for p in tmp_abs.list().unwrap() {
-            result.insert(p.unwrap());
+        PathFile::create(out.join("b.txt")).unwrap();
+        let err = PathDir::ensure_empty(&path, false).unwrap_err();
+        assert_eq!(io::ErrorKind::DirectoryNotEmpty, err.io_error().kind());
+        assert_eq!(out.as_path(), err.path());
+        assert_eq!(
+            format!(
+                "directory is not empty, it contains b.txt when ensuring empty {}",
+                out.display()
+            ),
+            err.to_string()
+        );
+
+        PathFile::create(out.join("a.txt")).unwrap();
+        PathFile::create_all(out.join("d/nested.txt")).unwrap();
+        PathFile::create(out.join("c.txt")).unwrap();
+        let err = PathDir::ensure_empty(&path, false).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("directory is not empty, it contains a.txt, b.txt, c.txt and 1 more"));
+        assert_eq!(4, out.list().unwrap().count());
+
+        let wiped = PathDir::ensure_empty(&path, true).unwrap();
+        assert_eq!(out, wiped);
+        assert!(wiped.is_empty().unwrap());
+
+        let file = PathFile::create(tmp_abs.join("file")).unwrap();
+        assert!(PathDir::ensure_empty(&file, true).is_err());
+        assert!(file.exists());
+    }
+
+    #[test]
+    fn sanity_remove_all() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let root = PathDir::create_all(tmp_abs.join("root/a/b")).unwrap();
+        let root = root.parent_dir().unwrap().parent_dir().unwrap();
+        PathFile::create(root.join("file")).unwrap();
+        PathFile::create(root.join("a/file")).unwrap();
+        let locked = PathFile::create(root.join("a/b/file")).unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let b = PathDir::new(root.join("a/b")).unwrap();
+            b.set_permissions(fs::Permissions::from_mode(0o555)).unwrap();
+            // Permissions are not enforced for root.
+            if let Err(err) = root.clone().remove_all() {
+                assert_eq!(locked.as_path(), err.path());
+                assert_eq!("removing", err.action());
+                b.set_permissions(fs::Permissions::from_mode(0o755)).unwrap();
+            }
         }
 
-        let mut expected = HashSet::new();
-        expected.insert(PathType::Dir(foo_dir.clone()));
-        expected.insert(PathType::File(bar_file.clone()));
+        if root.exists() {
+            root.clone().remove_all().unwrap();
+        }
+        assert!(!root.exists());
+        assert!(!locked.exists());
+        assert_eq!(0, tmp_abs.list().unwrap().count());
+    }
 
-        assert_eq!(expected, result);
+    #[test]
+    fn sanity_remove_contents() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let outside = PathDir::create(tmp_abs.join("outside")).unwrap();
+        let target = PathFile::create(outside.join("keep")).unwrap();
 
-        // just ensure that this compiles
-        let _: PathAbs = foo_dir.into();
-        let _: PathAbs = bar_file.into();
+        let cache = PathDir::create(tmp_abs.join("cache")).unwrap();
+        PathFile::create(cache.join("file")).unwrap();
+        PathDir::create_all(cache.join("a/b")).unwrap();
+        PathFile::create(cache.join("a/b/file")).unwrap();
+        outside.symlink(cache.join("link")).unwrap();
+        PathDir::new(cache.join("a"))
+            .unwrap()
+            .symlink(cache.join("a/b/loop"))
+            .unwrap();
+
+        cache.remove_contents().unwrap();
+        assert!(cache.exists());
+        assert_eq!(0, cache.list().unwrap().count());
+        assert!(target.exists());
+
+        // Already empty
+        cache.remove_contents().unwrap();
     }
-}
 
-#[cfg(unix)]
-fn symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
-    ::std::os::unix::fs::symlink(src, dst)
-}
+    #[test]
+    fn sanity_remove_all_progress() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let outside = PathDir::create(tmp_abs.join("outside")).unwrap();
+        let target = PathFile::create(outside.join("keep")).unwrap();
 
-#[cfg(windows)]
-fn symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
-    ::std::os::windows::fs::symlink_dir(src, dst)
+        let cache = PathDir::create(tmp_abs.join("cache")).unwrap();
+        let mut created = vec![cache.to_path_buf()];
+        for i in 0..3 {
+            let dir = PathDir::create_all(cache.join(format!("{}/nested", i))).unwrap();
+            created.push(dir.parent().unwrap().to_path_buf());
+            created.push(dir.to_path_buf());
+            for j in 0..3 {
+                let file = PathFile::create(dir.join(format!("{}.txt", j))).unwrap();
+                created.push(file.to_path_buf());
+            }
+        }
+        outside.symlink(cache.join("link")).unwrap();
+        created.push(cache.join("link").to_path_buf());
+
+        let plan = cache.remove_all_plan().unwrap();
+        assert_eq!(created.len(), plan.len());
+        assert_eq!(cache.as_path(), plan.last().unwrap().as_path());
+        // Contents come before their directory
+        for (i, path) in plan.iter().enumerate() {
+            assert!(!plan[..i].iter().any(|p| path.starts_with(p)));
+        }
+        assert!(cache.exists());
+
+        let mut removed = Vec::new();
+        cache
+            .clone()
+            .remove_all_with_progress(|path| {
+                assert!(!path.exists());
+                removed.push(path.to_path_buf());
+            })
+            .unwrap();
+        assert_eq!(created.len(), removed.len());
+        assert!(!cache.exists());
+        assert!(target.exists());
+
+        let created: HashSet<_> = created.into_iter().collect();
+        assert_eq!(created, plan.into_iter().collect());
+        assert_eq!(created, removed.into_iter().collect());
+    }
+
+    #[test]
+    fn sanity_rename() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let dir = PathDir::create(tmp_abs.join("dir")).unwrap();
+        PathFile::create_all(dir.join("a/file")).unwrap();
+        let path = dir.to_path_buf();
+
+        let moved = dir.rename(tmp_abs.join("moved")).unwrap();
+        assert!(!path.exists());
+        assert_eq!(tmp_abs.join("moved").as_path(), moved.as_path());
+        assert!(moved.join("a/file").exists());
+
+        let missing = PathDir(PathAbs::mock(tmp_abs.join("missing")));
+        let err = missing.rename(tmp_abs.join("other")).unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert_eq!(tmp_abs.join("missing").as_path(), err.path());
+        assert_eq!(
+            format!("renaming to {} from", tmp_abs.join("other").display()),
+            err.action()
+        );
+
+        // The fallback for different filesystems
+        let moved = moved.rename_by_copy(&tmp_abs.join("copied")).unwrap();
+        assert!(!tmp_abs.join("moved").exists());
+        assert!(moved.join("a/file").exists());
+
+        // A failed copy keeps the source
+        let other = PathDir::create(tmp_abs.join("other")).unwrap();
+        PathFile::create_all(other.join("a/file")).unwrap();
+        assert!(moved.clone().rename_by_copy(&other).is_err());
+        assert!(moved.join("a/file").exists());
+        assert!(other.join("a/file").exists());
+    }
+
+    #[test]
+    fn sanity_dir_is_a_file() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("file")).unwrap();
+
+        for err in [PathDir::create(&file).unwrap_err(), PathDir::new(&file).unwrap_err()] {
+            assert_eq!(io::ErrorKind::NotADirectory, err.io_error().kind());
+            assert_eq!(file.as_path(), err.path());
+            assert!(err.to_string().contains("path exists but is not a directory"));
+        }
+    }
 }
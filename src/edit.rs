@@ -163,6 +163,24 @@ impl FileEdit {
             .map_err(|err| Error::new(err, "writing", self.path.clone().into()))
     }
 
+    /// Read from the file at `offset` into `buf`, returning the number of bytes read.
+    ///
+    /// This does not use (or change) the cursor of the file on unix, but it may on Windows. Like
+    /// `io::Read::read`, fewer bytes than requested can be read, i.e. at the end of the file.
+    pub fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize> {
+        self.0.read_at(buf, offset)
+    }
+
+    /// Write all of `buf` to the file at `offset`, extending the file if necessary.
+    ///
+    /// This does not use (or change) the cursor of the file on unix, but it may on Windows.
+    ///
+    /// > On unix, files opened in append mode always write to the end of the file regardless
+    /// > of `offset`.
+    pub fn write_at(&self, buf: &[u8], offset: u64) -> Result<()> {
+        self.0.write_at(buf, offset)
+    }
+
     /// `std::io::File::flush` buth with the new error type.
     pub fn flush(&mut self) -> Result<()> {
         self.0
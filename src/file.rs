@@ -5,13 +5,16 @@
  * http://opensource.org/licenses/MIT>, at your option. This file may not be
  * copied, modified, or distributed except according to those terms.
  */
+use std::ffi::OsStr;
 use std::fs;
 use std::fmt;
 use std::io;
+use std::process;
+use std::time::SystemTime;
 use std_prelude::*;
 
 use super::{Error, Result};
-use super::{FileEdit, FileRead, FileWrite, PathDir, PathAbs, PathArc};
+use super::{FileEdit, FileRead, FileWrite, Lines, PathDir, PathAbs, PathArc, PathOpenOptions};
 
 #[derive(Clone, Eq, Hash, PartialEq, PartialOrd, Ord)]
 /// a `PathAbs` that was a file at the time of initialization, with associated methods.
@@ -20,7 +23,8 @@ pub struct PathFile(pub(crate) PathAbs);
 impl PathFile {
     /// Instantiate a new `PathFile`. The file must exist or `io::Error` will be returned.
     ///
-    /// Returns `io::ErrorKind::InvalidInput` if the path exists but is not a file.
+    /// Returns `io::ErrorKind::IsADirectory` if the path is a directory and
+    /// `io::ErrorKind::InvalidInput` if it is otherwise not a file.
     ///
     /// # Examples
     /// ```rust
@@ -36,6 +40,50 @@ impl PathFile {
         PathFile::from_abs(abs)
     }
 
+    /// Find the executable `name` in the directories of the `PATH` environment variable, like
+    /// the `which` command.
+    ///
+    /// The first file which exists (and is executable on unix) is returned. On Windows each
+    /// extension in `PATHEXT` (i.e. `.exe`) is also tried. If `name` contains a path separator
+    /// then `PATH` is not searched and the file is only checked.
+    ///
+    /// Returns `io::ErrorKind::NotFound` if no executable was found.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// # #[cfg(unix)] {
+    /// let sh = PathFile::which("sh")?;
+    /// assert!(sh.is_executable()?);
+    /// # }
+    /// assert!(PathFile::which("not-a-real-executable-name").is_err());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn which<S: AsRef<OsStr>>(name: S) -> Result<PathFile> {
+        let name = Path::new(name.as_ref());
+        if name.components().count() > 1 {
+            return PathFile::new(name);
+        }
+        let paths = ::std::env::var_os("PATH").unwrap_or_default();
+        for dir in ::std::env::split_paths(&paths) {
+            for candidate in executable_candidates(&dir.join(name)) {
+                if let Ok(file) = PathFile::new(&candidate) {
+                    if file.is_executable().unwrap_or(false) {
+                        return Ok(file);
+                    }
+                }
+            }
+        }
+        Err(Error::new(
+            io::Error::new(io::ErrorKind::NotFound, "executable not found in PATH"),
+            "searching for",
+            PathArc::new(name),
+        ))
+    }
+
     /// Get the parent directory of this file as a `PathDir`.
     ///
     /// > This does not make aditional syscalls, as the parent by definition must be a directory
@@ -62,7 +110,7 @@ impl PathFile {
     /// Consume the `PathAbs` validating that the path is a file and returning `PathFile`. The file
     /// must exist or `io::Error` will be returned.
     ///
-    /// If the path is actually a dir returns `io::ErrorKind::InvalidInput`.
+    /// If the path is actually a dir returns `io::ErrorKind::IsADirectory`.
     ///
     /// # Examples
     /// ```rust
@@ -77,6 +125,8 @@ impl PathFile {
     pub fn from_abs(abs: PathAbs) -> Result<PathFile> {
         if abs.is_file() {
             Ok(PathFile::from_abs_unchecked(abs))
+        } else if abs.is_dir() {
+            Err(Error::new(is_a_directory(), "resolving", abs.into()))
         } else {
             Err(Error::new(
                 io::Error::new(io::ErrorKind::InvalidInput, "path is not a file"),
@@ -97,6 +147,8 @@ impl PathFile {
 
     /// Instantiate a new `PathFile`, creating an empty file if it doesn't exist.
     ///
+    /// Returns `io::ErrorKind::IsADirectory` if the path is a directory.
+    ///
     /// # Examples
     /// ```rust
     /// # extern crate path_abs;
@@ -122,66 +174,91 @@ impl PathFile {
             .write(true)
             .create(true)
             .open(&path)
-            .map_err(|err| Error::new(err, "opening", PathArc::new(&path)))?;
+            .map_err(|err| {
+                if path.as_ref().is_dir() {
+                    Error::new(is_a_directory(), "creating", PathArc::new(&path))
+                } else {
+                    Error::new(err, "opening", PathArc::new(&path))
+                }
+            })?;
         PathFile::new(path)
     }
 
-    /// Read the entire contents of the file into a `String`.
+    /// Instantiate a new `PathFile`, creating an empty file and failing with
+    /// `io::ErrorKind::AlreadyExists` if it already exists.
+    ///
+    /// Checking for the file and creating it is a single atomic operation, so only one caller
+    /// can ever create the file (i.e. for lock files).
     ///
     /// # Examples
     /// ```rust
     /// # extern crate path_abs;
     /// # extern crate tempdir;
+    /// use std::io;
     /// use path_abs::PathFile;
     ///
     /// # fn try_main() -> ::std::io::Result<()> {
-    /// let example = "example.txt";
+    /// let example = "example.lock";
     /// # let tmp = tempdir::TempDir::new("ex")?;
     /// # let example = &tmp.path().join(example);
-    /// let file = PathFile::create(example)?;
     ///
-    /// let expected = "foo\nbar";
-    /// file.write_str(expected)?;
-    /// assert_eq!(expected, file.read_string()?);
+    /// let lock = PathFile::create_new(example)?;
+    ///
+    /// let err = PathFile::create_new(example).unwrap_err();
+    /// assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    pub fn read_string(&self) -> Result<String> {
-        let mut f = self.read()?;
-        f.read_string()
+    pub fn create_new<P: AsRef<Path>>(path: P) -> Result<PathFile> {
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|err| Error::new(err, "creating", PathArc::new(&path)))?;
+        PathFile::new(path)
     }
 
-    /// Write the `str` to a file, truncating it first if it exists and creating it otherwise.
+    /// Instantiate a new `PathFile` like `create`, but first create all of its missing parent
+    /// directories (like `mkdir -p` followed by `touch`).
+    ///
+    /// If a parent can't be created the error has the action `creating parent directory` and
+    /// the path of the parent.
     ///
     /// # Examples
     /// ```rust
     /// # extern crate path_abs;
     /// # extern crate tempdir;
-    /// use path_abs::PathFile;
+    /// use path_abs::{PathDir, PathFile};
     ///
     /// # fn try_main() -> ::std::io::Result<()> {
-    /// let example = "example.txt";
+    /// let example = "a/b/c/d.txt";
     /// # let tmp = tempdir::TempDir::new("ex")?;
     /// # let example = &tmp.path().join(example);
-    /// let file = PathFile::create(example)?;
     ///
-    /// let expected = "foo\nbar";
-    /// file.write_str(expected)?;
-    /// assert_eq!(expected, file.read_string()?);
+    /// let file = PathFile::create_all(example)?;
+    /// assert!(file.parent_dir().unwrap().ends_with("a/b/c"));
+    ///
+    /// // It can be done twice with no effect.
+    /// let _ = PathFile::create_all(example)?;
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    pub fn write_str(&self, s: &str) -> Result<()> {
-        let mut options = fs::OpenOptions::new();
-        options.create(true);
-        options.truncate(true);
-        let mut f = FileWrite::open_path(self.clone(), options)?;
-        if s.is_empty() {
-            return Ok(());
+    pub fn create_all<P: AsRef<Path>>(path: P) -> Result<PathFile> {
+        let path = path.as_ref();
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => {
+                fs::create_dir_all(parent).map_err(|err| {
+                    Error::new(err, "creating parent directory", PathArc::new(parent))
+                })?;
+            }
+            _ => {}
         }
-        f.write_str(s)?;
-        f.flush()
+        PathFile::create(path)
     }
 
-    /// Append the `str` to a file, creating it if it doesn't exist.
+    /// Instantiate a new `PathFile`, creating an empty file if it doesn't exist and otherwise
+    /// updating its modification time to now (like the `touch` command).
+    ///
+    /// An existing file is not opened, so its contents are not changed and it does not need to be
+    /// writable.
     ///
     /// # Examples
     /// ```rust
@@ -193,58 +270,63 @@ impl PathFile {
     /// let example = "example.txt";
     /// # let tmp = tempdir::TempDir::new("ex")?;
     /// # let example = &tmp.path().join(example);
-    /// let file = PathFile::create(example)?;
     ///
-    /// let expected = "foo\nbar\nbaz";
-    /// file.append_str("foo\nbar")?;
-    /// file.append_str("\nbaz")?;
-    /// assert_eq!(expected, file.read_string()?);
+    /// let file = PathFile::touch(example)?;
+    /// file.write_str("foo")?;
+    ///
+    /// let file = PathFile::touch(example)?;
+    /// assert_eq!("foo", file.read_string()?);
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    pub fn append_str(&self, s: &str) -> Result<()> {
-        let mut f = self.append()?;
-        if s.is_empty() {
-            return Ok(());
+    pub fn touch<P: AsRef<Path>>(path: P) -> Result<PathFile> {
+        match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(_) => PathFile::new(path),
+            Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                let file = PathFile::new(path)?;
+                file.set_modified(SystemTime::now())?;
+                Ok(file)
+            }
+            Err(err) => Err(Error::new(err, "creating", PathArc::new(&path))),
         }
-        f.write_str(s)?;
-        f.flush()
     }
 
-    /// Open the file as read-only.
+    /// Instantiate a new `PathFile` containing `contents`, replacing the file if it already
+    /// exists.
+    ///
+    /// The contents are written to a temporary file in the same directory which is then renamed
+    /// into place, so other processes never observe an empty or partially written file.
     ///
     /// # Examples
     /// ```rust
     /// # extern crate path_abs;
     /// # extern crate tempdir;
-    /// use std::io::Read;
     /// use path_abs::PathFile;
     ///
     /// # fn try_main() -> ::std::io::Result<()> {
     /// let example = "example.txt";
     /// # let tmp = tempdir::TempDir::new("ex")?;
     /// # let example = &tmp.path().join(example);
-    /// let file = PathFile::create(example)?;
     ///
-    /// let expected = "foo\nbar";
-    /// file.write_str(expected)?;
+    /// let file = PathFile::create_with(example, b"foo\n")?;
+    /// assert_eq!("foo\n", file.read_string()?);
     ///
-    /// let mut read = file.read()?;
-    /// let mut s = String::new();
-    /// read.read_to_string(&mut s)?;
-    /// assert_eq!(expected, s);
+    /// // An existing file is replaced.
+    /// let file = PathFile::create_with(example, b"bar\n")?;
+    /// assert_eq!("bar\n", file.read_string()?);
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    pub fn read(&self) -> Result<FileRead> {
-        FileRead::read_path(self.clone())
+    pub fn create_with<P: AsRef<Path>>(path: P, contents: &[u8]) -> Result<PathFile> {
+        let abs = PathAbs::new(path)?;
+        replace_atomic(&abs, contents)?;
+        Ok(PathFile::from_abs_unchecked(abs))
     }
 
-    /// Open the file as write-only in append mode.
+    /// Read the entire contents of the file into a `String`.
     ///
     /// # Examples
     /// ```rust
     /// # extern crate path_abs;
     /// # extern crate tempdir;
-    /// use std::io::Write;
     /// use path_abs::PathFile;
     ///
     /// # fn try_main() -> ::std::io::Result<()> {
@@ -253,205 +335,1343 @@ impl PathFile {
     /// # let example = &tmp.path().join(example);
     /// let file = PathFile::create(example)?;
     ///
-    /// let expected = "foo\nbar\n";
-    /// file.write_str("foo\n")?;
-    ///
-    /// let mut append = file.append()?;
-    /// append.write_all(b"bar\n")?;
-    /// append.flush();
+    /// let expected = "foo\nbar";
+    /// file.write_str(expected)?;
     /// assert_eq!(expected, file.read_string()?);
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    pub fn append(&self) -> Result<FileWrite> {
-        let mut options = fs::OpenOptions::new();
-        options.append(true);
-        FileWrite::open_path(self.clone(), options)
+    pub fn read_string(&self) -> Result<String> {
+        let mut f = self.read()?;
+        f.read_string()
     }
 
-    /// Open the file for editing (reading and writing).
+    /// Read the entire contents of the file into a `Vec<u8>`.
     ///
     /// # Examples
     /// ```rust
     /// # extern crate path_abs;
     /// # extern crate tempdir;
-    /// use std::io::{Read, Seek, Write, SeekFrom};
     /// use path_abs::PathFile;
     ///
     /// # fn try_main() -> ::std::io::Result<()> {
-    /// let example = "example.txt";
+    /// let example = "example.bin";
     /// # let tmp = tempdir::TempDir::new("ex")?;
     /// # let example = &tmp.path().join(example);
     /// let file = PathFile::create(example)?;
     ///
-    /// let expected = "foo\nbar";
-    ///
-    /// let mut edit = file.edit()?;
-    /// let mut s = String::new();
+    /// // An empty file reads as an empty `Vec`
+    /// assert!(file.read_bytes()?.is_empty());
     ///
-    /// edit.write_all(expected.as_bytes())?;
-    /// edit.seek(SeekFrom::Start(0))?;
-    /// edit.read_to_string(&mut s)?;
-    /// assert_eq!(expected, s);
+    /// file.write_str("foo\nbar")?;
+    /// assert_eq!(b"foo\nbar".to_vec(), file.read_bytes()?);
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    pub fn edit(&self) -> Result<FileEdit> {
-        FileEdit::open_path(self.clone(), fs::OpenOptions::new())
+    pub fn read_bytes(&self) -> Result<Vec<u8>> {
+        let mut f = self.read()?;
+        f.read_bytes()
     }
 
-    /// Copy the file to another location, including permission bits
+    /// Read the entire contents of the file into a `String` like `read_string`, unless it is
+    /// larger than `max` bytes.
     ///
-    /// # Examples
+    /// The size is checked before reading and again while reading (in case the file grows), so
+    /// at most `max + 1` bytes are ever read. A file which is too large returns
+    /// `io::ErrorKind::FileTooLarge` with the limit in the message.
     ///
+    /// # Examples
     /// ```rust
     /// # extern crate path_abs;
     /// # extern crate tempdir;
+    /// use std::io;
     /// use path_abs::PathFile;
-    /// use std::path::Path;
     ///
     /// # fn try_main() -> ::std::io::Result<()> {
     /// let example = "example.txt";
-    /// let example_bk = "example.txt.bk";
     /// # let tmp = tempdir::TempDir::new("ex")?;
     /// # let example = &tmp.path().join(example);
-    /// # let example_bk = &tmp.path().join(example_bk);
-    /// let file = PathFile::create(example)?;
+    /// let file = PathFile::create_with(example, b"foo\nbar")?;
     ///
-    /// let contents = "This is some contents";
-    /// file.write_str(contents);
-    /// let file_bk = file.copy(example_bk)?;
-    /// assert_eq!(contents, file.read_string()?);
-    /// assert_eq!(contents, file_bk.read_string()?);
+    /// assert_eq!("foo\nbar", file.read_string_limited(1024)?);
+    /// let err = file.read_string_limited(4).unwrap_err();
+    /// assert_eq!(io::ErrorKind::FileTooLarge, err.io_error().kind());
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    pub fn copy<P: AsRef<Path>>(&self, path: P) -> Result<PathFile> {
-        fs::copy(&self, &path).map_err(|err| {
-            Error::new(
-                err,
-                &format!("copying {} from", path.as_ref().display()),
-                self.clone().into(),
-            )
-        })?;
-        Ok(PathFile::new(path)?)
+    pub fn read_string_limited(&self, max: u64) -> Result<String> {
+        let bytes = self.read_bytes_limited(max)?;
+        String::from_utf8(bytes).map_err(|_| {
+            let err = io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            );
+            Error::new(err, "reading", self.clone().into())
+        })
     }
 
-    /// Rename a file, replacing the original file if `to` already exists.
+    /// Read the entire contents of the file into a `Vec<u8>` like `read_bytes`, unless it is
+    /// larger than `max` bytes.
     ///
-    /// This will not work if the new name is on a different mount point.
+    /// See [`read_string_limited`](struct.PathFile.html#method.read_string_limited).
+    pub fn read_bytes_limited(&self, max: u64) -> Result<Vec<u8>> {
+        let too_large = || {
+            let err = io::Error::new(
+                io::ErrorKind::FileTooLarge,
+                format!("file is larger than the limit of {} bytes", max),
+            );
+            Error::new(err, "reading", self.clone().into())
+        };
+
+        let mut f = self.read()?;
+        let len = f.metadata()?.len();
+        if len > max {
+            return Err(too_large());
+        }
+        let mut bytes = Vec::with_capacity(len as usize);
+        (&mut f)
+            .take(max.saturating_add(1))
+            .read_to_end(&mut bytes)
+            .map_err(|err| Error::new(err, "reading", self.clone().into()))?;
+        if bytes.len() as u64 > max {
+            return Err(too_large());
+        }
+        Ok(bytes)
+    }
+
+    /// Read the entire contents of the file into a `String`, decoding it according to its byte
+    /// order mark (BOM).
+    ///
+    /// UTF-8, UTF-16LE and UTF-16BE BOMs are detected and removed. Files without a BOM are read
+    /// as UTF-8 like `read_string`. Invalid contents return `io::ErrorKind::InvalidData` with
+    /// the detected encoding in the message.
     ///
     /// # Examples
     /// ```rust
     /// # extern crate path_abs;
     /// # extern crate tempdir;
     /// use path_abs::PathFile;
-    /// use std::path::Path;
     ///
     /// # fn try_main() -> ::std::io::Result<()> {
     /// let example = "example.txt";
-    /// let example_bk = "example.txt.bk";
     /// # let tmp = tempdir::TempDir::new("ex")?;
     /// # let example = &tmp.path().join(example);
-    /// # let example_bk = &tmp.path().join(example_bk);
-    /// let file = PathFile::create(example)?;
-    ///
-    /// let contents = "This is some contents";
-    /// file.write_str(contents);
-    /// let file_bk = file.clone().rename(example_bk)?;
-    /// assert!(!file.exists());
-    /// assert_eq!(contents, file_bk.read_string()?);
+    /// // "hi" as UTF-16LE, with a BOM
+    /// let file = PathFile::create_with(example, b"\xff\xfeh\x00i\x00")?;
+    /// assert_eq!("hi", file.read_string_detect()?);
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    pub fn rename<P: AsRef<Path>>(self, to: P) -> Result<PathFile> {
-        fs::rename(&self, &to).map_err(|err| {
-            Error::new(
-                err,
-                &format!("renaming to {} from", to.as_ref().display()),
-                self.clone().into(),
-            )
-        })?;
-        Ok(PathFile::new(to)?)
+    pub fn read_string_detect(&self) -> Result<String> {
+        let bytes = self.read_bytes()?;
+        let invalid = |encoding: &str| {
+            let err = io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("stream did not contain valid {}", encoding),
+            );
+            Error::new(err, "reading", self.clone().into())
+        };
+
+        let (encoding, big_endian, body) = if bytes.starts_with(b"\xef\xbb\xbf") {
+            ("UTF-8", None, &bytes[3..])
+        } else if bytes.starts_with(b"\xff\xfe") {
+            ("UTF-16LE", Some(false), &bytes[2..])
+        } else if bytes.starts_with(b"\xfe\xff") {
+            ("UTF-16BE", Some(true), &bytes[2..])
+        } else {
+            ("UTF-8", None, &bytes[..])
+        };
+
+        match big_endian {
+            None => ::std::str::from_utf8(body)
+                .map(|s| s.to_string())
+                .map_err(|_| invalid(encoding)),
+            Some(big_endian) => {
+                if body.len() % 2 != 0 {
+                    return Err(invalid(encoding));
+                }
+                let units: Vec<u16> = body
+                    .chunks(2)
+                    .map(|c| {
+                        if big_endian {
+                            u16::from_be_bytes([c[0], c[1]])
+                        } else {
+                            u16::from_le_bytes([c[0], c[1]])
+                        }
+                    })
+                    .collect();
+                String::from_utf16(&units).map_err(|_| invalid(encoding))
+            }
+        }
     }
 
-    /// Creates a new symbolic link on the filesystem to the dst.
+    /// Read the file line by line, without reading all of it into memory.
     ///
-    /// This handles platform specific behavior correctly.
+    /// See [`Lines`](struct.Lines.html) for details.
     ///
     /// # Examples
-    ///
     /// ```rust
     /// # extern crate path_abs;
     /// # extern crate tempdir;
     /// use path_abs::PathFile;
-    /// use std::path::Path;
     ///
     /// # fn try_main() -> ::std::io::Result<()> {
     /// let example = "example.txt";
-    /// let example_sym = "example.txt.sym";
     /// # let tmp = tempdir::TempDir::new("ex")?;
     /// # let example = &tmp.path().join(example);
-    /// # let example_sym = &tmp.path().join(example_sym);
     /// let file = PathFile::create(example)?;
+    /// file.write_str("foo\nbar\r\nbaz")?;
     ///
-    /// let contents = "This is some contents";
-    /// file.write_str(contents);
-    /// let file_sym = file.symlink(example_sym)?;
+    /// let mut lines = Vec::new();
+    /// for line in file.read_lines()? {
+    ///     lines.push(line?);
+    /// }
+    /// assert_eq!(vec!["foo", "bar", "baz"], lines);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn read_lines(&self) -> Result<Lines> {
+        Ok(Lines::new(self.read()?))
+    }
+
+    /// Read the first `n` lines of the file, or all of them if it has fewer.
     ///
-    /// // They have a different "absolute path"
-    /// assert_ne!(file, file_sym);
+    /// Lines are split like `read_lines` and reading stops after the `n`th line.
     ///
-    /// // But they can be canonicalized to the same file.
-    /// let file_can = file_sym.canonicalize()?;
-    /// assert_eq!(file, file_can);
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.log";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create_with(example, b"one\ntwo\nthree\nfour")?;
+    ///
+    /// assert_eq!(vec!["one", "two"], file.head(2)?);
+    /// assert_eq!(vec!["three", "four"], file.tail(2)?);
+    /// assert_eq!(4, file.tail(10)?.len());
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    pub fn symlink<P: AsRef<Path>>(&self, dst: P) -> Result<PathFile> {
-        symlink_file(&self, &dst).map_err(|err| {
+    pub fn head(&self, n: usize) -> Result<Vec<String>> {
+        self.read_lines()?.take(n).collect()
+    }
+
+    /// Read the last `n` lines of the file, or all of them if it has fewer.
+    ///
+    /// The file is read backwards in chunks from the end until `n` lines have been found, so
+    /// only the end of a large file is read. See [`head`](struct.PathFile.html#method.head) for
+    /// an example.
+    pub fn tail(&self, n: usize) -> Result<Vec<String>> {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        let f = self.read()?;
+        let mut pos = f.metadata()?.len();
+        let end = pos;
+
+        // Chunks read from the end, in reverse order.
+        let mut chunks: Vec<Vec<u8>> = Vec::new();
+        let mut newlines = 0;
+        let start = loop {
+            if pos == 0 {
+                break None;
+            }
+            let len = ::std::cmp::min(pos, CHUNK_SIZE as u64);
+            pos -= len;
+            let mut chunk = vec![0; len as usize];
+            let mut read = 0;
+            while read < chunk.len() {
+                match f.read_at(&mut chunk[read..], pos + read as u64)? {
+                    0 => break,
+                    l => read += l,
+                }
+            }
+            chunk.truncate(read);
+
+            // The newline ending the last line doesn't start a new line.
+            let search = if pos + len == end && chunk.last() == Some(&b'\n') {
+                &chunk[..chunk.len() - 1]
+            } else {
+                &chunk[..]
+            };
+            let mut found = None;
+            for (i, b) in search.iter().enumerate().rev() {
+                if *b == b'\n' {
+                    newlines += 1;
+                    if newlines == n {
+                        found = Some(i + 1);
+                        break;
+                    }
+                }
+            }
+            chunks.push(chunk);
+            if found.is_some() {
+                break found;
+            }
+        };
+
+        let mut bytes = Vec::new();
+        let mut chunks = chunks.into_iter().rev();
+        if let Some(first) = chunks.next() {
+            bytes.extend_from_slice(&first[start.unwrap_or(0)..]);
+        }
+        for chunk in chunks {
+            bytes.extend_from_slice(&chunk);
+        }
+        let s = String::from_utf8(bytes).map_err(|err| {
             Error::new(
-                err,
-                &format!("linking from {} to", dst.as_ref().display()),
+                io::Error::new(io::ErrorKind::InvalidData, err),
+                "reading lines of",
                 self.clone().into(),
             )
         })?;
-        PathFile::new(dst)
+        Ok(s.lines().map(|l| l.to_string()).collect())
     }
 
-    /// Remove (delete) the file from the filesystem, consuming self.
+    /// Write the `str` to a file, truncating it first if it exists and creating it otherwise.
     ///
     /// # Examples
-    ///
     /// ```rust
     /// # extern crate path_abs;
     /// # extern crate tempdir;
     /// use path_abs::PathFile;
-    /// use std::path::Path;
     ///
     /// # fn try_main() -> ::std::io::Result<()> {
     /// let example = "example.txt";
     /// # let tmp = tempdir::TempDir::new("ex")?;
     /// # let example = &tmp.path().join(example);
     /// let file = PathFile::create(example)?;
-    /// assert!(file.exists());
-    /// file.remove()?;
-    ///
-    /// // file.exists() <--- COMPILER ERROR, `file` was consumed
     ///
-    /// assert!(!Path::new(example).exists());
+    /// let expected = "foo\nbar";
+    /// file.write_str(expected)?;
+    /// assert_eq!(expected, file.read_string()?);
     /// # Ok(()) } fn main() { try_main().unwrap() }
     /// ```
-    pub fn remove(self) -> Result<()> {
-        fs::remove_file(&self).map_err(|err| Error::new(err, "removing", self.into()))
+    pub fn write_str(&self, s: &str) -> Result<()> {
+        let mut options = fs::OpenOptions::new();
+        options.create(true);
+        options.truncate(true);
+        let mut f = FileWrite::open_path(self.clone(), options)?;
+        if s.is_empty() {
+            return Ok(());
+        }
+        f.write_str(s)?;
+        f.flush()
     }
 
-    /// Return a reference to a basic `std::path::Path`
-    pub fn as_path(&self) -> &Path {
-        self.as_ref()
+    /// Write the bytes to a file, truncating it first if it exists and creating it otherwise.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.bin";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// file.write_bytes(b"\x00\x01\x02\x03\x04\x05")?;
+    /// assert_eq!(b"\x00\x01\x02\x03\x04\x05".to_vec(), file.read_bytes()?);
+    ///
+    /// // Shorter contents replace the longer ones entirely.
+    /// file.write_bytes(b"\xff\xfe")?;
+    /// assert_eq!(b"\xff\xfe".to_vec(), file.read_bytes()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn write_bytes(&self, bytes: &[u8]) -> Result<()> {
+        let mut options = fs::OpenOptions::new();
+        options.create(true);
+        options.truncate(true);
+        let mut f = FileWrite::open_path(self.clone(), options)?;
+        if bytes.is_empty() {
+            return Ok(());
+        }
+        f.write_bytes(bytes)?;
+        f.flush()
     }
 
-    /// Returns the canonical form of the path with all intermediate components normalized and
-    /// symbolic links resolved.
+    /// Write the `str` to a file like `write_str`, but only return once the contents are durably
+    /// on disk.
     ///
-    /// See [`PathAbs::canonicalize`]
+    /// The file is synced and then (on unix) so is its parent directory, so that the directory
+    /// entry is also durable. Combine `write_str_atomic` with
+    /// [`PathDir::sync_all`](struct.PathDir.html#method.sync_all) for a durable atomic write.
     ///
-    /// [`PathAbs::canonicalize`]: struct.PathAbs.html#method.canonicalize
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// file.write_str_sync("foo\nbar")?;
+    /// assert_eq!("foo\nbar", file.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn write_str_sync(&self, s: &str) -> Result<()> {
+        self.write_bytes_sync(s.as_bytes())
+    }
+
+    /// Write the bytes to a file like `write_bytes`, but only return once the contents are
+    /// durably on disk.
+    ///
+    /// See [`write_str_sync`](struct.PathFile.html#method.write_str_sync) for details.
+    pub fn write_bytes_sync(&self, bytes: &[u8]) -> Result<()> {
+        let mut options = fs::OpenOptions::new();
+        options.create(true);
+        options.truncate(true);
+        let mut f = FileWrite::open_path(self.clone(), options)?;
+        f.write_bytes(bytes)?;
+        f.sync_all()?;
+        match self.parent_dir() {
+            Some(dir) => dir.sync_all(),
+            None => Ok(()),
+        }
+    }
+
+    /// Atomically replace the contents of the file with the `str`.
+    ///
+    /// The `str` is written and synced to a uniquely named temporary file in the same directory,
+    /// which is then renamed over the file. The file is therefore never observed half-written,
+    /// even if the process is killed during the write.
+    ///
+    /// - The permissions of the original file are preserved.
+    /// - If the file is a symlink, the file it points to is replaced and the symlink is kept.
+    /// - The temporary file is removed if writing or renaming fails.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// file.write_str_atomic("foo\nbar")?;
+    /// assert_eq!("foo\nbar", file.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn write_str_atomic(&self, s: &str) -> Result<()> {
+        replace_atomic(self, s.as_bytes())
+    }
+
+    /// Atomically replace the contents of the file with the bytes.
+    ///
+    /// See [`write_str_atomic`](struct.PathFile.html#method.write_str_atomic) for details.
+    pub fn write_bytes_atomic(&self, bytes: &[u8]) -> Result<()> {
+        replace_atomic(self, bytes)
+    }
+
+    /// Read the contents of the file, transform them with `f` and atomically write the result
+    /// back.
+    ///
+    /// The result is written like `write_str_atomic`, so a panic in `f` leaves the file
+    /// unchanged.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("foo\nbar")?;
+    ///
+    /// file.edit_str(|s| s.to_uppercase())?;
+    /// assert_eq!("FOO\nBAR", file.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn edit_str<F>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce(String) -> String,
+    {
+        self.try_edit_str(|s| Ok::<_, Error>(f(s)))
+    }
+
+    /// Like [`edit_str`](struct.PathFile.html#method.edit_str), except `f` can fail. If it
+    /// does, the file is left unchanged and the error is returned.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::io;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("41")?;
+    ///
+    /// let increment = |s: String| -> io::Result<String> {
+    ///     let value: u32 = s.parse()
+    ///         .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "not a number"))?;
+    ///     Ok((value + 1).to_string())
+    /// };
+    ///
+    /// file.try_edit_str(&increment)?;
+    /// assert_eq!("42", file.read_string()?);
+    ///
+    /// file.write_str("forty two")?;
+    /// assert!(file.try_edit_str(&increment).is_err());
+    /// assert_eq!("forty two", file.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn try_edit_str<F, E>(&self, f: F) -> ::std::result::Result<(), E>
+    where
+        F: FnOnce(String) -> ::std::result::Result<String, E>,
+        E: From<Error>,
+    {
+        let contents = f(self.read_string()?)?;
+        replace_atomic(self, contents.as_bytes())?;
+        Ok(())
+    }
+
+    /// Append the `str` to the end of the file.
+    ///
+    /// The file is not re-created if it has been removed since the `PathFile` was constructed.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// let expected = "foo\nbar\nbaz";
+    /// file.append_str("foo\nbar")?;
+    /// file.append_str("\nbaz")?;
+    /// assert_eq!(expected, file.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn append_str(&self, s: &str) -> Result<()> {
+        let mut f = self.append()?;
+        if s.is_empty() {
+            return Ok(());
+        }
+        f.write_str(s)?;
+        f.flush()
+    }
+
+    /// Append `line` and a newline to the end of the file.
+    ///
+    /// If the file is not empty and doesn't end with a newline, one is written first so that
+    /// lines are never joined together. No newline is added if `line` already ends with one.
+    /// Only the last byte of the file is read.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.log";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// file.append_line("foo")?;
+    /// file.append_str("bar")?;
+    /// file.append_line("baz\n")?;
+    /// assert_eq!("foo\nbar\nbaz\n", file.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn append_line(&self, line: &str) -> Result<()> {
+        let map_err = |err| Error::new(err, "appending to", self.clone().into());
+        let mut f = fs::OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(self)
+            .map_err(|err| Error::new(err, "opening", self.clone().into()))?;
+
+        let mut out = String::with_capacity(line.len() + 2);
+        let len = f.metadata().map_err(map_err)?.len();
+        if len > 0 {
+            let mut last = [0; 1];
+            f.seek(SeekFrom::Start(len - 1)).map_err(map_err)?;
+            f.read_exact(&mut last).map_err(map_err)?;
+            if last[0] != b'\n' {
+                out.push('\n');
+            }
+        }
+        out.push_str(line);
+        if !line.ends_with('\n') {
+            out.push('\n');
+        }
+        f.write_all(out.as_bytes()).map_err(map_err)
+    }
+
+    /// Append the bytes to the end of the file.
+    ///
+    /// The file is not re-created if it has been removed since the `PathFile` was constructed.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.bin";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// file.append_bytes(b"\x00\x01")?;
+    /// file.append_str("foo")?;
+    /// file.append_bytes(b"\x02")?;
+    /// assert_eq!(b"\x00\x01foo\x02".to_vec(), file.read_bytes()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn append_bytes(&self, bytes: &[u8]) -> Result<()> {
+        let mut f = self.append()?;
+        if bytes.is_empty() {
+            return Ok(());
+        }
+        f.write_bytes(bytes)?;
+        f.flush()
+    }
+
+    /// Copy the contents of `reader` into the file, creating it if it doesn't exist and
+    /// truncating it if it does. Returns the number of bytes written.
+    ///
+    /// The contents are streamed in chunks, so they are never all held in memory at once.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::io::Cursor;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// assert_eq!(7, file.write_from(&mut Cursor::new("foo\nbar"))?);
+    /// assert_eq!("foo\nbar", file.read_string()?);
+    ///
+    /// assert_eq!(4, file.append_from(&mut Cursor::new("\nbaz"))?);
+    /// assert_eq!("foo\nbar\nbaz", file.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn write_from<R: Read + ?Sized>(&self, reader: &mut R) -> Result<u64> {
+        let mut options = fs::OpenOptions::new();
+        options.create(true);
+        options.truncate(true);
+        let mut f = FileWrite::open_path(self.clone(), options)?;
+        let written = f.write_from(reader)?;
+        f.flush()?;
+        Ok(written)
+    }
+
+    /// Copy the contents of `reader` to the end of the file, returning the number of bytes
+    /// written.
+    ///
+    /// See [`write_from`](struct.PathFile.html#method.write_from) for an example.
+    pub fn append_from<R: Read + ?Sized>(&self, reader: &mut R) -> Result<u64> {
+        let mut f = self.append()?;
+        let written = f.write_from(reader)?;
+        f.flush()?;
+        Ok(written)
+    }
+
+    /// Get the size of the file in bytes.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    /// assert_eq!(0, file.size()?);
+    ///
+    /// file.write_str("foo\nbar")?;
+    /// assert_eq!(7, file.size()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn size(&self) -> Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+
+    /// Return whether the file is executable.
+    ///
+    /// On unix this is true if _any_ of the execute bits (owner, group or other) are set, the
+    /// current user may still not be allowed to execute it. On Windows this is true if the file
+    /// has one of the `.exe`, `.com`, `.bat` or `.cmd` extensions.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    /// assert!(!file.is_executable()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn is_executable(&self) -> Result<bool> {
+        is_executable(self)
+    }
+
+    /// Truncate or extend the file to `len` bytes.
+    ///
+    /// The contents before `len` are unchanged. If the file is extended, the new bytes are
+    /// zeroes.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("foo bar")?;
+    ///
+    /// file.truncate(3)?;
+    /// assert_eq!("foo", file.read_string()?);
+    ///
+    /// file.truncate(5)?;
+    /// assert_eq!(b"foo\0\0", file.read_bytes()?.as_slice());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn truncate(&self, len: u64) -> Result<()> {
+        let mut f = FileWrite::open_path(self.clone(), fs::OpenOptions::new())?;
+        f.set_len(len)
+    }
+
+    /// Read from the file at `offset` into `buf`, returning the number of bytes read. Fewer
+    /// bytes than requested are read at the end of the file.
+    ///
+    /// This opens the file each time, use `FileRead::read_at` or `FileEdit::read_at` to reuse
+    /// an open handle.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "records.bin";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create_with(example, b"aaaabbbbcccc")?;
+    ///
+    /// file.write_at(b"BBBB", 4)?;
+    /// let mut record = [0; 4];
+    /// assert_eq!(4, file.read_at(&mut record, 4)?);
+    /// assert_eq!(b"BBBB", &record);
+    /// assert_eq!("aaaaBBBBcccc", file.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize> {
+        self.read()?.read_at(buf, offset)
+    }
+
+    /// Write all of `buf` to the file at `offset`, extending the file if necessary. The file is
+    /// not created if it doesn't exist.
+    ///
+    /// This opens the file each time, use `FileWrite::write_at` or `FileEdit::write_at` to reuse
+    /// an open handle. See [`read_at`](struct.PathFile.html#method.read_at) for an example.
+    pub fn write_at(&self, buf: &[u8], offset: u64) -> Result<()> {
+        FileWrite::open_path(self.clone(), fs::OpenOptions::new())?.write_at(buf, offset)
+    }
+
+    /// Return whether the file has the same contents as `other`.
+    ///
+    /// The sizes of the files are compared first and then their contents are streamed and
+    /// compared chunk by chunk, stopping at the first difference. Paths which canonicalize to
+    /// the same file are always equal.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// let example_bk = "example.txt.bk";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// # let example_bk = &tmp.path().join(example_bk);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("foo")?;
+    /// let file_bk = file.copy(example_bk)?;
+    ///
+    /// assert!(file.content_eq(&file_bk)?);
+    ///
+    /// file_bk.write_str("bar")?;
+    /// assert!(!file.content_eq(&file_bk)?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn content_eq(&self, other: &PathFile) -> Result<bool> {
+        if self.canonicalize()? == other.canonicalize()? {
+            return Ok(true);
+        }
+        if self.size()? != other.size()? {
+            return Ok(false);
+        }
+
+        let mut a = self.read()?;
+        let mut b = other.read()?;
+        let mut buf_a = vec![0; CHUNK_SIZE];
+        let mut buf_b = vec![0; CHUNK_SIZE];
+        loop {
+            let len_a = read_chunk(&mut a, &mut buf_a)?;
+            let len_b = read_chunk(&mut b, &mut buf_b)?;
+            if buf_a[..len_a] != buf_b[..len_b] {
+                return Ok(false);
+            }
+            if len_a == 0 {
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Open the file as read-only.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::io::Read;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// let expected = "foo\nbar";
+    /// file.write_str(expected)?;
+    ///
+    /// let mut read = file.read()?;
+    /// let mut s = String::new();
+    /// read.read_to_string(&mut s)?;
+    /// assert_eq!(expected, s);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn read(&self) -> Result<FileRead> {
+        FileRead::read_path(self.clone())
+    }
+
+    /// Open the file as write-only, truncating it first.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::io::Write;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("some old contents\n")?;
+    ///
+    /// let mut write = file.write()?;
+    /// write.write_all(b"foo\n")?;
+    /// write.flush()?;
+    /// assert_eq!("foo\n", file.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn write(&self) -> Result<FileWrite> {
+        let mut options = fs::OpenOptions::new();
+        options.truncate(true);
+        FileWrite::open_path(self.clone(), options)
+    }
+
+    /// Open the file as write-only in append mode.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::io::Write;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// let expected = "foo\nbar\n";
+    /// file.write_str("foo\n")?;
+    ///
+    /// let mut append = file.append()?;
+    /// append.write_all(b"bar\n")?;
+    /// append.flush();
+    /// assert_eq!(expected, file.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn append(&self) -> Result<FileWrite> {
+        let mut options = fs::OpenOptions::new();
+        options.append(true);
+        FileWrite::open_path(self.clone(), options)
+    }
+
+    /// Open the file for editing (reading and writing).
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::io::{Read, Seek, Write, SeekFrom};
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// let expected = "foo\nbar";
+    ///
+    /// let mut edit = file.edit()?;
+    /// let mut s = String::new();
+    ///
+    /// edit.write_all(expected.as_bytes())?;
+    /// edit.seek(SeekFrom::Start(0))?;
+    /// edit.read_to_string(&mut s)?;
+    /// assert_eq!(expected, s);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn edit(&self) -> Result<FileEdit> {
+        FileEdit::open_path(self.clone(), fs::OpenOptions::new())
+    }
+
+    /// Create a new set of `PathOpenOptions` for opening a file with fine-grained control.
+    ///
+    /// See the docs for [`PathOpenOptions`](struct.PathOpenOptions.html)
+    pub fn open_opts() -> PathOpenOptions {
+        PathOpenOptions::new()
+    }
+
+    /// Copy the file to another location, including permission bits
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    /// use std::path::Path;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// let example_bk = "example.txt.bk";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// # let example_bk = &tmp.path().join(example_bk);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// let contents = "This is some contents";
+    /// file.write_str(contents);
+    /// let file_bk = file.copy(example_bk)?;
+    /// assert_eq!(contents, file.read_string()?);
+    /// assert_eq!(contents, file_bk.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    ///
+    /// The destination's parent directory must already exist:
+    ///
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// let backups = "backups/example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// # let backups = &tmp.path().join(backups);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("This is some contents")?;
+    ///
+    /// assert!(file.copy(backups.join("example.txt")).is_err());
+    ///
+    /// let backups = PathDir::create_all(backups)?;
+    /// let file_bk = file.copy(backups.join("example.txt"))?;
+    /// assert_eq!("This is some contents", file_bk.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn copy<P: AsRef<Path>>(&self, path: P) -> Result<PathFile> {
+        fs::copy(&self, &path).map_err(|err| {
+            let err = match path.as_ref().parent() {
+                Some(parent)
+                    if err.kind() == io::ErrorKind::NotFound && self.exists()
+                        && !parent.as_os_str().is_empty() && !parent.exists() =>
+                {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("destination directory {} does not exist", parent.display()),
+                    )
+                }
+                _ => err,
+            };
+            Error::new(
+                err,
+                &format!("copying {} from", path.as_ref().display()),
+                self.clone().into(),
+            )
+        })?;
+        // The copy succeeded so the destination is known to be a file.
+        Ok(PathFile::from_abs_unchecked(PathAbs::new(path)?))
+    }
+
+    /// Copy the file to `path` like `copy`, but share the underlying data blocks between the two
+    /// files when the filesystem supports it (a "reflink").
+    ///
+    /// Cloning the blocks is nearly instantaneous regardless of the size of the file, and the
+    /// copies are still independent: writing to one does not change the other. If the
+    /// filesystem (or platform) does not support it, this falls back to `copy`.
+    ///
+    /// The clone is made in a temporary file next to `path` which then replaces it, so an
+    /// existing file at `path` is left untouched if cloning fails. Copying a file onto itself is
+    /// an error.
+    ///
+    /// > Reflinks are currently only attempted on Linux (i.e. btrfs and XFS) when the `reflink`
+    /// > feature is enabled.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// let example_bk = "example.txt.bk";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// # let example_bk = &tmp.path().join(example_bk);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("This is some contents")?;
+    ///
+    /// let file_bk = file.copy_reflink(example_bk)?;
+    /// file.write_str("changed")?;
+    /// assert_eq!("This is some contents", file_bk.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn copy_reflink<P: AsRef<Path>>(&self, path: P) -> Result<PathFile> {
+        if is_same_file(self, path.as_ref()) {
+            return Err(Error::new(
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "source and destination are the same file",
+                ),
+                &format!("copying {} from", path.as_ref().display()),
+                self.clone().into(),
+            ));
+        }
+        if reflink(self, path.as_ref())? {
+            Ok(PathFile::from_abs_unchecked(PathAbs::new(path)?))
+        } else {
+            self.copy(path)
+        }
+    }
+
+    /// Rename a file, replacing the original file if `to` already exists.
+    ///
+    /// This will not work if the new name is on a different mount point.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    /// use std::path::Path;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// let example_bk = "example.txt.bk";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// # let example_bk = &tmp.path().join(example_bk);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// let contents = "This is some contents";
+    /// file.write_str(contents);
+    /// let file_bk = file.clone().rename(example_bk)?;
+    /// assert!(!file.exists());
+    /// assert_eq!(contents, file_bk.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn rename<P: AsRef<Path>>(self, to: P) -> Result<PathFile> {
+        fs::rename(&self, &to).map_err(|err| {
+            Error::new(
+                err,
+                &format!("renaming to {} from", to.as_ref().display()),
+                self.clone().into(),
+            )
+        })?;
+        Ok(PathFile::new(to)?)
+    }
+
+    /// Copy the file into `dir`, keeping its file name, and return the new `PathFile`.
+    ///
+    /// An existing file with the same name in `dir` is replaced.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("This is some contents")?;
+    /// let backups = PathDir::create(file.parent_dir().unwrap().join("backups"))?;
+    ///
+    /// let file_bk = file.copy_to_dir(&backups)?;
+    /// assert_eq!(backups.join("example.txt").as_path(), file_bk.as_path());
+    /// assert_eq!("This is some contents", file_bk.read_string()?);
+    ///
+    /// // Or move it instead
+    /// let moved = file.clone().move_to_dir(&PathDir::create(backups.join("moved"))?)?;
+    /// assert!(!file.exists());
+    /// assert_eq!("This is some contents", moved.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn copy_to_dir(&self, dir: &PathDir) -> Result<PathFile> {
+        let dest = self.dest_in_dir(dir, "copying to directory")?;
+        self.copy(dest)
+    }
+
+    /// Move the file into `dir` with `rename`, keeping its file name, and return the new
+    /// `PathFile`.
+    ///
+    /// An existing file with the same name in `dir` is replaced. See
+    /// [`copy_to_dir`](struct.PathFile.html#method.copy_to_dir) for an example.
+    pub fn move_to_dir(self, dir: &PathDir) -> Result<PathFile> {
+        let dest = self.dest_in_dir(dir, "moving to directory")?;
+        self.rename(dest)
+    }
+
+    fn dest_in_dir(&self, dir: &PathDir, action: &str) -> Result<PathArc> {
+        match self.file_name() {
+            Some(name) => Ok(dir.join(name)),
+            None => Err(Error::new(
+                io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"),
+                action,
+                self.clone().into(),
+            )),
+        }
+    }
+
+    /// Creates a new symbolic link on the filesystem to the dst.
+    ///
+    /// This handles platform specific behavior correctly.
+    ///
+    /// The returned `PathFile` is the path of the link itself, use `read_link` to get the path it
+    /// points to or `canonicalize` to resolve it.
+    ///
+    /// > On windows, creating symlinks requires the `SeCreateSymbolicLinkPrivilege` (i.e. running
+    /// > as administrator or with developer mode enabled), otherwise
+    /// > `io::ErrorKind::PermissionDenied` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    /// use std::path::Path;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// let example_sym = "example.txt.sym";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// # let example_sym = &tmp.path().join(example_sym);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// let contents = "This is some contents";
+    /// file.write_str(contents);
+    /// let file_sym = file.symlink(example_sym)?;
+    ///
+    /// // They have a different "absolute path"
+    /// assert_ne!(file, file_sym);
+    ///
+    /// // But they can be canonicalized to the same file.
+    /// let file_can = file_sym.canonicalize()?;
+    /// assert_eq!(file, file_can);
+    ///
+    /// // And the link can be read without resolving it.
+    /// assert_eq!(file.as_path(), file_sym.read_link()?.as_path());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn symlink<P: AsRef<Path>>(&self, dst: P) -> Result<PathFile> {
+        symlink_file(&self, &dst).map_err(|err| {
+            Error::new(
+                err,
+                &format!("linking from {} to", dst.as_ref().display()),
+                self.clone().into(),
+            )
+        })?;
+        PathFile::new(dst)
+    }
+
+    /// Creates a new hard link on the filesystem to the dst.
+    ///
+    /// Both paths then refer to the same underlying file, so changing the contents through one
+    /// is visible through the other. Returns `io::ErrorKind::AlreadyExists` if `dst` exists.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// let example_link = "example.txt.link";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// # let example_link = &tmp.path().join(example_link);
+    /// let file = PathFile::create(example)?;
+    /// let file_link = file.hard_link(example_link)?;
+    ///
+    /// file.write_str("This is some contents")?;
+    /// assert_eq!("This is some contents", file_link.read_string()?);
+    ///
+    /// // The link already exists
+    /// assert!(file.hard_link(example_link).is_err());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn hard_link<P: AsRef<Path>>(&self, dst: P) -> Result<PathFile> {
+        fs::hard_link(self, &dst).map_err(|err| {
+            Error::new(
+                err,
+                &format!("hard linking {} to", dst.as_ref().display()),
+                self.clone().into(),
+            )
+        })?;
+        // The link succeeded so the destination is known to be a file.
+        Ok(PathFile::from_abs_unchecked(PathAbs::new(dst)?))
+    }
+
+    /// Remove (delete) the file from the filesystem, consuming self.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathAbs, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    /// assert!(file.exists());
+    /// file.remove()?;
+    ///
+    /// // file.exists() <--- COMPILER ERROR, `file` was consumed
+    ///
+    /// // `PathAbs` doesn't require the path to exist, but `PathFile` does
+    /// assert!(!PathAbs::new(example)?.exists());
+    /// assert!(PathFile::new(example).is_err());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn remove(self) -> Result<()> {
+        fs::remove_file(&self).map_err(|err| Error::new(err, "removing", self.into()))
+    }
+
+    /// Remove (delete) the file from the filesystem, consuming self. Unlike `remove`, this
+    /// succeeds if the file has already been removed (i.e. by another process).
+    ///
+    /// All other errors (such as `io::ErrorKind::PermissionDenied`) are still returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    /// use std::path::Path;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// file.clone().remove_ok()?;
+    /// assert!(!Path::new(example).exists());
+    ///
+    /// // Removing it again is not an error
+    /// file.remove_ok()?;
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn remove_ok(self) -> Result<()> {
+        match fs::remove_file(&self) {
+            Ok(()) => Ok(()),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(Error::new(err, "removing", self.into())),
+        }
+    }
+
+    /// Return a reference to a basic `std::path::Path`
+    pub fn as_path(&self) -> &Path {
+        self.as_ref()
+    }
+
+    /// Returns the canonical form of the path with all intermediate components normalized and
+    /// symbolic links resolved.
+    ///
+    /// See [`PathAbs::canonicalize`]
+    ///
+    /// [`PathAbs::canonicalize`]: struct.PathAbs.html#method.canonicalize
     pub fn canonicalize(&self) -> Result<PathFile> {
         Ok(PathFile(self.0.canonicalize()?))
     }
@@ -577,12 +1797,1022 @@ impl From<PathFile> for PathBuf {
     }
 }
 
+/// The size of the chunks used when streaming through files.
+pub(crate) const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Read from `f` until `buf` is full or the end of the file is reached, returning the number of
+/// bytes read.
+pub(crate) fn read_chunk(f: &mut FileRead, buf: &mut [u8]) -> Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match f.0.file.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::new(err, "reading", f.path().clone().into())),
+        }
+    }
+    Ok(len)
+}
+
+/// The error for a path which should be a file but is a directory.
+pub(crate) fn is_a_directory() -> io::Error {
+    io::Error::new(io::ErrorKind::IsADirectory, "path exists but is a directory")
+}
+
+static TEMP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// A new hidden name based on `name` which is unique within this process.
+pub(crate) fn temp_name(name: &OsStr) -> OsString {
+    let mut temp_name = OsString::from(".");
+    temp_name.push(name);
+    temp_name.push(format!(
+        ".{}.{}.tmp",
+        process::id(),
+        TEMP_COUNT.fetch_add(1, AtomicOrdering::SeqCst)
+    ));
+    temp_name
+}
+
+/// Create a new, uniquely named temporary file in `dir` based on `name`.
+pub(crate) fn create_temp(dir: &Path, name: &OsStr) -> Result<(PathArc, fs::File)> {
+    loop {
+        let temp = PathArc::new(dir.join(temp_name(name)));
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)
+        {
+            Ok(file) => return Ok((temp, file)),
+            Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(Error::new(err, "creating temporary file", temp)),
+        }
+    }
+}
+
+/// Create a new, uniquely named temporary directory in `dir` based on `name`.
+pub(crate) fn create_temp_dir(dir: &Path, name: &OsStr) -> Result<PathArc> {
+    loop {
+        let temp = PathArc::new(dir.join(temp_name(name)));
+        match fs::create_dir(&temp) {
+            Ok(()) => return Ok(temp),
+            Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(Error::new(err, "creating temporary directory", temp)),
+        }
+    }
+}
+
+#[cfg(unix)]
+/// Return whether `err` is the error of renaming across filesystems.
+pub(crate) fn is_cross_device(err: &io::Error) -> bool {
+    use libc;
+
+    err.raw_os_error() == Some(libc::EXDEV)
+}
+
+#[cfg(windows)]
+/// Return whether `err` is the error of renaming across filesystems.
+pub(crate) fn is_cross_device(err: &io::Error) -> bool {
+    const ERROR_NOT_SAME_DEVICE: i32 = 17;
+    err.raw_os_error() == Some(ERROR_NOT_SAME_DEVICE)
+}
+
+#[cfg(not(any(unix, windows)))]
+/// Return whether `err` is the error of renaming across filesystems.
+pub(crate) fn is_cross_device(_: &io::Error) -> bool {
+    false
+}
+
+#[cfg(unix)]
+/// The paths to check for an executable at `path`.
+fn executable_candidates(path: &Path) -> Vec<PathBuf> {
+    vec![path.to_path_buf()]
+}
+
+#[cfg(windows)]
+/// The paths to check for an executable at `path`, with each extension from `PATHEXT`.
+fn executable_candidates(path: &Path) -> Vec<PathBuf> {
+    let pathext = ::std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".into());
+    let mut candidates = vec![path.to_path_buf()];
+    for ext in pathext.split(';').filter(|ext| !ext.is_empty()) {
+        let mut candidate = path.as_os_str().to_os_string();
+        candidate.push(ext);
+        candidates.push(candidate.into());
+    }
+    candidates
+}
+
+#[cfg(unix)]
+fn is_executable(path: &PathFile) -> Result<bool> {
+    Ok(path.mode()? & 0o111 != 0)
+}
+
+#[cfg(windows)]
+fn is_executable(path: &PathFile) -> Result<bool> {
+    // Match the error behavior of unix.
+    path.metadata()?;
+    Ok(match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => ["exe", "com", "bat", "cmd"]
+            .iter()
+            .any(|exe| ext.eq_ignore_ascii_case(exe)),
+        None => false,
+    })
+}
+
+#[cfg(all(feature = "reflink", target_os = "linux"))]
+/// Try to clone `src` to `dst` with the `FICLONE` ioctl, returning whether it succeeded.
+///
+/// The clone is made in a temporary file in the directory of `dst` and then renamed over it.
+/// Returns `Ok(false)` if reflinks are unsupported (or the temporary file can't be created, or
+/// `dst` is a symlink), in which case the caller should fall back to a normal copy, which also
+/// reports any error writing `dst`.
+fn reflink(src: &PathFile, dst: &Path) -> Result<bool> {
+    use std::os::unix::io::AsRawFd;
+    use libc;
+
+    let err_action = || format!("reflinking {} from", dst.display());
+    let from = fs::File::open(src)
+        .map_err(|err| Error::new(err, &err_action(), src.clone().into()))?;
+    let perms = from
+        .metadata()
+        .map_err(|err| Error::new(err, "getting metadata of", src.clone().into()))?
+        .permissions();
+    match fs::symlink_metadata(dst) {
+        // Renaming would replace the symlink instead of writing to its target like `copy`.
+        Ok(ref meta) if meta.file_type().is_symlink() => return Ok(false),
+        _ => {}
+    }
+    let dir = match dst.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = dst.file_name().unwrap_or_else(|| OsStr::new("reflink"));
+    let (temp, to) = match create_temp(dir, name) {
+        Ok(temp) => temp,
+        Err(_) => return Ok(false),
+    };
+
+    if unsafe { libc::ioctl(to.as_raw_fd(), libc::FICLONE, from.as_raw_fd()) } != 0 {
+        let err = io::Error::last_os_error();
+        let _ = fs::remove_file(&temp);
+        return match err.raw_os_error() {
+            Some(libc::EOPNOTSUPP)
+            | Some(libc::EXDEV)
+            | Some(libc::EINVAL)
+            | Some(libc::ENOTTY)
+            | Some(libc::ENOSYS) => Ok(false),
+            _ => Err(Error::new(err, &err_action(), src.clone().into())),
+        };
+    }
+    // Match `fs::copy`, which copies the permissions.
+    let replaced = to
+        .set_permissions(perms)
+        .map_err(|err| Error::new(err, "setting permisions for", temp.clone()))
+        .and_then(|()| {
+            fs::rename(&temp, dst).map_err(|err| {
+                Error::new(
+                    err,
+                    &format!("renaming to {} from", dst.display()),
+                    temp.clone(),
+                )
+            })
+        });
+    if replaced.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    replaced.map(|()| true)
+}
+
+#[cfg(not(all(feature = "reflink", target_os = "linux")))]
+/// Reflinks are not supported, always fall back to a normal copy.
+fn reflink(_src: &PathFile, _dst: &Path) -> Result<bool> {
+    Ok(false)
+}
+
+/// Return whether `dst` exists and is the same file as `src`, following symlinks.
+pub(crate) fn is_same_file(src: &PathFile, dst: &Path) -> bool {
+    match (fs::canonicalize(src), fs::canonicalize(dst)) {
+        (Ok(src), Ok(dst)) => src == dst,
+        _ => false,
+    }
+}
+
+/// Atomically replace the file at `path` with one containing `bytes`.
+///
+/// The bytes are written and synced to a temporary file in the same directory which is then
+/// renamed over `path`. The permissions of an existing file are preserved and if `path` is a
+/// symlink then its target is replaced. The temporary file is removed if any step fails.
+pub(crate) fn replace_atomic(path: &PathAbs, bytes: &[u8]) -> Result<()> {
+    let path = match path.symlink_metadata() {
+        Ok(ref meta) if meta.file_type().is_symlink() => path.canonicalize()?,
+        _ => path.clone(),
+    };
+    let (dir, name) = match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) => (dir, name),
+        _ => {
+            return Err(Error::new(
+                io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"),
+                "creating",
+                path.clone().into(),
+            ))
+        }
+    };
+    if !dir.is_dir() {
+        return Err(Error::new(
+            io::Error::new(io::ErrorKind::NotFound, "parent directory does not exist"),
+            "creating",
+            path.clone().into(),
+        ));
+    }
+
+    let permissions = path.metadata().ok().map(|meta| meta.permissions());
+
+    let (temp, mut file) = create_temp(dir, name)?;
+    let result = file.write_all(bytes)
+        .map_err(|err| Error::new(err, "writing", temp.clone()))
+        .and_then(|_| file.sync_all().map_err(|err| Error::new(err, "syncing", temp.clone())))
+        .and_then(|_| match permissions {
+            Some(perms) => file.set_permissions(perms)
+                .map_err(|err| Error::new(err, "setting permisions for", temp.clone())),
+            None => Ok(()),
+        })
+        .and_then(|_| {
+            drop(file);
+            fs::rename(&temp, &path).map_err(|err| {
+                Error::new(
+                    err,
+                    &format!("renaming to {} from", path.display()),
+                    temp.clone(),
+                )
+            })
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
 #[cfg(unix)]
-fn symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
+pub(crate) fn symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
     ::std::os::unix::fs::symlink(src, dst)
 }
 
 #[cfg(windows)]
-fn symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
+pub(crate) fn symlink_file<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
     ::std::os::windows::fs::symlink_file(src, dst)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{self, Seek, SeekFrom, Write};
+    use std::time::{Duration, SystemTime};
+    use tempdir::TempDir;
+    use super::super::{PathDir, PathFile, PathType};
+
+    #[test]
+    fn sanity_read_bytes_large() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("large.txt")).unwrap();
+
+        let contents = "0123456789abcdef".repeat(4 * 1024 * 1024 / 16);
+        file.write_str(&contents).unwrap();
+
+        let bytes = file.read_bytes().unwrap();
+        assert_eq!(contents.len(), bytes.len());
+        assert_eq!(contents.as_bytes(), bytes.as_slice());
+    }
+
+    #[test]
+    fn sanity_write_bytes_empty() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("empty.bin")).unwrap();
+
+        file.write_bytes(b"some contents").unwrap();
+        file.write_bytes(&[]).unwrap();
+        assert!(file.read_bytes().unwrap().is_empty());
+    }
+
+    #[test]
+    fn sanity_append_str_removed() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("removed.txt")).unwrap();
+        file.clone().remove().unwrap();
+
+        let err = file.append_str("foo").unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert_eq!(file.as_path(), err.path());
+        assert!(!file.exists());
+    }
+
+    #[test]
+    fn sanity_append_bytes_readonly() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("readonly.bin")).unwrap();
+
+        file.set_readonly(true).unwrap();
+        if fs::OpenOptions::new().append(true).open(&file).is_ok() {
+            // Permissions are not enforced (i.e. running as root).
+            return;
+        }
+
+        let err = file.append_bytes(b"foo").unwrap_err();
+        assert_eq!(io::ErrorKind::PermissionDenied, err.io_error().kind());
+        assert_eq!(file.as_path(), err.path());
+    }
+
+    #[test]
+    fn sanity_write_handles() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+        file.write_str("this will be truncated").unwrap();
+
+        {
+            let mut write = file.write().unwrap();
+            assert_eq!(&file, write.path());
+            write.write_all(b"foo\n").unwrap();
+            write.sync_all().unwrap();
+        }
+        assert_eq!("foo\n", file.read_string().unwrap());
+
+        {
+            let mut append = file.append().unwrap();
+            append.write_all(b"bar\n").unwrap();
+            append.sync_data().unwrap();
+        }
+        assert_eq!("foo\nbar\n", file.read_string().unwrap());
+    }
+
+    #[test]
+    fn sanity_edit_in_place() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+        file.write_str("hello world").unwrap();
+
+        let mut edit = file.edit().unwrap();
+        edit.seek(SeekFrom::Start(6)).unwrap();
+        edit.write_all(b"rust!").unwrap();
+        edit.seek(SeekFrom::Start(0)).unwrap();
+        assert_eq!("hello rust!", edit.read_string().unwrap());
+
+        edit.set_len(5).unwrap();
+        assert_eq!("hello", file.read_string().unwrap());
+    }
+
+    #[test]
+    fn sanity_create_with() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+
+        let file = PathFile::create_with(tmp_abs.join("foo.txt"), b"foo").unwrap();
+        let file = PathFile::create_with(&file, b"longer contents").unwrap();
+        assert_eq!("longer contents", file.read_string().unwrap());
+
+        // No temporary files are left behind
+        let entries: Vec<_> = tmp_abs.list().unwrap().map(|p| p.unwrap()).collect();
+        assert_eq!(vec![PathType::File(file)], entries);
+
+        let err = PathFile::create_with(tmp_abs.join("dne").join("foo.txt"), b"foo").unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert!(err.to_string().contains("parent directory does not exist"));
+    }
+
+    #[test]
+    fn sanity_write_atomic_failure() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let dir = PathDir::create(tmp_abs.join("dir")).unwrap();
+
+        // Renaming a file over a directory fails
+        let file = PathFile::mock(&dir);
+        assert!(file.write_str_atomic("foo").is_err());
+
+        let entries: Vec<_> = tmp_abs.list().unwrap().map(|p| p.unwrap()).collect();
+        assert_eq!(vec![PathType::Dir(dir)], entries);
+    }
+
+    #[test]
+    fn sanity_write_atomic_symlink() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+        let link = match file.symlink(tmp_abs.join("link.txt")) {
+            Ok(link) => link,
+            // Creating symlinks is not permitted (i.e. windows without privileges).
+            Err(ref err) if err.io_error().kind() == io::ErrorKind::PermissionDenied => return,
+            Err(err) => panic!("{}", err),
+        };
+
+        link.write_str_atomic("foo").unwrap();
+        assert!(link.symlink_metadata().unwrap().file_type().is_symlink());
+        assert_eq!("foo", file.read_string().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sanity_write_atomic_permissions() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+        file.set_mode(0o640).unwrap();
+
+        file.write_bytes_atomic(b"foo").unwrap();
+        assert_eq!(0o640, file.mode().unwrap() & 0o777);
+        assert_eq!("foo", file.read_string().unwrap());
+    }
+
+    #[test]
+    fn sanity_set_readonly() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+
+        file.set_readonly(true).unwrap();
+        if fs::OpenOptions::new().write(true).open(&file).is_ok() {
+            // Permissions are not enforced (i.e. running as root).
+            return;
+        }
+        let err = file.write_str("foo").unwrap_err();
+        assert_eq!(io::ErrorKind::PermissionDenied, err.io_error().kind());
+        assert_eq!(file.as_path(), err.path());
+
+        file.set_readonly(false).unwrap();
+        file.write_str("foo").unwrap();
+        assert_eq!("foo", file.read_string().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sanity_mode() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+        let dir = PathDir::create(tmp_abs.join("foo")).unwrap();
+
+        file.set_mode(0o600).unwrap();
+        assert_eq!(0o600, file.mode().unwrap() & 0o7777);
+        assert_eq!(0, file.mode().unwrap() & 0o044);
+
+        dir.set_mode(0o700).unwrap();
+        assert_eq!(0o700, dir.mode().unwrap() & 0o7777);
+    }
+
+    #[test]
+    fn sanity_hard_link_exists() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+        let other = PathFile::create(tmp_abs.join("bar.txt")).unwrap();
+
+        let err = file.hard_link(&other).unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+        let msg = err.to_string();
+        assert!(msg.contains(&file.display().to_string()), "{}", msg);
+        assert!(msg.contains(&other.display().to_string()), "{}", msg);
+    }
+
+    #[test]
+    fn sanity_touch() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+        file.write_str("foo").unwrap();
+
+        let past = SystemTime::now() - Duration::from_secs(60 * 60);
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(past)
+            .unwrap();
+
+        let file = PathFile::touch(&file).unwrap();
+        assert_eq!("foo", file.read_string().unwrap());
+        assert!(file.metadata().unwrap().modified().unwrap() > past);
+    }
+
+    #[test]
+    fn sanity_touch_readonly() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+        file.write_str("foo").unwrap();
+
+        let past = SystemTime::now() - Duration::from_secs(60 * 60);
+        file.set_modified(past).unwrap();
+        file.set_readonly(true).unwrap();
+
+        let file = PathFile::touch(&file).unwrap();
+        assert_eq!("foo", file.read_string().unwrap());
+        assert!(file.modified().unwrap() > past);
+        file.set_readonly(false).unwrap();
+    }
+
+    #[test]
+    fn sanity_read_lines() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+
+        let read_lines = |file: &PathFile| -> Vec<String> {
+            file.read_lines().unwrap().map(|l| l.unwrap()).collect()
+        };
+
+        assert!(read_lines(&file).is_empty());
+
+        file.write_str("foo\nbar\nbaz\n").unwrap();
+        assert_eq!(vec!["foo", "bar", "baz"], read_lines(&file));
+
+        file.write_str("foo\r\nbar\r\nbaz").unwrap();
+        assert_eq!(vec!["foo", "bar", "baz"], read_lines(&file));
+    }
+
+    #[test]
+    fn sanity_content_eq() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let a = PathFile::create(tmp_abs.join("a.txt")).unwrap();
+        let b = PathFile::create(tmp_abs.join("b.txt")).unwrap();
+
+        // Empty files
+        assert!(a.content_eq(&b).unwrap());
+        assert!(a.content_eq(&a).unwrap());
+
+        let contents = "0123456789".repeat(20000);
+        a.write_str(&contents).unwrap();
+        b.write_str(&contents).unwrap();
+        assert!(a.content_eq(&b).unwrap());
+
+        // Same length, differing after the first chunk
+        let mut different = contents.clone();
+        different.pop();
+        different.push('x');
+        b.write_str(&different).unwrap();
+        assert!(!a.content_eq(&b).unwrap());
+
+        // Different lengths
+        b.write_str(&contents[1..]).unwrap();
+        assert!(!a.content_eq(&b).unwrap());
+    }
+
+    #[test]
+    fn sanity_truncate() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo")).unwrap();
+        file.write_str("0123456789").unwrap();
+
+        file.truncate(4).unwrap();
+        assert_eq!("0123", file.read_string().unwrap());
+
+        file.truncate(100).unwrap();
+        assert_eq!(100, file.size().unwrap());
+        let bytes = file.read_bytes().unwrap();
+        assert_eq!(b"0123", &bytes[..4]);
+        assert!(bytes[4..].iter().all(|b| *b == 0));
+
+        let missing = PathFile::mock(tmp_abs.join("missing"));
+        let err = missing.truncate(0).unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert_eq!(missing.as_path(), err.path());
+    }
+
+    #[test]
+    fn sanity_modified() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo")).unwrap();
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(60))
+            .unwrap();
+
+        let before = SystemTime::now() - Duration::from_millis(10);
+        file.write_str("foo").unwrap();
+        assert!(file.modified().unwrap() > before);
+        assert!(tmp_abs.modified().unwrap() <= SystemTime::now());
+        match file.created() {
+            Ok(created) => assert!(created <= file.modified().unwrap()),
+            Err(err) => {
+                assert_eq!(io::ErrorKind::Unsupported, err.io_error().kind());
+                assert_eq!(file.as_path(), err.path());
+            }
+        }
+
+        let missing = PathFile::mock(tmp_abs.join("missing"));
+        let err = missing.modified().unwrap_err();
+        assert_eq!(missing.as_path(), err.path());
+    }
+
+    #[test]
+    fn sanity_write_from() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo")).unwrap();
+        file.write_str("old contents which are longer").unwrap();
+
+        let bytes: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let written = file.write_from(&mut io::Cursor::new(bytes.clone())).unwrap();
+        assert_eq!(bytes.len() as u64, written);
+        assert_eq!(bytes, file.read_bytes().unwrap());
+
+        assert_eq!(3, file.append_from(&mut io::Cursor::new(b"end".to_vec())).unwrap());
+        assert_eq!(bytes.len() + 3, file.size().unwrap() as usize);
+
+        struct Broken;
+        impl io::Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe"))
+            }
+        }
+        let err = file.write_from(&mut Broken).unwrap_err();
+        assert_eq!(file.as_path(), err.path());
+        assert!(err.to_string().contains("broken pipe"));
+    }
+
+    #[test]
+    fn sanity_copy_reflink() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo")).unwrap();
+        let bytes: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        file.write_bytes(&bytes).unwrap();
+
+        // Overwrites existing files like `copy`
+        let existing = PathFile::create(tmp_abs.join("existing")).unwrap();
+        existing.write_str("some much longer old contents").unwrap();
+        let copied = file.copy_reflink(&existing).unwrap();
+        assert_eq!(existing, copied);
+        assert_eq!(bytes, copied.read_bytes().unwrap());
+
+        file.write_str("changed").unwrap();
+        assert_eq!(bytes, copied.read_bytes().unwrap());
+
+        let err = file.copy_reflink(tmp_abs.join("missing").join("foo")).unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert!(err.to_string().contains("does not exist"));
+
+        // Copying onto itself leaves the file untouched
+        let err = copied.copy_reflink(&copied).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.io_error().kind());
+        assert_eq!(copied.as_path(), err.path());
+        assert_eq!(bytes, copied.read_bytes().unwrap());
+        let err = copied.copy_reflink(tmp_abs.join(".").join("existing")).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.io_error().kind());
+        assert_eq!(bytes, copied.read_bytes().unwrap());
+
+        // No temporary files are left behind
+        assert_eq!(2, tmp_abs.list().unwrap().count());
+    }
+
+    #[test]
+    fn sanity_copy_to_dir() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let dir = PathDir::create(tmp_abs.join("dir")).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+        file.write_str("foo").unwrap();
+
+        let existing = PathFile::create(dir.join("foo.txt")).unwrap();
+        existing.write_str("old").unwrap();
+        assert_eq!(existing, file.copy_to_dir(&dir).unwrap());
+        assert_eq!("foo", existing.read_string().unwrap());
+        assert!(file.exists());
+
+        let other = PathDir::create(tmp_abs.join("other")).unwrap();
+        let moved = file.clone().move_to_dir(&other).unwrap();
+        assert_eq!(other.join("foo.txt").as_path(), moved.as_path());
+        assert!(!file.exists());
+
+        let err = moved.copy_to_dir(&PathDir::mock(tmp_abs.join("missing"))).unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+    }
+
+    #[test]
+    fn sanity_append_line() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.ndjson")).unwrap();
+
+        file.append_line("{}").unwrap();
+        assert_eq!("{}\n", file.read_string().unwrap());
+
+        file.append_line("[]").unwrap();
+        assert_eq!("{}\n[]\n", file.read_string().unwrap());
+
+        file.write_str("unterminated").unwrap();
+        file.append_line("{}").unwrap();
+        assert_eq!("unterminated\n{}\n", file.read_string().unwrap());
+
+        let missing = PathFile::mock(tmp_abs.join("missing"));
+        let err = missing.append_line("foo").unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert!(!missing.exists());
+    }
+
+    #[test]
+    fn sanity_read_at() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("records")).unwrap();
+
+        let edit = file.edit().unwrap();
+        for i in 0..10u8 {
+            edit.write_at(&[i; 8], u64::from(i) * 8).unwrap();
+        }
+        assert_eq!(80, file.size().unwrap());
+
+        let read = file.read().unwrap();
+        let mut buf = [0xff; 8];
+        for &i in &[3u8, 0, 9, 5] {
+            assert_eq!(8, read.read_at(&mut buf, u64::from(i) * 8).unwrap());
+            assert_eq!([i; 8], buf);
+        }
+        assert_eq!(8, edit.read_at(&mut buf, 8).unwrap());
+        assert_eq!([1; 8], buf);
+
+        // Straddling and past the end of the file
+        assert_eq!(4, file.read_at(&mut buf, 76).unwrap());
+        assert_eq!([9; 4], &buf[..4]);
+        assert_eq!(0, file.read_at(&mut buf, 80).unwrap());
+        assert_eq!(0, file.read_at(&mut buf, 1000).unwrap());
+
+        // Writing past the end extends the file with zeroes
+        file.write_at(b"end", 90).unwrap();
+        assert_eq!(93, file.size().unwrap());
+        let bytes = file.read_bytes().unwrap();
+        assert_eq!(&[0; 10], &bytes[80..90]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sanity_is_executable() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("plugin")).unwrap();
+
+        file.set_mode(0o755).unwrap();
+        assert!(file.is_executable().unwrap());
+        file.set_mode(0o644).unwrap();
+        assert!(!file.is_executable().unwrap());
+        file.set_mode(0o601).unwrap();
+        assert!(file.is_executable().unwrap());
+
+        let missing = PathFile::mock(tmp_abs.join("missing"));
+        let err = missing.is_executable().unwrap_err();
+        assert_eq!(missing.as_path(), err.path());
+    }
+
+    #[test]
+    fn sanity_set_modified() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo")).unwrap();
+
+        let time = SystemTime::now() - Duration::new(60 * 60 * 24, 123_456_789);
+        let close = |a: SystemTime| {
+            let diff = match a.duration_since(time) {
+                Ok(d) => d,
+                Err(err) => err.duration(),
+            };
+            // FAT only has a precision of 2 seconds
+            diff < Duration::from_secs(2)
+        };
+        file.set_modified(time).unwrap();
+        assert!(close(file.modified().unwrap()));
+        file.set_accessed(time).unwrap();
+        assert!(close(file.accessed().unwrap()));
+        assert!(close(file.modified().unwrap()));
+
+        tmp_abs.set_modified(time).unwrap();
+        assert!(close(tmp_abs.modified().unwrap()));
+
+        let missing = PathFile::mock(tmp_abs.join("missing"));
+        let err = missing.set_modified(time).unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert_eq!(missing.as_path(), err.path());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sanity_set_modified_without_opening() {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::fs::PermissionsExt;
+        use super::super::PathAbs;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let time = SystemTime::now() - Duration::from_secs(60 * 60);
+
+        // Writable but not readable.
+        let file = PathFile::create(tmp_abs.join("write-only")).unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o200)).unwrap();
+        file.set_modified(time).unwrap();
+        assert_eq!(time, file.modified().unwrap());
+
+        // Opening a FIFO would block until a writer appears.
+        let fifo = tmp_abs.join("fifo");
+        let c_fifo = CString::new(fifo.as_os_str().as_bytes()).unwrap();
+        assert_eq!(0, unsafe { ::libc::mkfifo(c_fifo.as_ptr(), 0o600) });
+        let fifo = PathAbs::new(fifo).unwrap();
+        fifo.set_modified(time).unwrap();
+        fifo.set_accessed(time).unwrap();
+        assert_eq!(time, fifo.modified().unwrap());
+        assert_eq!(time, fifo.accessed().unwrap());
+    }
+
+    #[test]
+    fn sanity_head_tail() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.log")).unwrap();
+
+        let empty: Vec<String> = Vec::new();
+        assert_eq!(empty, file.head(3).unwrap());
+        assert_eq!(empty, file.tail(3).unwrap());
+
+        file.write_str("a\nb\nc").unwrap();
+        assert_eq!(vec!["a", "b"], file.head(2).unwrap());
+        assert_eq!(vec!["b", "c"], file.tail(2).unwrap());
+        assert_eq!(vec!["a", "b", "c"], file.tail(5).unwrap());
+        assert_eq!(empty, file.tail(0).unwrap());
+
+        file.write_str("a\r\nb\n\nc\n").unwrap();
+        assert_eq!(vec!["a", "b", "", "c"], file.tail(4).unwrap());
+        assert_eq!(vec!["c"], file.tail(1).unwrap());
+
+        // Larger than the chunk size, with lines straddling chunks
+        let lines: Vec<String> = (0..300_000).map(|i| format!("line {}", i)).collect();
+        file.write_str(&lines.join("\n")).unwrap();
+        assert!(file.size().unwrap() > 2 * 1024 * 1024);
+        assert_eq!(&lines[..10], &file.head(10).unwrap()[..]);
+        assert_eq!(&lines[lines.len() - 20_000..], &file.tail(20_000).unwrap()[..]);
+        assert_eq!(lines, file.tail(lines.len() + 1).unwrap());
+    }
+
+    #[test]
+    fn sanity_read_limited() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo")).unwrap();
+        file.write_str("0123456789").unwrap();
+
+        assert_eq!("0123456789", file.read_string_limited(11).unwrap());
+        assert_eq!("0123456789", file.read_string_limited(10).unwrap());
+        assert_eq!(file.read_bytes().unwrap(), file.read_bytes_limited(10).unwrap());
+
+        let err = file.read_string_limited(9).unwrap_err();
+        assert_eq!(io::ErrorKind::FileTooLarge, err.io_error().kind());
+        assert_eq!(file.as_path(), err.path());
+        assert!(err.to_string().contains("limit of 9 bytes"));
+
+        file.write_bytes(b"\xff\xfe").unwrap();
+        let err = file.read_string_limited(10).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.io_error().kind());
+
+        let empty = PathFile::create(tmp_abs.join("empty")).unwrap();
+        assert_eq!("", empty.read_string_limited(0).unwrap());
+    }
+
+    #[test]
+    fn sanity_create_all() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+
+        let file = PathFile::create_all(tmp_abs.join("a/b/c/d.txt")).unwrap();
+        assert!(file.is_file());
+        assert!(tmp_abs.join("a/b/c").is_dir());
+
+        // A parent which is a file can't be created
+        let err = PathFile::create_all(file.join("e/f.txt")).unwrap_err();
+        assert_eq!("creating parent directory", err.action());
+        assert_eq!(file.join("e").as_path(), err.path());
+
+        // Neither can a file which is a directory
+        let err = PathFile::create_all(tmp_abs.join("a/b")).unwrap_err();
+        assert_eq!("creating", err.action());
+        assert_eq!(io::ErrorKind::IsADirectory, err.io_error().kind());
+    }
+
+    #[test]
+    fn sanity_create_new() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let path = tmp_abs.join("foo.lock");
+
+        let file = PathFile::create_new(&path).unwrap();
+        file.write_str("pid").unwrap();
+
+        let err = PathFile::create_new(&path).unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+        assert_eq!(path.as_path(), err.path());
+        assert_eq!("pid", file.read_string().unwrap());
+    }
+
+    #[test]
+    fn sanity_which() {
+        #[cfg(unix)]
+        let name = "sh";
+        #[cfg(windows)]
+        let name = "cmd";
+        let found = PathFile::which(name).unwrap();
+        assert!(found.is_absolute());
+        assert!(found.is_executable().unwrap());
+
+        // Paths are only verified
+        assert_eq!(found, PathFile::which(found.as_os_str()).unwrap());
+        let missing = found.with_file_name("not-a-real-executable-name");
+        assert!(PathFile::which(missing.as_os_str()).is_err());
+
+        let err = PathFile::which("not-a-real-executable-name").unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert!(err.to_string().contains("not-a-real-executable-name"));
+    }
+
+    #[test]
+    fn sanity_read_string_detect() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("config.ini")).unwrap();
+        let expected = "[section]\r\nkey = värde ✓\r\n";
+
+        let utf16le: Vec<u8> = expected
+            .encode_utf16()
+            .flat_map(|u| u.to_le_bytes().to_vec())
+            .collect();
+        let utf16be: Vec<u8> = expected
+            .encode_utf16()
+            .flat_map(|u| u.to_be_bytes().to_vec())
+            .collect();
+        let fixtures = vec![
+            expected.as_bytes().to_vec(),
+            [&b"\xef\xbb\xbf"[..], expected.as_bytes()].concat(),
+            [&b"\xff\xfe"[..], &utf16le].concat(),
+            [&b"\xfe\xff"[..], &utf16be].concat(),
+        ];
+        for bytes in &fixtures {
+            file.write_bytes(bytes).unwrap();
+            assert_eq!(expected, file.read_string_detect().unwrap());
+        }
+
+        file.write_bytes(b"").unwrap();
+        assert_eq!("", file.read_string_detect().unwrap());
+
+        // An unpaired surrogate
+        file.write_bytes(b"\xff\xfe\x00\xd8").unwrap();
+        let err = file.read_string_detect().unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.io_error().kind());
+        assert!(err.to_string().contains("UTF-16LE"));
+        assert_eq!(file.as_path(), err.path());
+
+        file.write_bytes(b"\xfe\xff\x00").unwrap();
+        assert!(file.read_string_detect().unwrap_err().to_string().contains("UTF-16BE"));
+    }
+
+    #[test]
+    fn sanity_file_is_a_dir() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let dir = PathDir::create(tmp_abs.join("dir")).unwrap();
+
+        for err in [PathFile::create(&dir).unwrap_err(), PathFile::new(&dir).unwrap_err()] {
+            assert_eq!(io::ErrorKind::IsADirectory, err.io_error().kind());
+            assert_eq!(dir.as_path(), err.path());
+            assert!(err.to_string().contains("path exists but is a directory"));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sanity_is_cross_device() {
+        use super::is_cross_device;
+
+        assert!(is_cross_device(&io::Error::from_raw_os_error(::libc::EXDEV)));
+        assert!(!is_cross_device(&io::Error::from_raw_os_error(::libc::ENOENT)));
+        assert!(!is_cross_device(&io::Error::new(io::ErrorKind::InvalidInput, "no os error")));
+    }
+
+    #[test]
+    fn sanity_copy_missing_parent() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+        let dest = tmp_abs.join("dne").join("foo.txt");
+
+        let err = file.copy(&dest).unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        let msg = err.to_string();
+        assert!(msg.contains("destination directory"), "{}", msg);
+        assert!(msg.contains(&dest.display().to_string()), "{}", msg);
+        assert!(msg.contains(&file.display().to_string()), "{}", msg);
+    }
+}
@@ -0,0 +1,167 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Finding entries in directory trees.
+
+use std::ffi::OsStr;
+
+use super::Result;
+use super::{PathDir, PathFile, PathType};
+
+impl PathDir {
+    /// Return the first entry under the directory for which `f` returns true.
+    ///
+    /// The directory is walked like `PathDir::walk` with entries sorted by file name, so the
+    /// result is deterministic. The walk stops at the first match: directories after it are
+    /// not read and their errors are not returned. An error before the match is returned.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile, PathType};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// let sub = PathDir::create(dir.join("sub"))?;
+    /// let file = PathFile::create(sub.join("file.txt"))?;
+    ///
+    /// let found = dir.find_first(|entry| entry.is_file())?;
+    /// assert_eq!(Some(PathType::File(file)), found);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn find_first<F: Fn(&PathType) -> bool>(&self, f: F) -> Result<Option<PathType>> {
+        for entry in self.walk_opts().sort_by_file_name(true).walk() {
+            let entry = entry?;
+            if f(&entry) {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Return all the entries under the directory for which `f` returns true, in the order
+    /// of `find_first`.
+    ///
+    /// The first error is returned.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile, PathType};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// let foo = PathFile::create(dir.join("foo.rs"))?;
+    /// let bar = PathFile::create_all(dir.join("sub/bar.rs"))?;
+    /// PathFile::create(dir.join("README.md"))?;
+    ///
+    /// let found = dir.find_all(|entry| entry.extension() == Some("rs".as_ref()))?;
+    /// assert_eq!(vec![PathType::File(foo), PathType::File(bar)], found);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn find_all<F: Fn(&PathType) -> bool>(&self, f: F) -> Result<Vec<PathType>> {
+        let mut found = Vec::new();
+        for entry in self.walk_opts().sort_by_file_name(true).walk() {
+            let entry = entry?;
+            if f(&entry) {
+                found.push(entry);
+            }
+        }
+        Ok(found)
+    }
+
+    /// Return the first file under the directory with the file name `name`, like
+    /// `find_first`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// let cargo = PathFile::create_all(dir.join("crate/Cargo.toml"))?;
+    ///
+    /// assert_eq!(Some(cargo), dir.find_file_named("Cargo.toml")?);
+    /// assert_eq!(None, dir.find_file_named("crate")?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn find_file_named<S: AsRef<OsStr>>(&self, name: S) -> Result<Option<PathFile>> {
+        let name = name.as_ref();
+        let found = self.find_first(|entry| entry.is_file() && entry.file_name() == Some(name))?;
+        Ok(found.map(PathType::unwrap_file))
+    }
+
+    /// Return all the files under the directory with the file name `name`, like `find_all`.
+    pub fn find_files_named<S: AsRef<OsStr>>(&self, name: S) -> Result<Vec<PathFile>> {
+        let name = name.as_ref();
+        let found = self.find_all(|entry| entry.is_file() && entry.file_name() == Some(name))?;
+        Ok(found.into_iter().map(PathType::unwrap_file).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::file::symlink_file;
+    use super::super::{PathDir, PathFile, PathType};
+    use std::cell::Cell;
+    use std::io;
+    use tempdir::TempDir;
+
+    #[test]
+    fn sanity_find() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let root = PathDir::new(tmp_dir.path()).unwrap();
+        let first = PathFile::create_all(root.join("a/Cargo.toml")).unwrap();
+        let second = PathFile::create_all(root.join("b/c/Cargo.toml")).unwrap();
+        PathDir::create(root.join("b/Cargo.toml")).unwrap();
+        PathFile::create_all(root.join("b/c/lib.rs")).unwrap();
+
+        assert_eq!(Some(first.clone()), root.find_file_named("Cargo.toml").unwrap());
+        assert_eq!(
+            vec![first.clone(), second.clone()],
+            root.find_files_named("Cargo.toml").unwrap()
+        );
+        assert_eq!(None, root.find_file_named("missing").unwrap());
+        assert!(root.find_files_named("c").unwrap().is_empty());
+
+        let dirs = root.find_all(|entry| entry.is_dir()).unwrap();
+        let dirs: Vec<_> = dirs
+            .iter()
+            .map(|d| d.strip_prefix(&root).unwrap().display().to_string())
+            .collect();
+        assert_eq!(vec!["a", "b", "b/Cargo.toml", "b/c"], dirs);
+
+        // The walk stops at the first match.
+        symlink_file(root.join("missing"), root.join("zz-broken")).unwrap();
+        let seen = Cell::new(0);
+        let found = root
+            .find_first(|entry| {
+                seen.set(seen.get() + 1);
+                entry.file_name() == Some("Cargo.toml".as_ref())
+            })
+            .unwrap();
+        assert_eq!(Some(PathType::File(first)), found);
+        assert_eq!(2, seen.get());
+
+        let err = root.find_files_named("Cargo.toml").unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert_eq!(root.join("zz-broken").as_path(), err.path());
+    }
+}
@@ -0,0 +1,161 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Glob matching in a directory, enabled with the `glob` feature.
+
+use glob::{MatchOptions, Pattern};
+use std::fmt;
+use std::io;
+use std_prelude::*;
+
+use super::{Error, Result};
+use super::{PathDir, PathType, Walk};
+
+impl PathDir {
+    /// Return an iterator over the entries of the directory tree whose paths relative to the
+    /// directory match the glob `pattern`.
+    ///
+    /// The syntax is that of the [glob](https://docs.rs/glob) crate: `?`, `*` and `[...]`
+    /// match within a single path component and `**` matches any number of directories, so
+    /// `*.rs` only matches in the directory itself and `**/*.rs` matches at any depth. Entries
+    /// are walked like `PathDir::walk`, sorted by file name.
+    ///
+    /// An invalid pattern returns `io::ErrorKind::InvalidInput`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile, PathType};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// let cargo = PathFile::create(dir.join("Cargo.toml"))?;
+    /// let nested = PathFile::create_all(dir.join("nested/Cargo.toml"))?;
+    /// PathFile::create(dir.join("README.md"))?;
+    ///
+    /// let matches = dir.glob("**/*.toml")?.collect::<Result<Vec<_>, _>>()?;
+    /// assert_eq!(vec![PathType::File(cargo), PathType::File(nested)], matches);
+    ///
+    /// assert!(dir.glob("[").is_err());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn glob(&self, pattern: &str) -> Result<GlobMatches> {
+        let compiled = Pattern::new(pattern).map_err(|err| {
+            let err = io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid glob pattern {:?}: {}", pattern, err),
+            );
+            Error::new(err, "globbing", self.clone().into())
+        })?;
+
+        let mut opts = self.walk_opts().sort_by_file_name(true);
+        if !pattern.contains("**") {
+            // Without `**` each component of the pattern matches a single component.
+            opts = opts.max_depth(Path::new(pattern).components().count());
+        }
+        Ok(GlobMatches {
+            root: self.clone(),
+            pattern: compiled,
+            walk: opts.walk(),
+        })
+    }
+}
+
+/// An iterator over the entries matching a glob pattern, returned by `PathDir::glob`.
+pub struct GlobMatches {
+    root: PathDir,
+    pattern: Pattern,
+    walk: Walk,
+}
+
+impl ::std::iter::Iterator for GlobMatches {
+    type Item = Result<PathType>;
+    fn next(&mut self) -> Option<Result<PathType>> {
+        let options = MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+        for entry in &mut self.walk {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => return Some(Err(err)),
+            };
+            let matched = match entry.strip_prefix(&self.root) {
+                Ok(rel) => self.pattern.matches_path_with(rel, options),
+                Err(_) => false,
+            };
+            if matched {
+                return Some(Ok(entry));
+            }
+        }
+        None
+    }
+}
+
+impl fmt::Debug for GlobMatches {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "GlobMatches({:?} in {})",
+            self.pattern.as_str(),
+            self.root.display()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{PathDir, PathFile};
+    use std::io;
+    use tempdir::TempDir;
+
+    fn glob(root: &PathDir, pattern: &str) -> Vec<String> {
+        root.glob(pattern)
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                entry.strip_prefix(root).unwrap().display().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sanity_glob() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let root = PathDir::new(tmp_dir.path()).unwrap();
+        for file in &[
+            "lib.rs", "main.rs", "notes.md", "a/mod.rs", "a/b/deep.rs", "a/b/data1.bin",
+            "a/b/data2.bin", "a/b/dataX.bin",
+        ] {
+            PathFile::create_all(root.join(file)).unwrap();
+        }
+
+        assert_eq!(vec!["lib.rs", "main.rs"], glob(&root, "*.rs"));
+        assert_eq!(
+            vec!["a/b/deep.rs", "a/mod.rs", "lib.rs", "main.rs"],
+            glob(&root, "**/*.rs")
+        );
+        assert_eq!(vec!["a/b/deep.rs"], glob(&root, "a/*/*.rs"));
+        assert_eq!(
+            vec!["a/b/data1.bin", "a/b/data2.bin"],
+            glob(&root, "**/data[0-9].bin")
+        );
+        assert_eq!(vec!["a/b/dataX.bin"], glob(&root, "a/b/data[!0-9].bin"));
+        assert_eq!(vec!["a/b"], glob(&root, "?/?"));
+        assert!(glob(&root, "*.toml").is_empty());
+
+        let err = root.glob("a/[b").unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.io_error().kind());
+        assert_eq!(root.as_path(), err.path());
+        assert!(err.to_string().contains("invalid glob pattern \"a/[b\""));
+    }
+}
@@ -0,0 +1,186 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Optimistic concurrency for read-modify-write edits.
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::time::SystemTime;
+use std_prelude::*;
+
+use super::file::replace_atomic;
+use super::{Error, PathFile};
+
+/// The error returned by `PathFile::edit_guarded`.
+pub enum EditError {
+    /// The file was changed by someone else while it was being edited, so the edit was not
+    /// written. The caller can retry the edit.
+    Conflict(PathFile),
+    /// An IO error occurred while reading or writing the file.
+    Io(Error),
+}
+
+impl PathFile {
+    /// Read the file, edit it with `f` and atomically write it back, unless the file was changed
+    /// in the meantime.
+    ///
+    /// The size and modification time (and the inode on unix) of the file are recorded before
+    /// reading it and checked again before writing it back. If they changed, the edit is
+    /// discarded and `EditError::Conflict` is returned, which is usually handled by retrying.
+    ///
+    /// > The check is only _optimistic_: a change made between the check and the write can
+    /// > still be lost. Use a lock if that is not acceptable.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{EditError, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "counter.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("0")?;
+    ///
+    /// loop {
+    ///     let incr = |s: String| (s.parse::<u32>().unwrap() + 1).to_string();
+    ///     match file.edit_guarded(incr) {
+    ///         Ok(()) => break,
+    ///         Err(EditError::Conflict(_)) => continue,
+    ///         Err(EditError::Io(err)) => return Err(err.into()),
+    ///     }
+    /// }
+    /// assert_eq!("1", file.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn edit_guarded<F>(&self, f: F) -> ::std::result::Result<(), EditError>
+    where
+        F: FnOnce(String) -> String,
+    {
+        let before = Stamp::new(&self.metadata()?);
+        let contents = f(self.read_string()?);
+        if before != Stamp::new(&self.metadata()?) {
+            return Err(EditError::Conflict(self.clone()));
+        }
+        replace_atomic(self, contents.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[derive(PartialEq)]
+/// The attributes of a file which change when it is modified.
+struct Stamp {
+    len: u64,
+    modified: Option<SystemTime>,
+    #[cfg(unix)]
+    ino: u64,
+}
+
+impl Stamp {
+    fn new(meta: &fs::Metadata) -> Stamp {
+        Stamp {
+            len: meta.len(),
+            modified: meta.modified().ok(),
+            #[cfg(unix)]
+            ino: {
+                use std::os::unix::fs::MetadataExt;
+                meta.ino()
+            },
+        }
+    }
+}
+
+impl EditError {
+    /// Return whether the edit failed because the file was changed by someone else.
+    pub fn is_conflict(&self) -> bool {
+        match *self {
+            EditError::Conflict(_) => true,
+            EditError::Io(_) => false,
+        }
+    }
+}
+
+impl fmt::Debug for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "EditError<{}>", self)
+    }
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EditError::Conflict(ref path) => write!(
+                f,
+                "file was modified during the edit when editing {}",
+                path.display()
+            ),
+            EditError::Io(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for EditError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            EditError::Conflict(_) => None,
+            EditError::Io(ref err) => Some(err),
+        }
+    }
+}
+
+impl From<Error> for EditError {
+    fn from(err: Error) -> EditError {
+        EditError::Io(err)
+    }
+}
+
+impl From<EditError> for io::Error {
+    fn from(err: EditError) -> io::Error {
+        match err {
+            EditError::Conflict(_) => io::Error::other(err),
+            EditError::Io(err) => err.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{EditError, PathDir, PathFile};
+    use tempdir::TempDir;
+
+    #[test]
+    fn sanity_edit_guarded() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("state")).unwrap();
+        file.write_str("a").unwrap();
+
+        file.edit_guarded(|s| s + "b").unwrap();
+        assert_eq!("ab", file.read_string().unwrap());
+
+        let err = file
+            .edit_guarded(|s| {
+                file.write_str("concurrent").unwrap();
+                s + "c"
+            })
+            .unwrap_err();
+        match err {
+            EditError::Conflict(ref path) => assert_eq!(&file, path),
+            EditError::Io(err) => panic!("unexpected error: {}", err),
+        }
+        assert!(err.is_conflict());
+        assert!(err.to_string().contains(&file.display().to_string()));
+        assert_eq!("concurrent", file.read_string().unwrap());
+
+        let missing = PathFile::mock(tmp_abs.join("missing"));
+        assert!(!missing.edit_guarded(|s| s).unwrap_err().is_conflict());
+    }
+}
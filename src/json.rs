@@ -0,0 +1,115 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Reading and writing JSON files, enabled by the `serialize` feature.
+
+use std::io;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+
+use super::{Error, Result};
+use super::PathFile;
+
+impl PathFile {
+    /// Read the file and deserialize its contents as JSON.
+    ///
+    /// Invalid JSON returns `io::ErrorKind::InvalidData` with the line and column of the error
+    /// as well as the path in the message.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::collections::BTreeMap;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.json";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// let mut expected = BTreeMap::new();
+    /// expected.insert("foo".to_string(), vec![1, 2]);
+    ///
+    /// file.write_json(&expected)?;
+    /// assert_eq!(r#"{"foo":[1,2]}"#, file.read_string()?);
+    ///
+    /// let result: BTreeMap<String, Vec<u32>> = file.read_json()?;
+    /// assert_eq!(expected, result);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn read_json<T: DeserializeOwned>(&self) -> Result<T> {
+        let bytes = self.read_bytes()?;
+        serde_json::from_slice(&bytes).map_err(|err| {
+            Error::new(
+                io::Error::from(err),
+                "deserializing JSON from",
+                self.clone().into(),
+            )
+        })
+    }
+
+    /// Serialize `value` as compact JSON and write it to the file, truncating it first.
+    ///
+    /// See [`read_json`](struct.PathFile.html#method.read_json) for an example.
+    pub fn write_json<T: Serialize + ?Sized>(&self, value: &T) -> Result<()> {
+        let bytes = serde_json::to_vec(value).map_err(|err| self.json_error(err))?;
+        self.write_bytes(&bytes)
+    }
+
+    /// Serialize `value` as pretty-printed JSON and write it to the file, truncating it first.
+    pub fn write_json_pretty<T: Serialize + ?Sized>(&self, value: &T) -> Result<()> {
+        let bytes = serde_json::to_vec_pretty(value).map_err(|err| self.json_error(err))?;
+        self.write_bytes(&bytes)
+    }
+
+    fn json_error(&self, err: serde_json::Error) -> Error {
+        Error::new(io::Error::from(err), "serializing JSON for", self.clone().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use tempdir::TempDir;
+    use super::super::{PathDir, PathFile};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        retries: u32,
+        tags: Vec<String>,
+    }
+
+    #[test]
+    fn sanity_json() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("config.json")).unwrap();
+
+        let expected = Config {
+            name: "foo".to_string(),
+            retries: 3,
+            tags: vec!["a".to_string(), "b".to_string()],
+        };
+        file.write_json(&expected).unwrap();
+        assert_eq!(expected, file.read_json::<Config>().unwrap());
+
+        file.write_json_pretty(&expected).unwrap();
+        assert!(file.read_string().unwrap().contains("\n  \"retries\": 3,\n"));
+        assert_eq!(expected, file.read_json::<Config>().unwrap());
+
+        file.write_str("{\n  \"name\": \"foo\",\n  \"retries\": oops\n}").unwrap();
+        let err = file.read_json::<Config>().unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.io_error().kind());
+        let msg = err.to_string();
+        assert!(msg.contains("line 3 column"), "{}", msg);
+        assert!(msg.contains(&file.display().to_string()), "{}", msg);
+    }
+}
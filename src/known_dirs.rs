@@ -0,0 +1,133 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Well-known directories of the user, enabled with the `dirs` feature.
+
+use dirs;
+use std::io;
+use std_prelude::*;
+
+use super::{Error, Result};
+use super::{PathArc, PathDir};
+
+impl PathDir {
+    /// Return the home directory of the current user.
+    ///
+    /// If the platform has no home directory this returns `io::ErrorKind::NotFound` for the
+    /// path `$HOME`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// use path_abs::PathDir;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let home = PathDir::home()?;
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn home() -> Result<PathDir> {
+        let home = base_dir(dirs::home_dir(), "$HOME", "home")?;
+        PathDir::new(home)
+    }
+
+    /// Return the configuration directory of the application `app_name`, creating it if it
+    /// doesn't exist.
+    ///
+    /// This is `app_name` inside the platform's configuration directory, i.e. `~/.config` on
+    /// linux, `~/Library/Application Support` on macOS and `%APPDATA%` on windows. If the
+    /// platform has no configuration directory this returns `io::ErrorKind::NotFound` for the
+    /// path `$CONFIG`; otherwise errors are from creating the directory.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// # extern crate path_abs;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let config = PathDir::config("my-app")?;
+    /// let settings = PathFile::create(config.join("settings.toml"))?;
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn config(app_name: &str) -> Result<PathDir> {
+        app_dir(dirs::config_dir(), "$CONFIG", "configuration", app_name)
+    }
+
+    /// Return the cache directory of the application `app_name`, creating it if it doesn't
+    /// exist.
+    ///
+    /// This is `app_name` inside the platform's cache directory, i.e. `~/.cache` on linux,
+    /// `~/Library/Caches` on macOS and `%LOCALAPPDATA%` on windows. If the platform has no
+    /// cache directory this returns `io::ErrorKind::NotFound` for the path `$CACHE`; otherwise
+    /// errors are from creating the directory.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// # extern crate path_abs;
+    /// use path_abs::PathDir;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let cache = PathDir::cache("my-app")?;
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn cache(app_name: &str) -> Result<PathDir> {
+        app_dir(dirs::cache_dir(), "$CACHE", "cache", app_name)
+    }
+}
+
+/// Create the directory `app_name` inside of `base`, which is the platform's `name` directory.
+fn app_dir(base: Option<PathBuf>, var: &str, name: &str, app_name: &str) -> Result<PathDir> {
+    let base = base_dir(base, var, name)?;
+    PathDir::create_all(base.join(app_name))
+}
+
+fn base_dir(dir: Option<PathBuf>, var: &str, name: &str) -> Result<PathBuf> {
+    dir.ok_or_else(|| {
+        let err = io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("the platform has no {} directory", name),
+        );
+        Error::new(err, "finding", PathArc::new(var))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::PathDir;
+    use super::app_dir;
+    use dirs;
+    use std::io;
+    use std::path::Path;
+    use tempdir::TempDir;
+
+    #[test]
+    fn sanity_known_dirs() {
+        if let Some(home) = dirs::home_dir() {
+            let dir = PathDir::home().unwrap();
+            assert_eq!(PathDir::new(home).unwrap(), dir);
+        }
+
+        // The application directory is created inside of the base directory
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let base = tmp_dir.path().join("config");
+        let app = "path_abs-sanity-known-dirs";
+        let dir = app_dir(Some(base.clone()), "$CONFIG", "configuration", app).unwrap();
+        assert!(dir.exists());
+        assert_eq!(base.join(app).as_path(), dir.as_path());
+        assert_eq!(dir, app_dir(Some(base), "$CONFIG", "configuration", app).unwrap());
+    }
+
+    #[test]
+    fn sanity_known_dirs_missing() {
+        let err = super::base_dir(None, "$CACHE", "cache").unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert_eq!("the platform has no cache directory when finding $CACHE", err.to_string());
+
+        let err = app_dir(None, "$CONFIG", "configuration", "app").unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert_eq!(Path::new("$CONFIG"), err.path());
+    }
+}
@@ -208,7 +208,25 @@ extern crate serde;
 #[macro_use]
 #[cfg(feature = "serialize")]
 extern crate serde_derive;
+#[cfg(feature = "dirs")]
+extern crate dirs;
+#[cfg(feature = "fs-lock")]
+extern crate fs2;
+#[cfg(feature = "glob")]
+extern crate glob;
+#[cfg(unix)]
+extern crate libc;
+#[cfg(feature = "mmap")]
+extern crate memmap2;
+#[cfg(feature = "parallel")]
+extern crate rayon;
+#[cfg(feature = "watch")]
+extern crate notify;
+#[cfg(feature = "digest")]
+extern crate sha2;
 extern crate std_prelude;
+#[cfg(any(test, feature = "serialize"))]
+extern crate serde_json;
 #[cfg(feature = "serialize")]
 extern crate stfu8;
 
@@ -218,8 +236,6 @@ extern crate pretty_assertions;
 #[cfg(test)]
 extern crate regex;
 #[cfg(test)]
-extern crate serde_json;
-#[cfg(test)]
 extern crate tempdir;
 
 use std::io;
@@ -229,25 +245,78 @@ use std_prelude::*;
 
 mod abs;
 mod arc;
+#[cfg(feature = "digest")]
+mod checksum;
+mod copy;
+mod copy_dir;
+mod diff;
 mod dir;
 mod edit;
 mod file;
+mod find;
+#[cfg(feature = "glob")]
+mod glob_dir;
+mod guarded;
+#[cfg(feature = "dirs")]
+mod known_dirs;
+#[cfg(feature = "serialize")]
+mod json;
+#[cfg(feature = "fs-lock")]
+mod lock;
+mod mirror;
+#[cfg(feature = "mmap")]
+mod mmap;
 pub mod open;
+#[cfg(feature = "parallel")]
+mod par_walk;
 #[cfg(feature = "serialize")]
 mod ser;
+mod stats;
+mod temp;
+mod tree;
 mod ty;
 mod write;
 mod read;
+mod rename;
+mod retry;
+mod walk;
+#[cfg(feature = "watch")]
+mod watch;
 
 pub use abs::PathAbs;
 pub use arc::{current_dir, PathArc};
-pub use dir::{ListDir, PathDir};
+#[cfg(feature = "digest")]
+pub use checksum::VerifyError;
+pub use copy::{BackupPolicy, CopyOptions};
+pub use copy_dir::{DirCopyOptions, SymlinkPolicy};
+pub use diff::DirDiff;
+pub use dir::{
+    Ancestors, Entry, EntryMeta, ErrorPolicy, ListDir, ListDirs, ListError, ListFiles, ListMeta,
+    ListRel, PathDir, RecursiveError,
+};
 pub use file::PathFile;
+#[cfg(feature = "glob")]
+pub use glob_dir::GlobMatches;
+pub use guarded::EditError;
+#[cfg(feature = "fs-lock")]
+pub use lock::FileLock;
+pub use mirror::MirrorReport;
+#[cfg(feature = "mmap")]
+pub use mmap::Mmap;
+pub use open::PathOpenOptions;
+pub use rename::RenamePolicy;
+pub use retry::RetryPolicy;
+pub use stats::DirCount;
+pub use temp::{PersistError, TmpDir, TmpFile};
+pub use tree::DirTree;
 pub use ty::PathType;
+pub use walk::{Walk, WalkBuilder, WalkMeta, WalkRel};
+#[cfg(feature = "watch")]
+pub use watch::{DirWatcher, WatchEvent};
 
 pub use edit::FileEdit;
 pub use write::FileWrite;
-pub use read::FileRead;
+pub use read::{FileRead, Lines};
 
 pub type Result<T> = ::std::result::Result<T, Error>;
 
@@ -392,7 +461,8 @@ mod tests {
                     escape(&foo)
                 )
             };
-            assert_match!(pat, foo.edit().unwrap_err())
+            assert_match!(pat, foo.edit().unwrap_err());
+            assert_match!(pat, foo.read().unwrap_err());
         }
     }
 }
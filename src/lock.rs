@@ -0,0 +1,178 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Advisory file locks, enabled by the `fs-lock` feature.
+
+use fs2::{self, FileExt};
+use std::fmt;
+use std::fs;
+use std::io;
+use std_prelude::*;
+
+use super::{Error, Result};
+use super::{FileEdit, FileRead, PathFile};
+
+/// An open file handle holding an advisory lock on its file, returned by the `PathFile::lock_*`
+/// methods. The lock is released when the `FileLock` is dropped.
+///
+/// Derefs to the open file handle: `FileEdit` for exclusive locks and `FileRead` for shared
+/// locks.
+///
+/// > The locks are _advisory_: they only exclude other processes which also take a lock on the
+/// > file, nothing prevents them from reading or writing it otherwise.
+pub struct FileLock<F: AsRef<File>> {
+    handle: F,
+}
+
+impl PathFile {
+    /// Open the file for editing and acquire an exclusive lock on it, blocking until the lock
+    /// can be acquired.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::io::Write;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    ///
+    /// {
+    ///     let mut lock = file.lock_exclusive()?;
+    ///     lock.write_all(b"foo")?;
+    ///
+    ///     // The file is already locked
+    ///     assert!(file.try_lock_shared().is_err());
+    /// }
+    ///
+    /// // The lock was released when it was dropped
+    /// let _lock = file.try_lock_shared()?;
+    /// assert_eq!("foo", file.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn lock_exclusive(&self) -> Result<FileLock<FileEdit>> {
+        let edit = self.edit()?;
+        // Called through the trait since newer versions of std have inherent methods with the
+        // same names.
+        FileExt::lock_exclusive(AsRef::<fs::File>::as_ref(&edit))
+            .map_err(|err| Error::new(err, "locking", self.clone().into()))?;
+        Ok(FileLock { handle: edit })
+    }
+
+    /// Open the file as read-only and acquire a shared lock on it, blocking until the lock can
+    /// be acquired.
+    ///
+    /// Any number of shared locks can be held at once, but not while an exclusive lock is held.
+    pub fn lock_shared(&self) -> Result<FileLock<FileRead>> {
+        let read = self.read()?;
+        FileExt::lock_shared(AsRef::<fs::File>::as_ref(&read))
+            .map_err(|err| Error::new(err, "locking shared", self.clone().into()))?;
+        Ok(FileLock { handle: read })
+    }
+
+    /// Like `lock_exclusive`, except it returns `io::ErrorKind::WouldBlock` immediately if the
+    /// file is already locked.
+    pub fn try_lock_exclusive(&self) -> Result<FileLock<FileEdit>> {
+        let edit = self.edit()?;
+        FileExt::try_lock_exclusive(AsRef::<fs::File>::as_ref(&edit))
+            .map_err(|err| Error::new(contended(err), "locking", self.clone().into()))?;
+        Ok(FileLock { handle: edit })
+    }
+
+    /// Like `lock_shared`, except it returns `io::ErrorKind::WouldBlock` immediately if the
+    /// file is exclusively locked.
+    pub fn try_lock_shared(&self) -> Result<FileLock<FileRead>> {
+        let read = self.read()?;
+        FileExt::try_lock_shared(AsRef::<fs::File>::as_ref(&read))
+            .map_err(|err| Error::new(contended(err), "locking shared", self.clone().into()))?;
+        Ok(FileLock { handle: read })
+    }
+}
+
+/// Give the error of a contended lock the `WouldBlock` kind, which it doesn't have on Windows.
+fn contended(err: io::Error) -> io::Error {
+    let raw = fs2::lock_contended_error().raw_os_error();
+    if err.kind() != io::ErrorKind::WouldBlock && raw.is_some() && err.raw_os_error() == raw {
+        io::Error::new(io::ErrorKind::WouldBlock, err)
+    } else {
+        err
+    }
+}
+
+impl<F: AsRef<File>> Drop for FileLock<F> {
+    fn drop(&mut self) {
+        // The lock is also released when the file is closed, so errors can be ignored.
+        let _ = FileExt::unlock(self.handle.as_ref());
+    }
+}
+
+impl<F: AsRef<File> + fmt::Debug> fmt::Debug for FileLock<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileLock(")?;
+        self.handle.fmt(f)?;
+        write!(f, ")")
+    }
+}
+
+impl<F: AsRef<File>> AsRef<fs::File> for FileLock<F> {
+    fn as_ref(&self) -> &fs::File {
+        self.handle.as_ref()
+    }
+}
+
+impl<F: AsRef<File>> Deref for FileLock<F> {
+    type Target = F;
+
+    fn deref(&self) -> &F {
+        &self.handle
+    }
+}
+
+impl<F: AsRef<File>> DerefMut for FileLock<F> {
+    fn deref_mut(&mut self) -> &mut F {
+        &mut self.handle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::thread;
+    use tempdir::TempDir;
+    use super::super::{PathDir, PathFile};
+
+    #[test]
+    fn sanity_lock_thread() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+
+        let lock = file.lock_exclusive().unwrap();
+        let other = file.clone();
+        let err = thread::spawn(move || other.try_lock_exclusive().unwrap_err())
+            .join()
+            .unwrap();
+        assert_eq!(io::ErrorKind::WouldBlock, err.io_error().kind());
+        assert_eq!(file.as_path(), err.path());
+
+        drop(lock);
+        let other = file.clone();
+        thread::spawn(move || other.try_lock_exclusive().map(|_| ()))
+            .join()
+            .unwrap()
+            .unwrap();
+
+        // Shared locks don't exclude each other
+        let _a = file.lock_shared().unwrap();
+        let _b = file.try_lock_shared().unwrap();
+        assert!(file.try_lock_exclusive().is_err());
+    }
+}
@@ -0,0 +1,382 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! One-way directory synchronization.
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std_prelude::*;
+
+use super::dir::{canonicalize_existing, remove_entry};
+use super::{CopyOptions, Error, Result};
+use super::{PathAbs, PathArc, PathDir, PathFile};
+
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+/// The changes made to the destination by `PathDir::mirror`.
+///
+/// The paths are relative to the source and destination directories, in sorted order.
+pub struct MirrorReport {
+    /// Files which were missing or changed in the destination and were copied.
+    pub copied: Vec<PathBuf>,
+    /// Directories which were missing in the destination and were created.
+    pub created: Vec<PathBuf>,
+    /// Files, directories and symlinks which are not in the source and were removed from the
+    /// destination. The contents of removed directories are not listed.
+    pub deleted: Vec<PathBuf>,
+}
+
+impl MirrorReport {
+    /// Return whether nothing was changed.
+    pub fn is_empty(&self) -> bool {
+        self.copied.is_empty() && self.created.is_empty() && self.deleted.is_empty()
+    }
+}
+
+impl PathDir {
+    /// Make `dest` match the directory, returning a report of the changes.
+    ///
+    /// Files which are missing in `dest` or whose size or modification time differ are copied,
+    /// preserving their modification time. Missing directories are created and everything in
+    /// `dest` which is not in the source is removed. `dest` and its missing parents are created.
+    ///
+    /// Symlinks in the source are followed, so their targets are copied. Symlinks in `dest` are
+    /// never followed, they are replaced or removed.
+    ///
+    /// `dest` can't contain or be contained in the directory, otherwise
+    /// `io::ErrorKind::InvalidInput` is returned.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::path::PathBuf;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// let example_site = "example_site";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// # let example_site = &tmp.path().join(example_site);
+    /// let build = PathDir::create(example)?;
+    /// PathFile::create(build.join("index.html"))?.write_str("<html/>")?;
+    /// let site = PathDir::create(example_site)?;
+    /// PathFile::create(site.join("stale.html"))?;
+    ///
+    /// let report = build.mirror(&site)?;
+    /// assert_eq!(vec![PathBuf::from("index.html")], report.copied);
+    /// assert_eq!(vec![PathBuf::from("stale.html")], report.deleted);
+    ///
+    /// // Nothing changed since
+    /// assert!(build.mirror(&site)?.is_empty());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn mirror<P: AsRef<Path>>(&self, dest: P) -> Result<MirrorReport> {
+        self.mirror_impl(dest.as_ref(), false)
+    }
+
+    /// Return the report of what `mirror` would change in `dest` without changing anything.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::path::PathBuf;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// let example_site = "example_site";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// # let example_site = &tmp.path().join(example_site);
+    /// let build = PathDir::create(example)?;
+    /// PathFile::create(build.join("index.html"))?;
+    ///
+    /// let report = build.mirror_dry_run(example_site)?;
+    /// assert_eq!(vec![PathBuf::from("index.html")], report.copied);
+    /// assert!(!example_site.exists());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn mirror_dry_run<P: AsRef<Path>>(&self, dest: P) -> Result<MirrorReport> {
+        self.mirror_impl(dest.as_ref(), true)
+    }
+
+    fn mirror_impl(&self, dest: &Path, dry_run: bool) -> Result<MirrorReport> {
+        let dest = PathAbs::new(dest)?;
+        // Compare the resolved paths, since either can be reached through a symlink.
+        let (resolved, source) = (canonicalize_existing(&dest)?, self.canonicalize()?);
+        if resolved.starts_with(&source) || source.starts_with(&resolved) {
+            return Err(Error::new(
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "destination contains or is inside of the source directory",
+                ),
+                &format!("mirroring {} from", dest.display()),
+                self.clone().into(),
+            ));
+        }
+        let exists = match fs::symlink_metadata(&dest) {
+            Ok(ref meta) if meta.is_dir() => true,
+            Ok(_) => {
+                return Err(Error::new(
+                    io::Error::new(
+                        io::ErrorKind::NotADirectory,
+                        "path exists but is not a directory",
+                    ),
+                    &format!("mirroring {} from", dest.display()),
+                    self.clone().into(),
+                ))
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => false,
+            Err(err) => return Err(Error::new(err, "getting symlink_metadata of", dest.into())),
+        };
+        if !exists && !dry_run {
+            PathDir::create_all(&dest)?;
+        }
+        let mut mirror = Mirror {
+            dry_run,
+            files: CopyOptions::new().preserve_mtime(true).clone(),
+            ancestors: vec![self.canonicalize()?.to_path_buf()],
+            report: MirrorReport::default(),
+        };
+        mirror.tree(self, &dest, Path::new(""), exists)?;
+        let mut report = mirror.report;
+        report.copied.sort();
+        report.created.sort();
+        report.deleted.sort();
+        Ok(report)
+    }
+}
+
+/// The state of a mirroring.
+struct Mirror {
+    dry_run: bool,
+    files: CopyOptions,
+    /// The canonical paths of the directory being mirrored and the directories containing it,
+    /// which are used to detect symlink loops.
+    ancestors: Vec<PathBuf>,
+    report: MirrorReport,
+}
+
+impl Mirror {
+    /// Mirror the contents of `src` into `dest`, which is an existing directory if `exists`.
+    fn tree(&mut self, src: &Path, dest: &Path, rel: &Path, exists: bool) -> Result<()> {
+        let sources = read_dir(src, fs::metadata)?;
+        let mut dests = if exists {
+            read_dir(dest, fs::symlink_metadata)?
+        } else {
+            BTreeMap::new()
+        };
+
+        for (name, meta) in &dests {
+            if !sources.contains_key(name) {
+                self.delete(&dest.join(name), rel.join(name), meta)?;
+            }
+        }
+        for (name, meta) in sources {
+            let existing = dests.remove(&name);
+            self.entry(
+                &src.join(&name),
+                &dest.join(&name),
+                &rel.join(&name),
+                &meta,
+                existing,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Mirror the entry at `from` to `to`, which has `existing` metadata if it exists.
+    fn entry(
+        &mut self,
+        from: &Path,
+        to: &Path,
+        rel: &Path,
+        meta: &fs::Metadata,
+        existing: Option<fs::Metadata>,
+    ) -> Result<()> {
+        let err_map = |err| {
+            Error::new(
+                err,
+                &format!("mirroring {} from", to.display()),
+                PathArc::new(from),
+            )
+        };
+
+        // Only an existing entry of the same type is kept, symlinks are always replaced.
+        let existing = match existing {
+            Some(ref old) if meta.is_dir() && !old.is_dir() => {
+                self.delete(to, rel.to_path_buf(), old)?;
+                None
+            }
+            Some(ref old) if !meta.is_dir() && !old.is_file() => {
+                self.delete(to, rel.to_path_buf(), old)?;
+                None
+            }
+            existing => existing,
+        };
+
+        if meta.is_dir() {
+            let canonical = from.canonicalize().map_err(err_map)?;
+            if self.ancestors.contains(&canonical) {
+                return Err(err_map(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "symlink loop detected",
+                )));
+            }
+            if existing.is_none() {
+                if !self.dry_run {
+                    fs::create_dir(to).map_err(err_map)?;
+                }
+                self.report.created.push(rel.to_path_buf());
+            }
+            self.ancestors.push(canonical);
+            let mirrored = self.tree(from, to, rel, existing.is_some());
+            self.ancestors.pop();
+            return mirrored;
+        }
+
+        let changed = match existing {
+            Some(ref old) => {
+                old.len() != meta.len()
+                    || old.modified().map_err(err_map)? != meta.modified().map_err(err_map)?
+            }
+            None => true,
+        };
+        if changed {
+            if !self.dry_run {
+                PathFile(PathAbs(PathArc::new(from))).copy_with(to, &self.files)?;
+            }
+            self.report.copied.push(rel.to_path_buf());
+        }
+        Ok(())
+    }
+
+    /// Remove the entry at `path` which is not in the source.
+    fn delete(&mut self, path: &Path, rel: PathBuf, meta: &fs::Metadata) -> Result<()> {
+        if !self.dry_run {
+            remove_entry(path, meta.file_type(), &mut |_| {})?;
+        }
+        self.report.deleted.push(rel);
+        Ok(())
+    }
+}
+
+/// Read the entries of the directory at `path` by name, with the metadata from `get`.
+pub(crate) fn read_dir<F>(path: &Path, get: F) -> Result<BTreeMap<OsString, fs::Metadata>>
+where
+    F: Fn(PathBuf) -> io::Result<fs::Metadata>,
+{
+    let mut entries = BTreeMap::new();
+    let fsread =
+        fs::read_dir(path).map_err(|err| Error::new(err, "reading dir", PathArc::new(path)))?;
+    for entry in fsread {
+        let entry = entry.map_err(|err| Error::new(err, "reading dir", PathArc::new(path)))?;
+        let entry_path = entry.path();
+        let meta = get(entry_path.clone())
+            .map_err(|err| Error::new(err, "getting metadata of", PathArc::new(&entry_path)))?;
+        entries.insert(entry.file_name(), meta);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{PathDir, PathFile};
+    use std::io;
+    use std::path::PathBuf;
+    use tempdir::TempDir;
+
+    fn paths(paths: &[&str]) -> Vec<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn sanity_mirror() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let src = PathDir::create(tmp_abs.join("src")).unwrap();
+        PathFile::create_all(src.join("a/b.txt")).unwrap().write_str("b").unwrap();
+        PathFile::create(src.join("c.txt")).unwrap().write_str("c").unwrap();
+        PathDir::create(src.join("empty")).unwrap();
+
+        let dest = tmp_abs.join("dest");
+        let dry = src.mirror_dry_run(&dest).unwrap();
+        assert!(!dest.exists());
+
+        let report = src.mirror(&dest).unwrap();
+        assert_eq!(dry, report);
+        assert_eq!(paths(&["a/b.txt", "c.txt"]), report.copied);
+        assert_eq!(paths(&["a", "empty"]), report.created);
+        assert!(report.deleted.is_empty());
+        let dest = PathDir::new(dest).unwrap();
+        assert_eq!("b", PathFile::new(dest.join("a/b.txt")).unwrap().read_string().unwrap());
+
+        // The second run is a no-op
+        assert!(src.mirror(&dest).unwrap().is_empty());
+
+        // Changed and stale entries
+        PathFile::new(src.join("c.txt")).unwrap().write_str("changed").unwrap();
+        PathFile::create(dest.join("stale.txt")).unwrap();
+        PathFile::create_all(dest.join("stale/nested.txt")).unwrap();
+        PathFile::create(dest.join("empty/stale.txt")).unwrap();
+        PathDir::new(src.join("empty")).unwrap().remove().unwrap();
+        PathFile::create(src.join("empty")).unwrap();
+
+        let dry = src.mirror_dry_run(&dest).unwrap();
+        assert!(dest.join("stale.txt").exists());
+        let report = src.mirror(&dest).unwrap();
+        assert_eq!(dry, report);
+        assert_eq!(paths(&["c.txt", "empty"]), report.copied);
+        assert!(report.created.is_empty());
+        assert_eq!(paths(&["empty", "stale", "stale.txt"]), report.deleted);
+        assert!(!dest.join("stale.txt").exists());
+        assert!(!dest.join("stale").exists());
+        assert!(dest.join("empty").is_file());
+        assert_eq!("changed", PathFile::new(dest.join("c.txt")).unwrap().read_string().unwrap());
+        assert!(src.mirror(&dest).unwrap().is_empty());
+    }
+
+    #[test]
+    fn sanity_mirror_nested() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let src = PathDir::create(tmp_abs.join("src")).unwrap();
+
+        let err = src.mirror(src.join("dest")).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.io_error().kind());
+        let err = src.mirror(&tmp_abs).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.io_error().kind());
+        assert!(src.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sanity_mirror_through_symlink() {
+        use std::os::unix::fs::symlink;
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let src = PathDir::create_all(tmp_abs.join("a/src")).unwrap();
+        PathFile::create_with(src.join("file.txt"), b"file").unwrap();
+        symlink(&tmp_abs, tmp_abs.join("alias")).unwrap();
+
+        // `alias/a` is the parent of the source, which must not be deleted as stale
+        for dest in &["alias/a", "alias/a/src", "alias/a/src/missing/dest"] {
+            let dest = tmp_abs.join(dest);
+            let err = src.mirror_dry_run(&dest).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidInput, err.io_error().kind());
+            let err = src.mirror(&dest).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidInput, err.io_error().kind());
+        }
+        assert_eq!("file", PathFile::new(src.join("file.txt")).unwrap().read_string().unwrap());
+        assert!(!src.join("missing").exists());
+    }
+}
@@ -0,0 +1,128 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Read-only memory maps of files, enabled by the `mmap` feature.
+
+use std::fmt;
+use std_prelude::*;
+
+use memmap2;
+
+use super::{Error, Result};
+use super::PathFile;
+
+/// A read-only memory map of a file, created with `PathFile::mmap`. Derefs to the mapped bytes.
+///
+/// The mapping stays valid for the lifetime of the `Mmap`, even if the `PathFile` is dropped.
+pub struct Mmap {
+    path: PathFile,
+    // Empty files are not mapped since zero-length mappings are an error on most platforms.
+    map: Option<memmap2::Mmap>,
+}
+
+impl PathFile {
+    /// Memory map the file as read-only.
+    ///
+    /// # Safety
+    ///
+    /// This method is safe to call, but the mapping is only valid while no other process (or
+    /// other part of this process) modifies the file. In particular, if the file is truncated
+    /// while it is mapped then accessing the removed part of the mapping will crash the process
+    /// with `SIGBUS` on unix. Only map files which you know will not be modified.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("some index")?;
+    ///
+    /// let map = file.mmap()?;
+    /// assert_eq!(b"some index", &map[..]);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn mmap(&self) -> Result<Mmap> {
+        let read = self.read()?;
+        let file: &File = read.as_ref();
+        let len = file
+            .metadata()
+            .map_err(|err| Error::new(err, "getting metadata of", self.clone().into()))?
+            .len();
+        let map = if len == 0 {
+            None
+        } else {
+            // Safety: documented above, the caller must ensure the file is not modified.
+            let map = unsafe { memmap2::Mmap::map(file) }
+                .map_err(|err| Error::new(err, "memory mapping", self.clone().into()))?;
+            Some(map)
+        };
+        Ok(Mmap {
+            path: self.clone(),
+            map,
+        })
+    }
+}
+
+impl Mmap {
+    /// Get the path of the mapped file.
+    pub fn path(&self) -> &PathFile {
+        &self.path
+    }
+}
+
+impl fmt::Debug for Mmap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Mmap(")?;
+        self.path.fmt(f)?;
+        write!(f, ")")
+    }
+}
+
+impl AsRef<[u8]> for Mmap {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self.map {
+            Some(ref map) => map,
+            None => &[],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+    use super::super::{PathDir, PathFile};
+
+    #[test]
+    fn sanity_mmap() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+
+        let empty = PathFile::create(tmp_abs.join("empty")).unwrap();
+        assert!(empty.mmap().unwrap().is_empty());
+
+        let file = PathFile::create(tmp_abs.join("foo")).unwrap();
+        let bytes: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+        file.write_bytes(&bytes).unwrap();
+        let map = file.mmap().unwrap();
+        drop(file);
+        assert_eq!(&bytes[..], &map[..]);
+    }
+}
@@ -9,10 +9,11 @@
 
 use std::fs;
 use std::fmt;
+use std::io;
 use std_prelude::*;
 
 use super::{Error, Result};
-use super::{PathArc, PathFile};
+use super::{FileEdit, FileRead, FileWrite, PathArc, PathFile};
 
 /// **INTERNAL TYPE: do not use directly.**
 ///
@@ -68,6 +69,72 @@ impl FileOpen {
             .map_err(|err| Error::new(err, "getting metadata for", self.path.clone().into()))
     }
 
+    /// Read from the file at `offset` without using the cursor, see `FileRead::read_at`.
+    pub(crate) fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize> {
+        #[cfg(unix)]
+        use std::os::unix::fs::FileExt;
+        #[cfg(windows)]
+        use std::os::windows::fs::FileExt;
+
+        loop {
+            #[cfg(unix)]
+            let res = self.file.read_at(buf, offset);
+            #[cfg(windows)]
+            let res = self.file.seek_read(buf, offset);
+            match res {
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                res => {
+                    return res.map_err(|err| {
+                        Error::new(
+                            err,
+                            &format!("reading at {} from", offset),
+                            self.path.clone().into(),
+                        )
+                    })
+                }
+            }
+        }
+    }
+
+    /// Write all of `buf` to the file at `offset` without using the cursor, see
+    /// `FileWrite::write_at`.
+    pub(crate) fn write_at(&self, buf: &[u8], offset: u64) -> Result<()> {
+        #[cfg(unix)]
+        use std::os::unix::fs::FileExt;
+        #[cfg(windows)]
+        use std::os::windows::fs::FileExt;
+
+        let mut written = 0;
+        while written < buf.len() {
+            let pos = offset + written as u64;
+            #[cfg(unix)]
+            let res = self.file.write_at(&buf[written..], pos);
+            #[cfg(windows)]
+            let res = self.file.seek_write(&buf[written..], pos);
+            match res {
+                Ok(0) => {
+                    let err =
+                        io::Error::new(io::ErrorKind::WriteZero, "failed to write whole buffer");
+                    return Err(Error::new(
+                        err,
+                        &format!("writing at {} to", pos),
+                        self.path.clone().into(),
+                    ));
+                }
+                Ok(len) => written += len,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => {
+                    return Err(Error::new(
+                        err,
+                        &format!("writing at {} to", pos),
+                        self.path.clone().into(),
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Creates a new independently owned handle to the underlying file.
     ///
     /// This function is identical to [std::fs::File::try_clone][0] except it has error
@@ -85,6 +152,172 @@ impl FileOpen {
     }
 }
 
+/// Options and flags which can be used to configure how a file is opened, returning the
+/// path-aware `FileRead`, `FileWrite` or `FileEdit` types.
+///
+/// This mirrors [std::fs::OpenOptions][0] except:
+///
+/// - It opens the file with one of `open_read`, `open_write` or `open_edit`, which always set
+///   the flags that the returned type requires.
+/// - It has error messages which include the action, the requested options and the path.
+///
+/// [0]: https://doc.rust-lang.org/std/fs/struct.OpenOptions.html
+///
+/// # Examples
+/// ```rust
+/// # extern crate path_abs;
+/// # extern crate tempdir;
+/// use std::io::Write;
+/// use path_abs::{PathFile, PathOpenOptions};
+///
+/// # fn try_main() -> ::std::io::Result<()> {
+/// let example = "example.txt";
+/// # let tmp = tempdir::TempDir::new("ex")?;
+/// # let example = &tmp.path().join(example);
+///
+/// let mut write = PathFile::open_opts().create_new(true).open_write(example)?;
+/// write.write_all(b"foo")?;
+/// write.flush()?;
+///
+/// // The file now exists, so it can't be created again.
+/// assert!(PathOpenOptions::new().create_new(true).open_write(example).is_err());
+///
+/// let file = PathFile::new(example)?;
+/// assert_eq!(&file, write.path());
+/// assert_eq!("foo", file.read_string()?);
+/// # Ok(()) } fn main() { try_main().unwrap() }
+/// ```
+#[derive(Clone, Debug)]
+pub struct PathOpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+}
+
+impl PathOpenOptions {
+    /// Creates a blank new set of options ready for configuration.
+    ///
+    /// All options are initially set to `false`.
+    pub fn new() -> PathOpenOptions {
+        PathOpenOptions {
+            read: false,
+            write: false,
+            append: false,
+            truncate: false,
+            create: false,
+            create_new: false,
+        }
+    }
+
+    /// Sets the option for read access.
+    pub fn read(&mut self, read: bool) -> &mut PathOpenOptions {
+        self.read = read;
+        self
+    }
+
+    /// Sets the option for write access.
+    pub fn write(&mut self, write: bool) -> &mut PathOpenOptions {
+        self.write = write;
+        self
+    }
+
+    /// Sets the option for the append mode.
+    pub fn append(&mut self, append: bool) -> &mut PathOpenOptions {
+        self.append = append;
+        self
+    }
+
+    /// Sets the option for truncating a previous file.
+    pub fn truncate(&mut self, truncate: bool) -> &mut PathOpenOptions {
+        self.truncate = truncate;
+        self
+    }
+
+    /// Sets the option for creating a new file if it does not exist.
+    pub fn create(&mut self, create: bool) -> &mut PathOpenOptions {
+        self.create = create;
+        self
+    }
+
+    /// Sets the option to always create a new file, failing with
+    /// `io::ErrorKind::AlreadyExists` if it already exists.
+    pub fn create_new(&mut self, create_new: bool) -> &mut PathOpenOptions {
+        self.create_new = create_new;
+        self
+    }
+
+    /// Open the file as a `FileRead`. `read` is always set to true.
+    pub fn open_read<P: AsRef<Path>>(&self, path: P) -> Result<FileRead> {
+        let mut options = self.clone();
+        options.read(true);
+        Ok(FileRead(options.open(path)?))
+    }
+
+    /// Open the file as a `FileWrite`. `write` is always set to true.
+    pub fn open_write<P: AsRef<Path>>(&self, path: P) -> Result<FileWrite> {
+        let mut options = self.clone();
+        options.write(true);
+        Ok(FileWrite(options.open(path)?))
+    }
+
+    /// Open the file as a `FileEdit`. `read` and `write` are always set to true.
+    pub fn open_edit<P: AsRef<Path>>(&self, path: P) -> Result<FileEdit> {
+        let mut options = self.clone();
+        options.read(true);
+        options.write(true);
+        Ok(FileEdit(options.open(path)?))
+    }
+
+    fn open<P: AsRef<Path>>(&self, path: P) -> Result<FileOpen> {
+        let file = fs::OpenOptions::new()
+            .read(self.read)
+            .write(self.write)
+            .append(self.append)
+            .truncate(self.truncate)
+            .create(self.create)
+            .create_new(self.create_new)
+            .open(&path)
+            .map_err(|err| {
+                Error::new(
+                    err,
+                    &format!("opening ({})", self.flags()),
+                    PathArc::new(&path),
+                )
+            })?;
+
+        // The file may have just been created, so it is only resolved after opening.
+        let path = PathFile::new(path)?;
+        Ok(FileOpen { path, file })
+    }
+
+    /// The names of the options which are set, i.e. `read, create_new`.
+    fn flags(&self) -> String {
+        let flags = [
+            ("read", self.read),
+            ("write", self.write),
+            ("append", self.append),
+            ("truncate", self.truncate),
+            ("create", self.create),
+            ("create_new", self.create_new),
+        ];
+        let names: Vec<&str> = flags
+            .iter()
+            .filter(|&&(_, set)| set)
+            .map(|&(name, _)| name)
+            .collect();
+        names.join(", ")
+    }
+}
+
+impl Default for PathOpenOptions {
+    fn default() -> PathOpenOptions {
+        PathOpenOptions::new()
+    }
+}
+
 impl fmt::Debug for FileOpen {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Open(")?;
@@ -116,3 +349,78 @@ impl From<FileOpen> for fs::File {
         orig.file
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{self, Read, Seek, SeekFrom, Write};
+    use tempdir::TempDir;
+    use super::FileOpen;
+    use super::super::{FileRead, FileWrite, PathDir, PathFile, PathOpenOptions};
+
+    #[test]
+    fn sanity_create_new_exists() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+
+        let err = PathOpenOptions::new()
+            .create_new(true)
+            .open_write(&file)
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+        assert_eq!(file.as_path(), err.path());
+        assert_eq!("opening (write, create_new)", err.action());
+    }
+
+    #[test]
+    fn sanity_append_read() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+        file.write_str("foo\n").unwrap();
+
+        let mut edit = PathFile::open_opts()
+            .append(true)
+            .read(true)
+            .open_edit(&file)
+            .unwrap();
+        edit.write_all(b"bar\n").unwrap();
+        edit.seek(SeekFrom::Start(0)).unwrap();
+
+        let mut s = String::new();
+        edit.read_to_string(&mut s).unwrap();
+        assert_eq!("foo\nbar\n", s);
+    }
+
+    #[test]
+    fn sanity_handle_helpers() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo.txt")).unwrap();
+
+        let mut write = FileWrite::create(&file).unwrap();
+        assert_eq!(&file, write.path());
+        write.write_str("foo\n").unwrap();
+        write.write_str("bar\n").unwrap();
+        write.flush_and_close().unwrap();
+
+        let mut read = FileRead::read(&file).unwrap();
+        assert_eq!(&file, read.path());
+        let mut buf = [0; 4];
+        read.read_exact(&mut buf).unwrap();
+        assert_eq!("bar\n", read.read_string().unwrap());
+        assert_eq!("", read.read_string().unwrap());
+        read.seek(SeekFrom::Start(2)).unwrap();
+        assert_eq!(b"o\nbar\n".to_vec(), read.read_bytes().unwrap());
+
+        // Writing to a handle which was only opened for reading
+        let mut options = fs::OpenOptions::new();
+        options.read(true);
+        let mut write = FileWrite(FileOpen::open(&file, options).unwrap());
+        let err = write.write_str("baz").unwrap_err();
+        assert_eq!(file.as_path(), err.path());
+        assert_eq!("writing", err.action());
+        assert_eq!("foo\nbar\n", file.read_string().unwrap());
+    }
+}
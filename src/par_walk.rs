@@ -0,0 +1,164 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Parallel recursive directory walks, enabled with the `parallel` feature.
+
+use rayon::iter::{walk_tree_prefix, ParallelIterator};
+use std::fs;
+
+use super::{Error, Result};
+use super::{PathDir, PathType};
+
+/// A node of the tree walked by `PathDir::par_walk`.
+enum Node {
+    /// The directory being walked, which is not yielded.
+    Root(PathDir),
+    /// An entry or error to yield, and whether it is a directory to descend into.
+    Entry(Result<PathType>, bool),
+}
+
+impl PathDir {
+    /// Recursively walk the directory in parallel, returning a rayon `ParallelIterator` over
+    /// every entry in the tree.
+    ///
+    /// The entries are the same as those of `PathDir::walk`, including the errors, but in no
+    /// particular order: directories are read on rayon's thread pool as soon as they are found.
+    /// Symlinks to directories are yielded as `PathType::Dir` but are not descended into.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate rayon;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    /// use rayon::prelude::*;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// PathFile::create_all(dir.join("a/b.txt"))?;
+    /// PathFile::create_all(dir.join("c/d.txt"))?;
+    ///
+    /// let files = dir
+    ///     .par_walk()
+    ///     .filter(|entry| entry.as_ref().map(|e| e.is_file()).unwrap_or(true))
+    ///     .collect::<Result<Vec<_>, _>>()?;
+    /// assert_eq!(2, files.len());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn par_walk(&self) -> impl ParallelIterator<Item = Result<PathType>> {
+        walk_tree_prefix(Node::Root(self.clone()), children).filter_map(|node| match node {
+            Node::Root(_) => None,
+            Node::Entry(entry, _) => Some(entry),
+        })
+    }
+}
+
+/// Read the entries of `node` if it is a directory to descend into.
+fn children(node: &Node) -> Vec<Node> {
+    let dir = match *node {
+        Node::Root(ref dir) | Node::Entry(Ok(PathType::Dir(ref dir)), true) => dir,
+        _ => return Vec::new(),
+    };
+    let read = match fs::read_dir(dir) {
+        Ok(read) => read,
+        Err(err) => {
+            let err = Error::new(err, "reading dir", dir.clone().into());
+            return vec![Node::Entry(Err(err), false)];
+        }
+    };
+    read.map(|entry| match entry {
+        Ok(entry) => {
+            let is_link = entry.file_type().map(|t| t.is_symlink()).unwrap_or(false);
+            match PathType::new(entry.path()) {
+                Ok(ty) => {
+                    let descend = ty.is_dir() && !is_link;
+                    Node::Entry(Ok(ty), descend)
+                }
+                Err(err) => Node::Entry(Err(err), false),
+            }
+        }
+        Err(err) => {
+            let err = Error::new(err, "iterating over", dir.clone().into());
+            Node::Entry(Err(err), false)
+        }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{PathDir, PathFile};
+    use rayon::iter::ParallelIterator;
+    use std::collections::HashSet;
+    use tempdir::TempDir;
+
+    #[test]
+    fn sanity_par_walk() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        for a in 0..5 {
+            for b in 0..5 {
+                PathFile::create_all(tmp_abs.join(format!("{}/{}/file.txt", a, b))).unwrap();
+                PathFile::create(tmp_abs.join(format!("{}/file{}.txt", a, b))).unwrap();
+            }
+        }
+
+        let walk: Vec<_> = tmp_abs.walk().map(|e| e.unwrap()).collect();
+        let par_walk: Vec<_> = tmp_abs.par_walk().map(|e| e.unwrap()).collect();
+        assert_eq!(walk.len(), par_walk.len());
+        let walk: HashSet<_> = walk.into_iter().collect();
+        let par_walk: HashSet<_> = par_walk.into_iter().collect();
+        assert_eq!(walk.len(), par_walk.len());
+        assert_eq!(walk, par_walk);
+        assert_eq!(
+            0,
+            PathDir::create(tmp_abs.join("empty"))
+                .unwrap()
+                .par_walk()
+                .count()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sanity_par_walk_errors() {
+        use std::fs;
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let locked = PathDir::create(tmp_abs.join("locked")).unwrap();
+        PathFile::create(locked.join("file")).unwrap();
+        symlink(&tmp_abs, tmp_abs.join("loop")).unwrap();
+        symlink("missing", tmp_abs.join("broken")).unwrap();
+        locked
+            .set_permissions(fs::Permissions::from_mode(0o000))
+            .unwrap();
+
+        let walk: Vec<_> = tmp_abs
+            .walk()
+            .map(|e| e.map_err(|e| e.to_string()))
+            .collect();
+        let par_walk: Vec<_> = tmp_abs
+            .par_walk()
+            .map(|e| e.map_err(|e| e.to_string()))
+            .collect();
+        locked
+            .set_permissions(fs::Permissions::from_mode(0o755))
+            .unwrap();
+
+        let mut walk: Vec<_> = walk.into_iter().map(|e| format!("{:?}", e)).collect();
+        let mut par_walk: Vec<_> = par_walk.into_iter().map(|e| format!("{:?}", e)).collect();
+        walk.sort();
+        par_walk.sort();
+        assert_eq!(walk, par_walk);
+        assert!(par_walk.iter().any(|e| e.contains("broken")));
+    }
+}
@@ -58,6 +58,36 @@ impl FileRead {
         Ok(FileRead(FileOpen::open_path(path, options)?))
     }
 
+    /// Read from the file at `offset` into `buf`, returning the number of bytes read.
+    ///
+    /// This does not use (or change) the cursor of the file on unix, but it may on Windows. Like
+    /// `io::Read::read`, fewer bytes than requested can be read, i.e. at the end of the file.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.bin";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let file = PathFile::create_with(example, b"0123456789")?;
+    /// let read = file.read()?;
+    ///
+    /// let mut buf = [0; 4];
+    /// assert_eq!(4, read.read_at(&mut buf, 2)?);
+    /// assert_eq!(b"2345", &buf);
+    /// assert_eq!(2, read.read_at(&mut buf, 8)?);
+    /// assert_eq!(b"89", &buf[..2]);
+    /// assert_eq!(0, read.read_at(&mut buf, 10)?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize> {
+        self.0.read_at(buf, offset)
+    }
+
     /// Read what remains of the file to a `String`.
     pub fn read_string(&mut self) -> Result<String> {
         let mut s = String::new();
@@ -67,6 +97,55 @@ impl FileRead {
             .map_err(|err| Error::new(err, "reading", self.path.clone().into()))?;
         Ok(s)
     }
+
+    /// Read what remains of the file to a `Vec<u8>`.
+    pub fn read_bytes(&mut self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.0
+            .file
+            .read_to_end(&mut bytes)
+            .map_err(|err| Error::new(err, "reading", self.path.clone().into()))?;
+        Ok(bytes)
+    }
+}
+
+/// An iterator over the lines of a file, returned by `PathFile::read_lines`.
+///
+/// Each line has its trailing `\n` (or `\r\n`) removed, like
+/// [std::io::BufRead::lines][0].
+///
+/// [0]: https://doc.rust-lang.org/std/io/trait.BufRead.html#method.lines
+pub struct Lines {
+    path: PathFile,
+    lines: io::Lines<BufReader<File>>,
+}
+
+impl Lines {
+    pub(crate) fn new(read: FileRead) -> Lines {
+        let FileOpen { path, file } = read.0;
+        Lines {
+            path,
+            lines: BufReader::new(file).lines(),
+        }
+    }
+}
+
+impl ::std::iter::Iterator for Lines {
+    type Item = Result<String>;
+    fn next(&mut self) -> Option<Result<String>> {
+        let path = &self.path;
+        self.lines
+            .next()
+            .map(|r| r.map_err(|err| Error::new(err, "reading lines of", path.clone().into())))
+    }
+}
+
+impl fmt::Debug for Lines {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Lines(")?;
+        self.path.fmt(f)?;
+        write!(f, ")")
+    }
 }
 
 impl fmt::Debug for FileRead {
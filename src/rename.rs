@@ -0,0 +1,603 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Renaming files with an explicit policy for existing destinations, swapping files and rotating
+//! logs.
+
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std_prelude::*;
+
+use super::file::create_temp;
+use super::{Error, Result};
+use super::{PathArc, PathFile};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+/// What `PathFile::rename_with` does when the destination already exists.
+pub enum RenamePolicy {
+    /// Replace the destination, like `PathFile::rename`.
+    Overwrite,
+    /// Fail with `io::ErrorKind::AlreadyExists`, leaving both files untouched.
+    NoClobber,
+    /// Move the existing destination out of the way by appending `.orig` to its file name
+    /// (replacing any previous `.orig` file), then rename.
+    RenameBoth,
+}
+
+impl PathFile {
+    /// Rename the file to `to`, handling an existing destination according to `policy`.
+    ///
+    /// With `RenamePolicy::NoClobber` the file is renamed with `renameat2(RENAME_NOREPLACE)` on
+    /// Linux, which atomically fails if `to` exists. Elsewhere, or if the filesystem doesn't
+    /// support it, the file is first hard linked to `to` and then the original name is removed.
+    /// If hard links are not supported either (i.e. FAT) the existence of `to` is checked before
+    /// renaming, which can race with another process creating it. Other errors are returned.
+    ///
+    /// This will not work if the new name is on a different mount point.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathFile, RenamePolicy};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// let example_out = "example.out";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// # let example_out = &tmp.path().join(example_out);
+    /// let file = PathFile::create(example)?;
+    /// file.write_str("new")?;
+    /// let out = PathFile::create(example_out)?;
+    /// out.write_str("old")?;
+    ///
+    /// // The destination exists
+    /// assert!(file.clone().rename_with(example_out, RenamePolicy::NoClobber).is_err());
+    ///
+    /// let out = file.rename_with(example_out, RenamePolicy::RenameBoth)?;
+    /// assert_eq!("new", out.read_string()?);
+    /// assert_eq!("old", PathFile::new(out.with_extension("out.orig"))?.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn rename_with<P: AsRef<Path>>(self, to: P, policy: RenamePolicy) -> Result<PathFile> {
+        let to = to.as_ref();
+        match policy {
+            RenamePolicy::Overwrite => self.rename(to),
+            RenamePolicy::NoClobber => {
+                self.rename_no_clobber(to)?;
+                PathFile::new(to)
+            }
+            RenamePolicy::RenameBoth => {
+                if fs::symlink_metadata(to).is_ok() {
+                    let mut orig = to.as_os_str().to_os_string();
+                    orig.push(".orig");
+                    fs::rename(to, &orig).map_err(|err| {
+                        Error::new(
+                            err,
+                            &format!("renaming to {} from", Path::new(&orig).display()),
+                            PathArc::new(to),
+                        )
+                    })?;
+                }
+                self.rename(to)
+            }
+        }
+    }
+
+    fn rename_no_clobber(&self, to: &Path) -> Result<()> {
+        let err_action = || format!("renaming to {} from", to.display());
+        let already_exists = || {
+            io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("destination {} already exists", to.display()),
+            )
+        };
+
+        match rename_noreplace(self, to) {
+            Ok(true) => return Ok(()),
+            Ok(false) => {}
+            Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                return Err(Error::new(
+                    already_exists(),
+                    &err_action(),
+                    self.clone().into(),
+                ));
+            }
+            Err(err) => return Err(Error::new(err, &err_action(), self.clone().into())),
+        }
+
+        match fs::hard_link(self, to) {
+            Ok(()) => {
+                return fs::remove_file(self).map_err(|err| {
+                    // Don't leave the file with two names.
+                    let _ = fs::remove_file(to);
+                    Error::new(err, &err_action(), self.clone().into())
+                });
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                return Err(Error::new(
+                    already_exists(),
+                    &err_action(),
+                    self.clone().into(),
+                ));
+            }
+            // Hard links are unsupported, fall back to checking first.
+            Err(ref err) if is_unsupported(err) => {}
+            Err(err) => return Err(Error::new(err, &err_action(), self.clone().into())),
+        }
+
+        if fs::symlink_metadata(to).is_ok() {
+            return Err(Error::new(
+                already_exists(),
+                &err_action(),
+                self.clone().into(),
+            ));
+        }
+        fs::rename(self, to).map_err(|err| Error::new(err, &err_action(), self.clone().into()))
+    }
+}
+
+#[cfg(target_os = "linux")]
+/// Rename `from` to `to` with `renameat2(RENAME_NOREPLACE)`, returning `Ok(false)` if it is not
+/// supported.
+fn rename_noreplace(from: &Path, to: &Path) -> io::Result<bool> {
+    use libc;
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = |path: &Path| {
+        CString::new(path.as_os_str().as_bytes())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains a null byte"))
+    };
+    let (c_from, c_to) = (c_path(from)?, c_path(to)?);
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_renameat2,
+            libc::AT_FDCWD,
+            c_from.as_ptr(),
+            libc::AT_FDCWD,
+            c_to.as_ptr(),
+            libc::RENAME_NOREPLACE,
+        )
+    };
+    if res == 0 {
+        return Ok(true);
+    }
+    let err = io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::EINVAL) | Some(libc::ENOSYS) | Some(libc::EPERM) => Ok(false),
+        _ => Err(err),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+/// Renaming without replacing is not supported, always fall back to hard linking.
+fn rename_noreplace(_from: &Path, _to: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+#[cfg(unix)]
+/// Return whether `err` means the operation is not supported by the platform or filesystem.
+fn is_unsupported(err: &io::Error) -> bool {
+    use libc;
+
+    matches!(
+        err.raw_os_error(),
+        Some(libc::EINVAL) | Some(libc::ENOSYS) | Some(libc::EPERM) | Some(libc::EOPNOTSUPP)
+    )
+}
+
+#[cfg(windows)]
+/// Return whether `err` means the operation is not supported by the platform or filesystem.
+fn is_unsupported(err: &io::Error) -> bool {
+    const ERROR_INVALID_FUNCTION: i32 = 1;
+    const ERROR_NOT_SUPPORTED: i32 = 50;
+    matches!(
+        err.raw_os_error(),
+        Some(ERROR_INVALID_FUNCTION) | Some(ERROR_NOT_SUPPORTED)
+    )
+}
+
+#[cfg(not(any(unix, windows)))]
+/// Return whether `err` means the operation is not supported by the platform or filesystem.
+fn is_unsupported(_: &io::Error) -> bool {
+    true
+}
+
+impl PathFile {
+    /// Atomically exchange the file with `other`, so that each path has the contents (and
+    /// metadata) the other had.
+    ///
+    /// On Linux this uses `renameat2` with `RENAME_EXCHANGE`. If that is unsupported (by the
+    /// platform or the filesystem), the files are swapped with three renames through a temporary
+    /// name next to `self`, which is _not_ atomic: a failure or crash in between can leave only
+    /// one of the files in place. The error states the step which failed.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let blue = "blue.conf";
+    /// let green = "green.conf";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let blue = &tmp.path().join(blue);
+    /// # let green = &tmp.path().join(green);
+    /// let blue = PathFile::create_with(blue, b"blue")?;
+    /// let green = PathFile::create_with(green, b"green")?;
+    ///
+    /// blue.swap(&green)?;
+    /// assert_eq!("green", blue.read_string()?);
+    /// assert_eq!("blue", green.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn swap(&self, other: &PathFile) -> Result<()> {
+        if exchange(self, other)? {
+            return Ok(());
+        }
+        swap_renames(self, other)
+    }
+}
+
+/// Swap `a` and `b` with three renames through a temporary file.
+fn swap_renames(a: &PathFile, b: &PathFile) -> Result<()> {
+    let dir = a.parent().unwrap_or_else(|| Path::new("/"));
+    let name = a.file_name().unwrap_or_else(|| OsStr::new("swap"));
+    let (temp, _) = create_temp(dir, name)?;
+    let step = |n: u32, to: &Path| {
+        format!(
+            "swapping (step {} of 3, renaming to {}) from",
+            n,
+            to.display()
+        )
+    };
+
+    if let Err(err) = fs::rename(a, &temp) {
+        let _ = fs::remove_file(&temp);
+        return Err(Error::new(err, &step(1, &temp), a.clone().into()));
+    }
+    if let Err(err) = fs::rename(b, a) {
+        // Put the file back where it was.
+        let _ = fs::rename(&temp, a);
+        return Err(Error::new(err, &step(2, a), b.clone().into()));
+    }
+    fs::rename(&temp, b).map_err(|err| Error::new(err, &step(3, b), temp.clone()))
+}
+
+#[cfg(target_os = "linux")]
+/// Exchange `a` and `b` with `renameat2(RENAME_EXCHANGE)`, returning `Ok(false)` if it is not
+/// supported.
+fn exchange(a: &PathFile, b: &PathFile) -> Result<bool> {
+    use libc;
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = |path: &PathFile| {
+        CString::new(path.as_os_str().as_bytes()).map_err(|_| {
+            Error::new(
+                io::Error::new(io::ErrorKind::InvalidInput, "path contains a null byte"),
+                "swapping",
+                path.clone().into(),
+            )
+        })
+    };
+    let (c_a, c_b) = (c_path(a)?, c_path(b)?);
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_renameat2,
+            libc::AT_FDCWD,
+            c_a.as_ptr(),
+            libc::AT_FDCWD,
+            c_b.as_ptr(),
+            libc::RENAME_EXCHANGE,
+        )
+    };
+    if res == 0 {
+        return Ok(true);
+    }
+    let err = io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::EINVAL) | Some(libc::ENOSYS) | Some(libc::EOPNOTSUPP) => Ok(false),
+        _ => Err(Error::new(
+            err,
+            &format!("swapping with {}", b.display()),
+            a.clone().into(),
+        )),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+/// Atomic exchange is not supported, always fall back to renaming.
+fn exchange(_a: &PathFile, _b: &PathFile) -> Result<bool> {
+    Ok(false)
+}
+
+impl PathFile {
+    /// Rotate the file like a log: `app.log.N` is renamed to `app.log.N+1`, `app.log` to
+    /// `app.log.1`, and a new empty `app.log` is created with the same permissions.
+    ///
+    /// At most `keep` rotated files are kept, older ones are removed (`keep == 0` only empties
+    /// the file). Files are shifted from the highest index downward so that none is clobbered,
+    /// and missing indices are skipped.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::PathFile;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "app.log";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let log = PathFile::create(example)?;
+    /// log.append_str("first\n")?;
+    ///
+    /// log.rotate(2)?;
+    /// assert_eq!("", log.read_string()?);
+    /// assert_eq!("first\n", PathFile::new(log.with_extension("log.1"))?.read_string()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn rotate(&self, keep: usize) -> Result<()> {
+        let rotated = |n: usize| {
+            let mut path = self.as_os_str().to_os_string();
+            path.push(format!(".{}", n));
+            PathBuf::from(path)
+        };
+        let rename = |from: &Path, to: &Path| {
+            fs::rename(from, to).map_err(|err| {
+                Error::new(
+                    err,
+                    &format!("renaming to {} from", to.display()),
+                    PathArc::new(from),
+                )
+            })
+        };
+        let perm = match fs::metadata(self) {
+            Ok(meta) => Some(meta.permissions()),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(Error::new(err, "getting metadata of", self.clone().into())),
+        };
+
+        // Remove everything which would be shifted beyond `keep`, including stale files left by
+        // a previous rotation with a larger `keep`.
+        for n in self.rotated_indices()? {
+            if n >= keep {
+                let path = rotated(n);
+                match fs::remove_file(&path) {
+                    Ok(()) => {}
+                    Err(ref err) if err.kind() == io::ErrorKind::NotFound => {}
+                    Err(err) => return Err(Error::new(err, "removing", PathArc::new(path))),
+                }
+            }
+        }
+
+        for n in (1..keep).rev() {
+            let from = rotated(n);
+            if fs::symlink_metadata(&from).is_ok() {
+                rename(&from, &rotated(n + 1))?;
+            }
+        }
+
+        if keep > 0 && perm.is_some() {
+            rename(self, &rotated(1))?;
+        }
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(self)
+            .map_err(|err| Error::new(err, "creating", self.clone().into()))?;
+        if let Some(perm) = perm {
+            file.set_permissions(perm)
+                .map_err(|err| Error::new(err, "setting permisions for", self.clone().into()))?;
+        }
+        Ok(())
+    }
+
+    /// The indices `N` of the existing `{self}.N` files.
+    fn rotated_indices(&self) -> Result<Vec<usize>> {
+        let dir = self.parent().unwrap_or_else(|| Path::new("/"));
+        let name = match self.file_name() {
+            Some(name) => name.to_string_lossy().into_owned() + ".",
+            None => return Ok(Vec::new()),
+        };
+        let entries =
+            fs::read_dir(dir).map_err(|err| Error::new(err, "reading dir", PathArc::new(dir)))?;
+        let mut indices = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|err| Error::new(err, "reading dir", PathArc::new(dir)))?;
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+            if file_name.starts_with(&name) {
+                if let Ok(n) = file_name[name.len()..].parse::<usize>() {
+                    indices.push(n);
+                }
+            }
+        }
+        Ok(indices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{PathDir, PathFile, RenamePolicy};
+    use std::io;
+    use tempdir::TempDir;
+
+    fn setup(existing: bool) -> (TempDir, PathFile, PathDir) {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let file = PathFile::create(tmp_abs.join("foo")).unwrap();
+        file.write_str("new").unwrap();
+        if existing {
+            PathFile::create(tmp_abs.join("bar"))
+                .unwrap()
+                .write_str("old")
+                .unwrap();
+        }
+        (tmp_dir, file, tmp_abs)
+    }
+
+    #[test]
+    fn sanity_rename_overwrite() {
+        for &existing in &[false, true] {
+            let (_tmp, file, dir) = setup(existing);
+            let bar = file
+                .clone()
+                .rename_with(dir.join("bar"), RenamePolicy::Overwrite)
+                .unwrap();
+            assert!(!file.exists());
+            assert_eq!("new", bar.read_string().unwrap());
+        }
+    }
+
+    #[test]
+    fn sanity_rename_no_clobber() {
+        let (_tmp, file, dir) = setup(false);
+        let bar = file
+            .clone()
+            .rename_with(dir.join("bar"), RenamePolicy::NoClobber)
+            .unwrap();
+        assert!(!file.exists());
+        assert_eq!("new", bar.read_string().unwrap());
+
+        let (_tmp, file, dir) = setup(true);
+        let err = file
+            .clone()
+            .rename_with(dir.join("bar"), RenamePolicy::NoClobber)
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+        assert_eq!(file.as_path(), err.path());
+        assert_eq!("new", file.read_string().unwrap());
+        assert_eq!(
+            "old",
+            PathFile::new(dir.join("bar"))
+                .unwrap()
+                .read_string()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn sanity_rename_no_clobber_errors() {
+        // Errors other than an existing destination are returned as they are
+        let (_tmp, file, dir) = setup(true);
+        let err = file
+            .clone()
+            .rename_with(dir.join("bar").join("baz"), RenamePolicy::NoClobber)
+            .unwrap_err();
+        assert_ne!(io::ErrorKind::AlreadyExists, err.io_error().kind());
+        #[cfg(unix)]
+        assert_eq!(Some(::libc::ENOTDIR), err.io_error().raw_os_error());
+        assert_eq!(file.as_path(), err.path());
+        assert_eq!("new", file.read_string().unwrap());
+
+        let err = file
+            .clone()
+            .rename_with(dir.join("missing").join("baz"), RenamePolicy::NoClobber)
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert!(file.exists());
+    }
+
+    #[test]
+    fn sanity_rename_both() {
+        let (_tmp, file, dir) = setup(false);
+        file.rename_with(dir.join("bar"), RenamePolicy::RenameBoth)
+            .unwrap();
+        assert!(!dir.join("bar.orig").exists());
+
+        let (_tmp, file, dir) = setup(true);
+        let bar = file
+            .rename_with(dir.join("bar"), RenamePolicy::RenameBoth)
+            .unwrap();
+        assert_eq!("new", bar.read_string().unwrap());
+        let orig = PathFile::new(dir.join("bar.orig")).unwrap();
+        assert_eq!("old", orig.read_string().unwrap());
+    }
+
+    #[test]
+    fn sanity_rotate() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let dir = PathDir::new(tmp_dir.path()).unwrap();
+        let log = PathFile::create(dir.join("app.log")).unwrap();
+        let mut perm = log.metadata().unwrap().permissions();
+        perm.set_readonly(true);
+        log.set_permissions(perm).unwrap();
+
+        for contents in &["one", "two", "three"] {
+            log.set_readonly(false).unwrap();
+            log.write_str(contents).unwrap();
+            log.set_readonly(true).unwrap();
+            log.rotate(2).unwrap();
+        }
+
+        assert_eq!("", log.read_string().unwrap());
+        assert!(log.metadata().unwrap().permissions().readonly());
+        let first = PathFile::new(dir.join("app.log.1")).unwrap();
+        let second = PathFile::new(dir.join("app.log.2")).unwrap();
+        assert_eq!("three", first.read_string().unwrap());
+        assert_eq!("two", second.read_string().unwrap());
+        assert!(!dir.join("app.log.3").exists());
+        assert_eq!(3, dir.list().unwrap().count());
+
+        // A missing index and a stale file beyond `keep`
+        first.remove().unwrap();
+        PathFile::create(dir.join("app.log.7")).unwrap();
+        log.set_readonly(false).unwrap();
+        log.write_str("four").unwrap();
+        log.rotate(3).unwrap();
+        let names = |dir: &PathDir| {
+            let mut names: Vec<_> = dir
+                .list()
+                .unwrap()
+                .map(|p| p.unwrap().file_name().unwrap().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(vec!["app.log", "app.log.1", "app.log.3"], names(&dir));
+        let third = PathFile::new(dir.join("app.log.3")).unwrap();
+        assert_eq!("four", PathFile::new(dir.join("app.log.1")).unwrap().read_string().unwrap());
+        assert_eq!("two", third.read_string().unwrap());
+
+        log.rotate(0).unwrap();
+        assert_eq!(vec!["app.log"], names(&dir));
+    }
+
+    #[test]
+    fn sanity_swap() {
+        let (_tmp, a, dir) = setup(false);
+        a.write_str("a").unwrap();
+        let b = PathFile::create(dir.join("b")).unwrap();
+        b.write_str("b contents").unwrap();
+        a.swap(&b).unwrap();
+        assert_eq!("b contents", a.read_string().unwrap());
+        assert_eq!("a", b.read_string().unwrap());
+        assert_eq!(2, dir.list().unwrap().count());
+
+        super::swap_renames(&a, &b).unwrap();
+        assert_eq!("a", a.read_string().unwrap());
+        assert_eq!("b contents", b.read_string().unwrap());
+        assert_eq!(2, dir.list().unwrap().count());
+
+        let missing = PathFile::mock(dir.join("missing"));
+        let err = a.swap(&missing).unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        let err = super::swap_renames(&a, &missing).unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert_eq!(missing.as_path(), err.path());
+        assert!(err.action().contains("step 2 of 3"));
+        assert_eq!("a", a.read_string().unwrap());
+        assert_eq!(2, dir.list().unwrap().count());
+    }
+}
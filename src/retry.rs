@@ -0,0 +1,235 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Retrying operations which fail transiently on Windows.
+
+use std::io;
+use std::thread;
+use std::time::Duration;
+use std_prelude::*;
+
+use super::Result;
+use super::{PathDir, PathFile};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+/// How often the `*_retry` methods (i.e. `PathDir::remove_all_retry`) attempt an operation
+/// which fails transiently.
+///
+/// On Windows, antivirus and indexing services briefly open files, which makes removing or
+/// renaming them fail with "access denied" or a sharing violation. Only these errors are
+/// retried, and only on Windows: on other platforms the operation is attempted once.
+///
+/// # Examples
+/// ```rust
+/// # extern crate path_abs;
+/// # extern crate tempdir;
+/// use std::time::Duration;
+/// use path_abs::{PathDir, PathFile, RetryPolicy};
+///
+/// # fn try_main() -> ::std::io::Result<()> {
+/// let example = "example";
+/// # let tmp = tempdir::TempDir::new("ex")?;
+/// # let example = &tmp.path().join(example);
+/// let cache = PathDir::create(example)?;
+/// PathFile::create(cache.join("file.txt"))?;
+///
+/// cache.remove_all_retry(&RetryPolicy::new(10, Duration::from_millis(50)))?;
+/// assert!(!example.exists());
+/// # Ok(()) } fn main() { try_main().unwrap() }
+/// ```
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the first one. 0 is the same as 1.
+    pub attempts: u32,
+    /// How long to sleep before attempting again.
+    pub delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Create a policy making up to `attempts` attempts, sleeping `delay` in between.
+    pub fn new(attempts: u32, delay: Duration) -> RetryPolicy {
+        RetryPolicy { attempts, delay }
+    }
+}
+
+impl Default for RetryPolicy {
+    /// 5 attempts, 50 milliseconds apart.
+    fn default() -> RetryPolicy {
+        RetryPolicy::new(5, Duration::from_millis(50))
+    }
+}
+
+impl PathDir {
+    /// Remove (delete) the directory and its contents like `remove_all`, retrying transient
+    /// failures according to `policy`.
+    ///
+    /// Each attempt removes what is left of the tree. The error of the last attempt is
+    /// returned, with the path of the entry which failed.
+    pub fn remove_all_retry(self, policy: &RetryPolicy) -> Result<()> {
+        retry(policy, is_transient, || self.clone().remove_all())
+    }
+
+    /// Rename (move) the directory to `to` like `rename`, retrying transient failures
+    /// according to `policy`.
+    pub fn rename_retry<P: AsRef<Path>>(self, to: P, policy: &RetryPolicy) -> Result<PathDir> {
+        retry(policy, is_transient, || self.clone().rename(&to))
+    }
+}
+
+impl PathFile {
+    /// Remove (delete) the file like `remove`, retrying transient failures according to
+    /// `policy`.
+    pub fn remove_retry(self, policy: &RetryPolicy) -> Result<()> {
+        retry(policy, is_transient, || self.clone().remove())
+    }
+
+    /// Rename (move) the file to `to` like `rename`, retrying transient failures according to
+    /// `policy`.
+    pub fn rename_retry<P: AsRef<Path>>(self, to: P, policy: &RetryPolicy) -> Result<PathFile> {
+        retry(policy, is_transient, || self.clone().rename(&to))
+    }
+}
+
+/// Call `op` until it succeeds, fails with an error which is not `transient` or the attempts of
+/// `policy` are used up.
+fn retry<T, F>(policy: &RetryPolicy, transient: fn(&io::Error) -> bool, mut op: F) -> Result<T>
+where
+    F: FnMut() -> Result<T>,
+{
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Err(ref err) if attempt < policy.attempts && transient(err.io_error()) => {
+                attempt += 1;
+                thread::sleep(policy.delay);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Return whether `err` can be caused by another process briefly having the file open.
+#[cfg(windows)]
+fn is_transient(err: &io::Error) -> bool {
+    const ERROR_ACCESS_DENIED: i32 = 5;
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+    const ERROR_LOCK_VIOLATION: i32 = 33;
+    const ERROR_DIR_NOT_EMPTY: i32 = 145;
+    match err.raw_os_error() {
+        Some(ERROR_ACCESS_DENIED)
+        | Some(ERROR_SHARING_VIOLATION)
+        | Some(ERROR_LOCK_VIOLATION)
+        | Some(ERROR_DIR_NOT_EMPTY) => true,
+        _ => err.kind() == io::ErrorKind::PermissionDenied,
+    }
+}
+
+/// Return whether `err` can be caused by another process briefly having the file open.
+#[cfg(not(windows))]
+fn is_transient(_: &io::Error) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Error, PathArc, PathDir, PathFile};
+    use super::{retry, RetryPolicy};
+    use std::cell::Cell;
+    use std::io;
+    use std::time::{Duration, Instant};
+    use tempdir::TempDir;
+
+    fn never(_: &io::Error) -> bool {
+        false
+    }
+
+    fn denied(err: &io::Error) -> bool {
+        err.kind() == io::ErrorKind::PermissionDenied
+    }
+
+    /// An operation which fails with `kind` the first `failures` times it is called.
+    fn failing(failures: u32, kind: io::ErrorKind, calls: &Cell<u32>) -> ::Result<u32> {
+        calls.set(calls.get() + 1);
+        if calls.get() <= failures {
+            let err = io::Error::new(kind, format!("attempt {}", calls.get()));
+            Err(Error::new(err, "removing", PathArc::new("/example")))
+        } else {
+            Ok(calls.get())
+        }
+    }
+
+    #[test]
+    fn sanity_retry() {
+        let policy = RetryPolicy::new(3, Duration::from_millis(10));
+        let kind = io::ErrorKind::PermissionDenied;
+
+        let calls = Cell::new(0);
+        let start = Instant::now();
+        assert_eq!(3, retry(&policy, denied, || failing(2, kind, &calls)).unwrap());
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        // The budget is used up, the last error is returned
+        let calls = Cell::new(0);
+        let err = retry(&policy, denied, || failing(5, kind, &calls)).unwrap_err();
+        assert_eq!(3, calls.get());
+        assert_eq!("attempt 3", err.io_error().to_string());
+        assert_eq!(PathArc::new("/example").as_path(), err.path());
+
+        // Other errors are not retried
+        let calls = Cell::new(0);
+        let err = retry(&policy, denied, || failing(5, io::ErrorKind::NotFound, &calls));
+        assert_eq!(io::ErrorKind::NotFound, err.unwrap_err().io_error().kind());
+        assert_eq!(1, calls.get());
+        let calls = Cell::new(0);
+        assert!(retry(&policy, never, || failing(5, kind, &calls)).is_err());
+        assert_eq!(1, calls.get());
+
+        // There is always one attempt
+        let calls = Cell::new(0);
+        let once = RetryPolicy::new(0, Duration::from_secs(60));
+        assert!(retry(&once, denied, || failing(1, kind, &calls)).is_err());
+        assert_eq!(1, calls.get());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn sanity_retry_not_windows() {
+        use super::is_transient;
+
+        let err = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+        assert!(!is_transient(&err));
+    }
+
+    #[test]
+    fn sanity_retry_methods() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let policy = RetryPolicy::default();
+
+        let dir = PathDir::create(tmp_abs.join("dir")).unwrap();
+        let file = PathFile::create(dir.join("file.txt")).unwrap();
+        let file = file.rename_retry(dir.join("moved.txt"), &policy).unwrap();
+        assert_eq!(dir.join("moved.txt").as_path(), file.as_path());
+        let dir = dir.rename_retry(tmp_abs.join("moved"), &policy).unwrap();
+        assert!(dir.join("moved.txt").exists());
+
+        PathFile::new(dir.join("moved.txt"))
+            .unwrap()
+            .remove_retry(&policy)
+            .unwrap();
+        PathFile::create(dir.join("other.txt")).unwrap();
+        dir.clone().remove_all_retry(&policy).unwrap();
+        assert!(!dir.exists());
+
+        // Genuine errors fail immediately
+        let start = Instant::now();
+        let missing = PathDir::mock(tmp_abs.join("missing"));
+        let slow = RetryPolicy::new(10, Duration::from_secs(1));
+        let err = missing.remove_all_retry(&slow).unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.io_error().kind());
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,222 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Summaries of directory trees.
+
+use std::fs;
+use std::io;
+
+use super::{Error, Result};
+use super::{PathDir, PathType, Walk};
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+/// The number of entries of each type in a directory, returned by `PathDir::count`.
+pub struct DirCount {
+    /// Regular files.
+    pub files: u64,
+    /// Directories, not including the one counted.
+    pub dirs: u64,
+    /// Symlinks, including broken ones.
+    pub symlinks: u64,
+}
+
+impl PathDir {
+    /// Return the total size in bytes of all regular files under the directory.
+    ///
+    /// The directory is walked like `PathDir::walk`, so symlinks are not followed. A symlink
+    /// counts as zero bytes, as do the directories themselves. Entries removed while walking
+    /// are skipped.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// PathFile::create(dir.join("foo.txt"))?.write_str("foo")?;
+    /// PathFile::create_all(dir.join("sub/bar.txt"))?.write_str("bar!")?;
+    ///
+    /// assert_eq!(7, dir.size()?);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn size(&self) -> Result<u64> {
+        let mut size = 0;
+        for entry in lenient(self.walk()) {
+            let entry = entry?;
+            match entry.symlink_metadata() {
+                Ok(meta) => {
+                    if meta.file_type().is_file() {
+                        size += meta.len();
+                    }
+                }
+                Err(ref err) if vanished(err) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(size)
+    }
+
+    /// Count the files, directories and symlinks under the directory.
+    ///
+    /// The directory is walked like `PathDir::walk`, so symlinks are counted but not followed.
+    /// Entries removed while walking are skipped.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{DirCount, PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// PathFile::create(dir.join("foo.txt"))?;
+    /// PathFile::create_all(dir.join("sub/bar.txt"))?;
+    ///
+    /// let count = dir.count()?;
+    /// assert_eq!(DirCount { files: 2, dirs: 1, symlinks: 0 }, count);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn count(&self) -> Result<DirCount> {
+        tally(self.walk())
+    }
+
+    /// Count the files, directories and symlinks directly in the directory, like `count`
+    /// without recursing.
+    pub fn count_shallow(&self) -> Result<DirCount> {
+        tally(self.walk_opts().max_depth(1).walk())
+    }
+}
+
+fn tally(walk: Walk) -> Result<DirCount> {
+    let mut count = DirCount::default();
+    for entry in walk {
+        let meta = match entry.and_then(|entry| entry.symlink_metadata()) {
+            Ok(meta) => meta,
+            Err(ref err) if vanished(err) => {
+                // A broken symlink can't be resolved but still exists.
+                match fs::symlink_metadata(err.path()) {
+                    Ok(meta) => meta,
+                    Err(_) => continue,
+                }
+            }
+            Err(err) => return Err(err),
+        };
+        let ty = meta.file_type();
+        if ty.is_symlink() {
+            count.symlinks += 1;
+        } else if ty.is_dir() {
+            count.dirs += 1;
+        } else if ty.is_file() {
+            count.files += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Whether the error is from an entry that was removed while walking (or a broken symlink).
+fn vanished(err: &Error) -> bool {
+    err.io_error().kind() == io::ErrorKind::NotFound
+}
+
+/// Skip the entries of the walk that vanished.
+fn lenient(walk: Walk) -> impl Iterator<Item = Result<PathType>> {
+    walk.filter(|entry| match *entry {
+        Err(ref err) => !vanished(err),
+        Ok(_) => true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::file::symlink_file;
+    use super::super::{DirCount, PathDir, PathFile};
+    use tempdir::TempDir;
+
+    #[test]
+    fn sanity_size() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let root = PathDir::create(tmp_dir.path().join("root")).unwrap();
+        assert_eq!(0, root.size().unwrap());
+
+        PathFile::create(root.join("a.txt"))
+            .unwrap()
+            .write_str("hello")
+            .unwrap();
+        PathFile::create_all(root.join("b/c.bin"))
+            .unwrap()
+            .write_bytes(&[0; 1000])
+            .unwrap();
+        PathFile::create_all(root.join("b/d/e.txt"))
+            .unwrap()
+            .write_str("0123456789")
+            .unwrap();
+        PathFile::create_all(root.join("b/d/empty")).unwrap();
+        PathDir::create(root.join("f")).unwrap();
+        assert_eq!(1015, root.size().unwrap());
+        assert_eq!(1010, PathDir::new(root.join("b")).unwrap().size().unwrap());
+
+        // Symlinks are not followed, even broken ones.
+        let outside = PathFile::create(tmp_dir.path().join("outside.bin")).unwrap();
+        outside.write_bytes(&[0; 4096]).unwrap();
+        outside.symlink(root.join("link.bin")).unwrap();
+        PathDir::new(root.join("b"))
+            .unwrap()
+            .symlink(root.join("b-link"))
+            .unwrap();
+        symlink_file(root.join("missing"), root.join("broken")).unwrap();
+        assert_eq!(1015, root.size().unwrap());
+    }
+
+    #[test]
+    fn sanity_count() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let root = PathDir::create(tmp_dir.path().join("root")).unwrap();
+        assert_eq!(DirCount::default(), root.count().unwrap());
+
+        for file in &["a.txt", "b/c.txt", "b/d/e.txt", "b/d/f.txt"] {
+            PathFile::create_all(root.join(file)).unwrap();
+        }
+        PathDir::create(root.join("g")).unwrap();
+        let b = PathDir::new(root.join("b")).unwrap();
+        b.symlink(root.join("b-link")).unwrap();
+        PathFile::new(root.join("a.txt"))
+            .unwrap()
+            .symlink(root.join("b/d/a-link"))
+            .unwrap();
+        symlink_file(root.join("missing"), root.join("broken")).unwrap();
+
+        let expected = DirCount {
+            files: 4,
+            dirs: 3,
+            symlinks: 3,
+        };
+        assert_eq!(expected, root.count().unwrap());
+
+        let expected = DirCount {
+            files: 1,
+            dirs: 2,
+            symlinks: 2,
+        };
+        assert_eq!(expected, root.count_shallow().unwrap());
+        assert_eq!(
+            DirCount {
+                files: 1,
+                dirs: 1,
+                symlinks: 0,
+            },
+            b.count_shallow().unwrap()
+        );
+    }
+}
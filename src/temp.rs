@@ -0,0 +1,463 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Temporary files and directories which are removed when dropped.
+
+use std::env;
+use std::error;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io;
+use std_prelude::*;
+
+use super::{Error, Result};
+use super::{PathAbs, PathArc, PathDir, PathFile};
+use super::file::{create_temp, create_temp_dir, is_cross_device};
+
+/// A temporary `PathFile` which is removed when it is dropped, created with
+/// `PathFile::temp_in`.
+///
+/// Use `keep` to prevent the file from being removed, or `persist` to move it to its final
+/// location.
+pub struct TmpFile {
+    path: Option<PathFile>,
+}
+
+/// A temporary `PathDir` which is recursively removed when it is dropped, created with
+/// `PathDir::temp` or `PathDir::temp_in`.
+///
+/// Use `keep` to prevent the directory from being removed.
+pub struct TmpDir {
+    path: Option<PathDir>,
+}
+
+/// The error returned by `TmpFile::persist`, which gives back the temporary file so that the
+/// caller can retry.
+pub struct PersistError {
+    /// The error which caused persisting to fail.
+    pub error: Error,
+    /// The temporary file, which has not been removed.
+    pub file: TmpFile,
+}
+
+impl PathFile {
+    /// Create a new, empty, uniquely named file in `dir` which is removed when the returned
+    /// `TmpFile` is dropped.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// let dir = PathDir::new(tmp.path())?;
+    ///
+    /// let path = {
+    ///     let temp = PathFile::temp_in(&dir)?;
+    ///     temp.write_str("scratch")?;
+    ///     assert_eq!("scratch", temp.read_string()?);
+    ///     temp.to_path_buf()
+    /// };
+    /// assert!(!path.exists());
+    ///
+    /// // Unless it is kept
+    /// let kept = PathFile::temp_in(&dir)?.keep();
+    /// assert!(kept.exists());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn temp_in(dir: &PathDir) -> Result<TmpFile> {
+        let (temp, _) = create_temp(dir, OsStr::new("tmp"))?;
+        // `dir` is canonical and the file name was just created, so the path is too.
+        Ok(TmpFile {
+            path: Some(PathFile(PathAbs(temp))),
+        })
+    }
+}
+
+impl PathDir {
+    /// Create a new, empty, uniquely named directory in the system's temporary directory which
+    /// is recursively removed when the returned `TmpDir` is dropped.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let path = {
+    ///     let temp = PathDir::temp()?;
+    ///     PathFile::create(temp.join("scratch"))?;
+    ///     temp.to_path_buf()
+    /// };
+    /// assert!(!path.exists());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn temp() -> Result<TmpDir> {
+        PathDir::new(env::temp_dir())?.temp_in()
+    }
+
+    /// Create a new, empty, uniquely named directory inside of `self` which is recursively
+    /// removed when the returned `TmpDir` is dropped.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// let dir = PathDir::new(tmp.path())?;
+    ///
+    /// let temp = dir.temp_in()?;
+    /// PathFile::create(temp.join("scratch"))?;
+    /// assert_eq!(1, dir.list()?.count());
+    /// drop(temp);
+    /// assert_eq!(0, dir.list()?.count());
+    ///
+    /// // Unless it is kept
+    /// let kept = dir.temp_in()?.keep();
+    /// assert!(kept.exists());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn temp_in(&self) -> Result<TmpDir> {
+        let temp = create_temp_dir(self, OsStr::new("tmp"))?;
+        // `self` is canonical and the directory name was just created, so the path is too.
+        Ok(TmpDir {
+            path: Some(PathDir(PathAbs(temp))),
+        })
+    }
+}
+
+impl TmpDir {
+    /// Keep the directory and its contents, returning its `PathDir`.
+    pub fn keep(mut self) -> PathDir {
+        self.path.take().expect("TmpDir path is only taken by keep")
+    }
+
+    fn path(&self) -> &PathDir {
+        self.path.as_ref().expect("TmpDir path is only taken by keep")
+    }
+}
+
+impl Drop for TmpDir {
+    fn drop(&mut self) {
+        if let Some(ref path) = self.path {
+            // As with `TmpFile`, failing to remove the directory must not panic.
+            let _ = fs::remove_dir_all(path);
+        }
+    }
+}
+
+impl fmt::Debug for TmpDir {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TmpDir(")?;
+        if let Some(ref path) = self.path {
+            path.fmt(f)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl AsRef<PathDir> for TmpDir {
+    fn as_ref(&self) -> &PathDir {
+        self.path()
+    }
+}
+
+impl AsRef<Path> for TmpDir {
+    fn as_ref(&self) -> &Path {
+        self.path().as_ref()
+    }
+}
+
+impl Deref for TmpDir {
+    type Target = PathDir;
+
+    fn deref(&self) -> &PathDir {
+        self.path()
+    }
+}
+
+impl TmpFile {
+    /// Keep the file, returning its `PathFile`.
+    pub fn keep(mut self) -> PathFile {
+        self.path.take().expect("TmpFile path is only taken by keep")
+    }
+
+    /// Atomically move the file to `dest`, replacing any existing file, and return the new
+    /// `PathFile`.
+    ///
+    /// If `dest` is on a different filesystem the contents are copied and synced to a temporary
+    /// file next to `dest` which is then renamed into place, so `dest` is never partially
+    /// written. The original temporary file is removed on success. On failure it is returned in
+    /// the `PersistError` so that persisting can be retried.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::new(example.parent().unwrap())?;
+    ///
+    /// let temp = PathFile::temp_in(&dir)?;
+    /// temp.write_str("complete")?;
+    /// let file = temp.persist(example)?;
+    /// assert_eq!("complete", file.read_string()?);
+    /// assert_eq!(1, dir.list()?.count());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn persist<P: AsRef<Path>>(
+        self,
+        dest: P,
+    ) -> ::std::result::Result<PathFile, PersistError> {
+        let dest = dest.as_ref();
+        let result = match fs::rename(self.path(), dest) {
+            Ok(()) => Ok(()),
+            Err(ref err) if is_cross_device(err) => persist_copy(self.path(), dest),
+            Err(err) => Err(Error::new(
+                err,
+                &format!("renaming to {} from", dest.display()),
+                self.path().clone().into(),
+            )),
+        };
+        // On success `self` is dropped, removing the original if it was copied.
+        result
+            .and_then(|_| PathFile::new(dest))
+            .map_err(|error| PersistError { error, file: self })
+    }
+
+    fn path(&self) -> &PathFile {
+        self.path.as_ref().expect("TmpFile path is only taken by keep")
+    }
+}
+
+/// Copy `src` to a synced temporary file next to `dest`, then rename it over `dest`.
+fn persist_copy(src: &PathFile, dest: &Path) -> Result<()> {
+    let (dir, name) = match (dest.parent(), dest.file_name()) {
+        (Some(dir), Some(name)) => (dir, name),
+        _ => {
+            return Err(Error::new(
+                io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"),
+                "persisting",
+                PathArc::new(dest),
+            ))
+        }
+    };
+    let (temp, mut file) = create_temp(dir, name)?;
+    let result = fs::File::open(src)
+        .and_then(|mut from| io::copy(&mut from, &mut file))
+        .map_err(|err| {
+            Error::new(
+                err,
+                &format!("copying {} from", temp.display()),
+                src.clone().into(),
+            )
+        })
+        .and_then(|_| file.sync_all().map_err(|err| Error::new(err, "syncing", temp.clone())))
+        .and_then(|_| {
+            drop(file);
+            fs::rename(&temp, dest).map_err(|err| {
+                Error::new(
+                    err,
+                    &format!("renaming to {} from", dest.display()),
+                    temp.clone(),
+                )
+            })
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+impl Drop for TmpFile {
+    fn drop(&mut self) {
+        if let Some(ref path) = self.path {
+            // The file may have already been removed, or be impossible to remove: neither
+            // should panic in a destructor.
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+impl fmt::Debug for TmpFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TmpFile(")?;
+        if let Some(ref path) = self.path {
+            path.fmt(f)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl AsRef<PathFile> for TmpFile {
+    fn as_ref(&self) -> &PathFile {
+        self.path()
+    }
+}
+
+impl AsRef<Path> for TmpFile {
+    fn as_ref(&self) -> &Path {
+        self.path().as_ref()
+    }
+}
+
+impl Deref for TmpFile {
+    type Target = PathFile;
+
+    fn deref(&self) -> &PathFile {
+        self.path()
+    }
+}
+
+impl fmt::Debug for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PersistError<{}>", self)
+    }
+}
+
+impl fmt::Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl error::Error for PersistError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<PersistError> for io::Error {
+    fn from(err: PersistError) -> io::Error {
+        err.error.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempdir::TempDir;
+    use super::super::{PathDir, PathFile};
+
+    #[test]
+    fn sanity_temp_in() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+
+        let temp = PathFile::temp_in(&tmp_abs).unwrap();
+        let path = temp.to_path_buf();
+        assert!(path.is_file());
+        assert_eq!(tmp_abs.as_path(), path.parent().unwrap());
+        drop(temp);
+        assert!(!path.exists());
+
+        // Already removed files are fine
+        let temp = PathFile::temp_in(&tmp_abs).unwrap();
+        fs::remove_file(&*temp).unwrap();
+        drop(temp);
+
+        let kept = PathFile::temp_in(&tmp_abs).unwrap().keep();
+        assert!(kept.exists());
+        assert_ne!(kept, PathFile::temp_in(&tmp_abs).unwrap().keep());
+    }
+
+    #[test]
+    fn sanity_temp_dir() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+
+        let temp = tmp_abs.temp_in().unwrap();
+        let path = temp.to_path_buf();
+        assert!(path.is_dir());
+        assert_eq!(tmp_abs.as_path(), path.parent().unwrap());
+
+        // Nested contents are removed too
+        let nested = PathDir::create(temp.join("nested")).unwrap();
+        PathFile::create(nested.join("file")).unwrap();
+        drop(temp);
+        assert!(!path.exists());
+        assert_eq!(0, tmp_abs.list().unwrap().count());
+
+        // Already removed directories are fine
+        let temp = tmp_abs.temp_in().unwrap();
+        fs::remove_dir(&*temp).unwrap();
+        drop(temp);
+
+        let kept = tmp_abs.temp_in().unwrap().keep();
+        assert!(kept.exists());
+        assert_ne!(kept, tmp_abs.temp_in().unwrap().keep());
+
+        let temp = PathDir::temp().unwrap();
+        let path = temp.to_path_buf();
+        assert!(path.is_dir());
+        drop(temp);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn sanity_persist() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let other = PathDir::create(tmp_abs.join("other")).unwrap();
+
+        // Same directory, replacing an existing file
+        let dest = PathFile::create_with(tmp_abs.join("dest"), b"old").unwrap();
+        let temp = PathFile::temp_in(&tmp_abs).unwrap();
+        temp.write_str("same dir").unwrap();
+        let persisted = temp.persist(&dest).unwrap();
+        assert_eq!(dest, persisted);
+        assert_eq!("same dir", dest.read_string().unwrap());
+        assert_eq!(2, tmp_abs.list().unwrap().count());
+
+        // Another directory
+        let temp = PathFile::temp_in(&tmp_abs).unwrap();
+        temp.write_str("other dir").unwrap();
+        let persisted = temp.persist(other.join("dest")).unwrap();
+        assert_eq!("other dir", persisted.read_string().unwrap());
+        assert_eq!(2, tmp_abs.list().unwrap().count());
+
+        // Failure keeps the temporary file so it can be retried
+        let temp = PathFile::temp_in(&tmp_abs).unwrap();
+        temp.write_str("retry").unwrap();
+        let err = temp.persist(tmp_abs.join("missing").join("dest")).unwrap_err();
+        assert_eq!(::std::io::ErrorKind::NotFound, err.error.io_error().kind());
+        assert!(err.file.exists());
+        let persisted = err.file.persist(other.join("retried")).unwrap();
+        assert_eq!("retry", persisted.read_string().unwrap());
+        assert_eq!(2, tmp_abs.list().unwrap().count());
+    }
+
+    #[test]
+    fn sanity_persist_copy() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let other = PathDir::create(tmp_abs.join("other")).unwrap();
+
+        let src = PathFile::create_with(tmp_abs.join("src"), b"copied").unwrap();
+        let dest = PathFile::create_with(other.join("dest"), b"old").unwrap();
+        super::persist_copy(&src, &dest).unwrap();
+        assert_eq!("copied", dest.read_string().unwrap());
+        assert!(src.exists());
+        assert_eq!(1, other.list().unwrap().count());
+
+        // The temporary copy is cleaned up on failure
+        let missing = PathFile::mock(tmp_abs.join("missing"));
+        assert!(super::persist_copy(&missing, &dest).is_err());
+        assert_eq!("copied", dest.read_string().unwrap());
+        assert_eq!(1, other.list().unwrap().count());
+    }
+}
@@ -0,0 +1,203 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Directory trees as nested structures.
+
+use super::Result;
+use super::{PathDir, PathFile, PathType, WalkBuilder};
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// A directory along with everything inside of it, returned by `PathDir::tree`.
+///
+/// The subdirectories and files are each sorted by their file names, which are compared as
+/// `OsStr` like `WalkBuilder::sort_by_file_name`.
+pub struct DirTree {
+    /// The directory itself.
+    pub dir: PathDir,
+    /// The trees of the subdirectories.
+    pub dirs: Vec<DirTree>,
+    /// The files in the directory.
+    pub files: Vec<PathFile>,
+}
+
+impl DirTree {
+    fn new(dir: PathDir) -> DirTree {
+        DirTree {
+            dir,
+            dirs: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Return every entry in the tree, depth-first with each directory before its contents.
+    ///
+    /// The directory of the tree itself is not included. This is the same order as a walk
+    /// which is sorted by file name, so the entries are the same as those of
+    /// `dir.walk_opts().sort_by_file_name(true).walk()`.
+    pub fn entries(&self) -> Vec<PathType> {
+        let mut entries = Vec::new();
+        self.push_entries(&mut entries);
+        entries
+    }
+
+    fn push_entries(&self, entries: &mut Vec<PathType>) {
+        let mut dirs = self.dirs.iter().peekable();
+        let mut files = self.files.iter().peekable();
+        loop {
+            let dir_first = match (dirs.peek(), files.peek()) {
+                (Some(dir), Some(file)) => dir.dir.file_name() < file.file_name(),
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => return,
+            };
+            if dir_first {
+                let tree = dirs.next().expect("peeked");
+                entries.push(PathType::Dir(tree.dir.clone()));
+                tree.push_entries(entries);
+            } else {
+                entries.push(PathType::File(files.next().expect("peeked").clone()));
+            }
+        }
+    }
+}
+
+impl PathDir {
+    /// Recursively walk the directory, returning its contents as a nested `DirTree`.
+    ///
+    /// The walk is the same as `walk`, so symlinks to directories are included as empty trees.
+    /// Use `WalkBuilder::tree` to configure it (i.e. with `max_depth`). The first error is
+    /// returned.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let project = PathDir::create(example)?;
+    /// let cargo = PathFile::create(project.join("Cargo.toml"))?;
+    /// let lib = PathFile::create_all(project.join("src/lib.rs"))?;
+    ///
+    /// let tree = project.tree()?;
+    /// assert_eq!(vec![cargo], tree.files);
+    /// assert_eq!(lib.parent_dir().unwrap(), tree.dirs[0].dir);
+    /// assert_eq!(vec![lib], tree.dirs[0].files);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn tree(&self) -> Result<DirTree> {
+        self.walk_opts().tree()
+    }
+}
+
+impl WalkBuilder {
+    /// Walk the directory, returning its contents as a nested `DirTree` like `PathDir::tree`.
+    ///
+    /// The walk is sorted by file name and `min_depth` is ignored. Directories deeper than
+    /// `max_depth` are not included, and directories at `max_depth` are empty trees.
+    pub fn tree(self) -> Result<DirTree> {
+        let mut walk = self.min_depth(0).sort_by_file_name(true).walk_rel();
+        let root = walk.next().expect("the root is yielded first")?;
+        // The trees of the directories being walked, from the root to the current one.
+        let mut stack = vec![DirTree::new(root.abs.unwrap_dir())];
+        for entry in walk {
+            let entry = entry?;
+            let depth = entry.rel.components().count();
+            while stack.len() > depth {
+                let tree = stack.pop().expect("the root is never popped");
+                stack.last_mut().expect("not empty").dirs.push(tree);
+            }
+            match entry.abs {
+                PathType::Dir(dir) => stack.push(DirTree::new(dir)),
+                PathType::File(file) => stack.last_mut().expect("not empty").files.push(file),
+            }
+        }
+        while stack.len() > 1 {
+            let tree = stack.pop().expect("the root is never popped");
+            stack.last_mut().expect("not empty").dirs.push(tree);
+        }
+        Ok(stack.pop().expect("the root is never popped"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{PathDir, PathFile, PathType};
+    use super::DirTree;
+    use tempdir::TempDir;
+
+    #[test]
+    fn sanity_tree() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let root = PathDir::new(tmp_dir.path()).unwrap();
+        for file in &["b.txt", "a/c.txt", "a/b/d.txt", "a/b/e.txt", "c"] {
+            PathFile::create_all(root.join(file)).unwrap();
+        }
+        let empty = PathDir::create(root.join("empty")).unwrap();
+        let file = |path: &str| PathFile::new(root.join(path)).unwrap();
+        let dir = |path: &str| PathDir::new(root.join(path)).unwrap();
+
+        let tree = root.tree().unwrap();
+        let expected = DirTree {
+            dir: root.clone(),
+            dirs: vec![
+                DirTree {
+                    dir: dir("a"),
+                    dirs: vec![DirTree {
+                        dir: dir("a/b"),
+                        dirs: vec![],
+                        files: vec![file("a/b/d.txt"), file("a/b/e.txt")],
+                    }],
+                    files: vec![file("a/c.txt")],
+                },
+                DirTree {
+                    dir: empty.clone(),
+                    dirs: vec![],
+                    files: vec![],
+                },
+            ],
+            files: vec![file("b.txt"), file("c")],
+        };
+        assert_eq!(expected, tree);
+
+        // The entries are the same as those of a sorted walk
+        let walked: Vec<_> = root
+            .walk_opts()
+            .sort_by_file_name(true)
+            .walk()
+            .map(|e| e.unwrap())
+            .collect();
+        assert_eq!(walked, tree.entries());
+        assert_eq!(PathType::Dir(dir("a")), tree.entries()[0]);
+        assert!(empty.tree().unwrap().entries().is_empty());
+    }
+
+    #[test]
+    fn sanity_tree_max_depth() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let root = PathDir::new(tmp_dir.path()).unwrap();
+        let deep = PathFile::create_all(root.join("a/b/c/d.txt")).unwrap();
+        let top = PathFile::create(root.join("top.txt")).unwrap();
+
+        let tree = root.walk_opts().max_depth(2).min_depth(2).tree().unwrap();
+        assert_eq!(vec![top], tree.files);
+        assert_eq!(1, tree.dirs.len());
+        let b = &tree.dirs[0].dirs[0];
+        assert!(b.dirs.is_empty());
+        assert!(b.files.is_empty());
+        assert_eq!(3, tree.entries().len());
+
+        let tree = root.walk_opts().max_depth(0).tree().unwrap();
+        assert_eq!(DirTree::new(root.clone()), tree);
+
+        let tree = root.tree().unwrap();
+        assert_eq!(vec![deep], tree.dirs[0].dirs[0].dirs[0].files);
+    }
+}
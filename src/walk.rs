@@ -0,0 +1,829 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Recursive directory walks.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs;
+use std::io;
+use std_prelude::*;
+
+use super::dir::{collect_vec, is_hidden, resolve_entry};
+use super::{Entry, EntryMeta, Error, ListError, Result};
+use super::{PathArc, PathDir, PathType};
+
+impl PathDir {
+    /// Recursively walk the directory, returning an iterator over every entry in the tree.
+    ///
+    /// The walk is depth-first and a directory is always yielded before its contents. The
+    /// directory itself is not yielded. Symlinks to directories are yielded as `PathType::Dir`
+    /// but are not descended into, so symlink loops can't make the walk run forever.
+    ///
+    /// Errors do not stop the walk: if a directory can't be read, the directory is yielded and
+    /// then the error, after which the walk continues with the next entry.
+    ///
+    /// Use [`walk_opts`](struct.PathDir.html#method.walk_opts) to configure the walk.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile, PathType};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// let nested = PathDir::create(dir.join("nested"))?;
+    /// let file = PathFile::create(nested.join("file.txt"))?;
+    ///
+    /// let entries = dir.walk().collect::<Result<Vec<_>, _>>()?;
+    /// assert_eq!(vec![PathType::Dir(nested), PathType::File(file)], entries);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn walk(&self) -> Walk {
+        self.walk_opts().walk()
+    }
+
+    /// Recursively walk the directory like `walk`, collecting the entries into a `Vec`.
+    ///
+    /// The whole tree is walked even if some entries fail, in which case a `ListError` is
+    /// returned with all of the errors and the entries which were read, like
+    /// `PathDir::list_vec`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile, PathType};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// let file = PathFile::create_all(dir.join("nested/file.txt"))?;
+    ///
+    /// let entries = dir.walk_vec()?;
+    /// assert_eq!(vec![PathType::Dir(file.parent_dir().unwrap()), PathType::File(file)], entries);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn walk_vec(&self) -> ::std::result::Result<Vec<PathType>, ListError> {
+        collect_vec(self.walk())
+    }
+
+    /// Recursively walk the directory like `walk`, yielding the entries along with their paths
+    /// relative to the directory.
+    ///
+    /// The relative paths are made of the names the entries were found by, so the contents of
+    /// a followed symlink (see `WalkBuilder::follow_links`) are under the path of the link.
+    /// Use `WalkBuilder::walk_rel` to configure the walk.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::path::PathBuf;
+    /// use path_abs::{PathDir, PathFile, PathType};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let project = PathDir::create(example)?;
+    /// let lib = PathFile::create_all(project.join("src/lib.rs"))?;
+    ///
+    /// let entries = project.walk_rel().collect::<Result<Vec<_>, _>>()?;
+    /// assert_eq!(PathBuf::from("src"), entries[0].rel);
+    /// assert_eq!(PathType::File(lib), entries[1].abs);
+    /// assert_eq!(PathBuf::from("src/lib.rs"), entries[1].rel);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn walk_rel(&self) -> WalkRel {
+        self.walk_opts().walk_rel()
+    }
+
+    /// Recursively walk the directory like `walk`, yielding the entries along with their
+    /// metadata.
+    ///
+    /// Like `PathDir::list_meta`, the metadata is read once per entry. Use
+    /// `WalkBuilder::walk_meta` to configure the walk.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// PathFile::create_all(dir.join("a/b.txt"))?.write_str("b")?;
+    /// PathFile::create_all(dir.join("c/d.txt"))?.write_str("dd")?;
+    ///
+    /// let size: u64 = dir
+    ///     .walk_meta()
+    ///     .filter_map(|entry| entry.ok())
+    ///     .filter(|entry| entry.abs.is_file())
+    ///     .map(|entry| entry.metadata.len())
+    ///     .sum();
+    /// assert_eq!(3, size);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn walk_meta(&self) -> WalkMeta {
+        self.walk_opts().walk_meta()
+    }
+
+    /// Return a `WalkBuilder` to configure a recursive walk of the directory.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// PathFile::create_all(dir.join("a/b/c.txt"))?;
+    ///
+    /// // Only "a/b"
+    /// let walk = dir.walk_opts().min_depth(2).max_depth(2).walk();
+    /// assert_eq!(1, walk.count());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn walk_opts(&self) -> WalkBuilder {
+        WalkBuilder {
+            root: self.clone(),
+            min_depth: 1,
+            max_depth: usize::MAX,
+            follow_links: false,
+            sort: false,
+            skip_hidden: false,
+            filter: None,
+        }
+    }
+}
+
+/// Configures a recursive walk of a directory, returned by `PathDir::walk_opts`.
+///
+/// The depth of an entry is the number of directories between it and the root of the walk:
+/// the root has depth 0 and its entries have depth 1.
+pub struct WalkBuilder {
+    root: PathDir,
+    min_depth: usize,
+    max_depth: usize,
+    follow_links: bool,
+    sort: bool,
+    skip_hidden: bool,
+    filter: Option<Box<EntryFilter>>,
+}
+
+/// The filter of `WalkBuilder::filter_entry`.
+type EntryFilter = dyn FnMut(&PathType) -> bool;
+
+impl WalkBuilder {
+    /// Only yield entries with a depth of at least `depth`. Shallower directories are still
+    /// walked.
+    ///
+    /// The default is 1, so the root is not yielded. With 0 the root is yielded first.
+    pub fn min_depth(mut self, depth: usize) -> WalkBuilder {
+        self.min_depth = depth;
+        self
+    }
+
+    /// Don't yield or descend into entries deeper than `depth`. With 1 only the entries of the
+    /// root are yielded, like `PathDir::list`.
+    ///
+    /// The default is unlimited.
+    pub fn max_depth(mut self, depth: usize) -> WalkBuilder {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Sets whether symlinks to directories are descended into.
+    ///
+    /// The canonical paths of the directories being walked are tracked, so a symlink which
+    /// points to one of its own ancestors is yielded but not descended into. The default is
+    /// false.
+    pub fn follow_links(mut self, follow_links: bool) -> WalkBuilder {
+        self.follow_links = follow_links;
+        self
+    }
+
+    /// Sets whether the entries of each directory are yielded sorted by their file names, which
+    /// makes the order of the walk deterministic. A directory is still yielded before its
+    /// contents.
+    ///
+    /// The names are compared as `OsStr`, which is byte-wise (so `B` sorts before `a`). Every
+    /// directory is read entirely before its entries are yielded. The default is false.
+    pub fn sort_by_file_name(mut self, sort: bool) -> WalkBuilder {
+        self.sort = sort;
+        self
+    }
+
+    /// Sets whether hidden entries are skipped, like `PathDir::list_visible`. Hidden
+    /// directories are not read, so nothing inside of them is yielded either.
+    ///
+    /// An entry is hidden if its file name starts with a `.` or, on Windows, if it has the
+    /// hidden attribute. The root is never skipped. The default is false.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let project = PathDir::create(example)?;
+    /// PathFile::create_all(project.join("src/lib.rs"))?;
+    /// PathFile::create_all(project.join(".git/HEAD"))?;
+    ///
+    /// let walk = project.walk_opts().skip_hidden(true).walk();
+    /// assert_eq!(2, walk.count());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn skip_hidden(mut self, skip_hidden: bool) -> WalkBuilder {
+        self.skip_hidden = skip_hidden;
+        self
+    }
+
+    /// Only yield entries for which `filter` returns true. Directories for which it returns
+    /// false are not read at all, so this is an efficient way to skip whole subtrees (i.e.
+    /// `target` or `.git`).
+    ///
+    /// The filter is called for every entry the walk finds, including those not yielded
+    /// because of `min_depth`, but not for the root.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::{PathDir, PathFile};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let project = PathDir::create(example)?;
+    /// PathFile::create_all(project.join("src/lib.rs"))?;
+    /// PathFile::create_all(project.join("target/debug/build.log"))?;
+    ///
+    /// let walk = project
+    ///     .walk_opts()
+    ///     .filter_entry(|entry| !entry.ends_with("target"))
+    ///     .walk();
+    /// assert_eq!(2, walk.count());
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn filter_entry<F: FnMut(&PathType) -> bool + 'static>(mut self, filter: F) -> WalkBuilder {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Start the walk, yielding the entries along with their paths relative to the root like
+    /// `PathDir::walk_rel`.
+    pub fn walk_rel(self) -> WalkRel {
+        WalkRel(self.walk())
+    }
+
+    /// Start the walk, yielding the entries along with their metadata like
+    /// `PathDir::walk_meta`.
+    pub fn walk_meta(self) -> WalkMeta {
+        WalkMeta(self.walk())
+    }
+
+    /// Start the walk.
+    pub fn walk(self) -> Walk {
+        let root = self.root.clone();
+        let mut walk = Walk {
+            options: self,
+            stack: Vec::new(),
+            pending: None,
+            root: None,
+        };
+        if walk.options.min_depth == 0 {
+            walk.root = Some(root.clone());
+        }
+        if walk.options.max_depth > 0 {
+            walk.push(root.into(), PathBuf::new());
+        }
+        walk
+    }
+}
+
+impl fmt::Debug for WalkBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WalkBuilder")
+            .field("root", &self.root)
+            .field("min_depth", &self.min_depth)
+            .field("max_depth", &self.max_depth)
+            .field("follow_links", &self.follow_links)
+            .field("sort", &self.sort)
+            .field("skip_hidden", &self.skip_hidden)
+            .field("filter", &self.filter.is_some())
+            .finish()
+    }
+}
+
+/// An iterator over the entries of a directory tree, returned by `PathDir::walk`.
+pub struct Walk {
+    options: WalkBuilder,
+    /// The directories being read, from the root to the current one.
+    stack: Vec<WalkDir>,
+    /// An error to yield before continuing.
+    pending: Option<Error>,
+    /// The root, if it still has to be yielded.
+    root: Option<PathDir>,
+}
+
+/// A directory being read by a `Walk`.
+struct WalkDir {
+    path: PathArc,
+    /// The path relative to the root, from the names the directory was found by.
+    rel: PathBuf,
+    read: Box<dyn Iterator<Item = io::Result<fs::DirEntry>>>,
+    /// The canonical path, only if following links.
+    canonical: Option<PathBuf>,
+}
+
+impl Walk {
+    /// Start reading the directory at `path`, unless following it would be a loop.
+    fn push(&mut self, path: PathArc, rel: PathBuf) {
+        let canonical = if self.options.follow_links {
+            match fs::canonicalize(&path) {
+                Ok(canonical) => {
+                    if self
+                        .stack
+                        .iter()
+                        .any(|d| d.canonical.as_ref() == Some(&canonical))
+                    {
+                        return;
+                    }
+                    Some(canonical)
+                }
+                Err(err) => {
+                    self.pending = Some(Error::new(err, "canonicalizing", path));
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+        let read = match fs::read_dir(&path) {
+            Ok(read) => read,
+            Err(err) => {
+                self.pending = Some(Error::new(err, "reading dir", path));
+                return;
+            }
+        };
+        let read: Box<dyn Iterator<Item = _>> = if self.options.sort {
+            let mut entries: Vec<_> = read.collect();
+            // Errors have no name, keep them last.
+            entries.sort_by(|a, b| match (a, b) {
+                (Ok(a), Ok(b)) => a.file_name().cmp(&b.file_name()),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => Ordering::Equal,
+            });
+            Box::new(entries.into_iter())
+        } else {
+            Box::new(read)
+        };
+        self.stack.push(WalkDir {
+            path,
+            rel,
+            read,
+            canonical,
+        });
+    }
+}
+
+impl Walk {
+    /// The next entry along with its path relative to the root and its metadata.
+    fn next_entry(&mut self) -> Option<Result<(PathType, PathBuf, fs::Metadata)>> {
+        if let Some(root) = self.root.take() {
+            return Some(
+                root.metadata()
+                    .map(|meta| (PathType::Dir(root), PathBuf::new(), meta)),
+            );
+        }
+        loop {
+            if let Some(err) = self.pending.take() {
+                return Some(Err(err));
+            }
+            let depth = self.stack.len();
+            let (next, rel) = match self.stack.last_mut() {
+                Some(dir) => {
+                    let path = &dir.path;
+                    let next = dir.read.next().map(|entry| {
+                        entry.map_err(|err| Error::new(err, "iterating over", path.clone()))
+                    });
+                    (next, &dir.rel)
+                }
+                None => return None,
+            };
+            let entry = match next {
+                Some(Ok(ref entry)) if self.options.skip_hidden && is_hidden(entry) => continue,
+                Some(Ok(entry)) => entry,
+                Some(Err(err)) => return Some(Err(err)),
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            let rel = rel.join(entry.file_name());
+
+            let (ty, meta, is_link) = match resolve_entry(&entry) {
+                Ok(resolved) => resolved,
+                Err(err) => return Some(Err(err)),
+            };
+            if let Some(ref mut filter) = self.options.filter {
+                if !filter(&ty) {
+                    continue;
+                }
+            }
+            if depth < self.options.max_depth {
+                if let PathType::Dir(ref dir) = ty {
+                    if !is_link || self.options.follow_links {
+                        self.push(dir.clone().into(), rel.clone());
+                    }
+                }
+            }
+            if depth >= self.options.min_depth {
+                return Some(Ok((ty, rel, meta)));
+            }
+        }
+    }
+}
+
+impl ::std::iter::Iterator for Walk {
+    type Item = Result<PathType>;
+    fn next(&mut self) -> Option<Result<PathType>> {
+        self.next_entry().map(|entry| entry.map(|(ty, _, _)| ty))
+    }
+}
+
+/// An iterator over the entries of a directory tree with their paths relative to the root,
+/// returned by `PathDir::walk_rel`.
+#[derive(Debug)]
+pub struct WalkRel(Walk);
+
+impl ::std::iter::Iterator for WalkRel {
+    type Item = Result<Entry>;
+    fn next(&mut self) -> Option<Result<Entry>> {
+        self.0
+            .next_entry()
+            .map(|entry| entry.map(|(abs, rel, _)| Entry { abs, rel }))
+    }
+}
+
+/// An iterator over the entries of a directory tree with their metadata, returned by
+/// `PathDir::walk_meta`.
+#[derive(Debug)]
+pub struct WalkMeta(Walk);
+
+impl ::std::iter::Iterator for WalkMeta {
+    type Item = Result<EntryMeta>;
+    fn next(&mut self) -> Option<Result<EntryMeta>> {
+        self.0
+            .next_entry()
+            .map(|entry| entry.map(|(abs, _, metadata)| EntryMeta { abs, metadata }))
+    }
+}
+
+impl fmt::Debug for Walk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.stack.last() {
+            Some(dir) => write!(f, "Walk({})", dir.path.display()),
+            None => write!(f, "Walk(done)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{PathDir, PathFile, PathType};
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+    use std::path::{Path, PathBuf};
+    use std::rc::Rc;
+    use tempdir::TempDir;
+
+    /// Create a tree in a new temporary directory.
+    fn fixture() -> (TempDir, PathDir) {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let root = PathDir::new(tmp_dir.path()).unwrap();
+        for file in &["a.txt", "b/c.txt", "b/d/e.txt", "b/d/f/g.txt", "h/i.txt"] {
+            PathFile::create_all(root.join(file)).unwrap();
+        }
+        PathDir::create(root.join("empty")).unwrap();
+        (tmp_dir, root)
+    }
+
+    /// The paths yielded by `walk`, relative to `root`, in order.
+    fn relative<I: Iterator<Item = ::Result<PathType>>>(root: &PathDir, walk: I) -> Vec<String> {
+        walk.map(|entry| {
+            let entry = entry.unwrap();
+            entry.strip_prefix(root).unwrap().display().to_string()
+        })
+        .collect()
+    }
+
+    #[test]
+    fn sanity_walk() {
+        let (_tmp, root) = fixture();
+        let walked = relative(&root, root.walk());
+
+        let expected: HashSet<_> = [
+            "a.txt",
+            "b",
+            "b/c.txt",
+            "b/d",
+            "b/d/e.txt",
+            "b/d/f",
+            "b/d/f/g.txt",
+            "empty",
+            "h",
+            "h/i.txt",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(expected.len(), walked.len());
+        assert_eq!(expected, walked.iter().cloned().collect());
+
+        // Directories come before their contents
+        for (i, path) in walked.iter().enumerate() {
+            if let Some(parent) = ::std::path::Path::new(path).parent() {
+                if !parent.as_os_str().is_empty() {
+                    let parent = parent.display().to_string();
+                    assert!(walked[..i].contains(&parent), "{} before {}", path, parent);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sanity_walk_symlink_loop() {
+        let (_tmp, root) = fixture();
+        let b = PathDir::new(root.join("b")).unwrap();
+        root.symlink(b.join("d/loop")).unwrap();
+
+        let walked = relative(&root, root.walk());
+        assert_eq!(11, walked.len());
+        assert!(walked.contains(&"b/d/loop".to_string()));
+        assert!(!walked.iter().any(|p| p.starts_with("b/d/loop/")));
+    }
+
+    #[test]
+    fn sanity_walk_errors() {
+        let (_tmp, root) = fixture();
+        // A broken symlink can't be resolved
+        PathFile::new(root.join("a.txt"))
+            .unwrap()
+            .symlink(root.join("b/broken"))
+            .unwrap();
+        PathFile::new(root.join("a.txt")).unwrap().remove().unwrap();
+
+        let mut ok = 0;
+        let mut errors = Vec::new();
+        for entry in root.walk() {
+            match entry {
+                Ok(_) => ok += 1,
+                Err(err) => errors.push(err),
+            }
+        }
+        assert_eq!(9, ok);
+        assert_eq!(1, errors.len());
+        assert_eq!(root.join("b/broken").as_path(), errors[0].path());
+
+        let missing = PathDir::mock(root.join("missing"));
+        let errors: Vec<_> = missing.walk().collect();
+        assert_eq!(1, errors.len());
+        assert_eq!("reading dir", errors[0].as_ref().unwrap_err().action());
+    }
+
+    #[test]
+    fn sanity_walk_vec() {
+        let (_tmp, root) = fixture();
+        let walked: Vec<_> = root.walk().map(|e| e.unwrap()).collect();
+        assert_eq!(walked, root.walk_vec().unwrap());
+
+        PathFile::new(root.join("a.txt"))
+            .unwrap()
+            .symlink(root.join("b/d/broken"))
+            .unwrap();
+        PathFile::new(root.join("a.txt")).unwrap().remove().unwrap();
+        let err = root.walk_vec().unwrap_err();
+        // `a.txt` was removed
+        assert_eq!(walked.len() - 1, err.entries().len());
+        assert_eq!(1, err.errors().len());
+        assert_eq!(root.join("b/d/broken").as_path(), err.errors()[0].path());
+    }
+
+    #[test]
+    fn sanity_walk_max_depth() {
+        let (_tmp, root) = fixture();
+        let listed: HashSet<_> = root.list().unwrap().map(|e| e.unwrap()).collect();
+        let walked: HashSet<_> = root
+            .walk_opts()
+            .max_depth(1)
+            .walk()
+            .map(|e| e.unwrap())
+            .collect();
+        assert_eq!(listed, walked);
+
+        let walked = relative(&root, root.walk_opts().max_depth(2).walk());
+        assert_eq!(7, walked.len());
+        assert!(walked.contains(&"b/d".to_string()));
+        assert!(!walked.contains(&"b/d/e.txt".to_string()));
+
+        assert_eq!(0, root.walk_opts().max_depth(0).walk().count());
+    }
+
+    #[test]
+    fn sanity_walk_min_depth() {
+        let (_tmp, root) = fixture();
+        let mut walk = root.walk_opts().min_depth(0).walk();
+        assert_eq!(PathType::Dir(root.clone()), walk.next().unwrap().unwrap());
+        assert_eq!(10, walk.count());
+
+        let walked = relative(&root, root.walk_opts().min_depth(3).walk());
+        let walked: HashSet<_> = walked.iter().map(|s| s.as_str()).collect();
+        let expected: HashSet<_> = ["b/d/e.txt", "b/d/f", "b/d/f/g.txt"]
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(expected, walked);
+
+        let walk = root.walk_opts().min_depth(0).max_depth(0).walk();
+        assert_eq!(1, walk.count());
+    }
+
+    #[test]
+    fn sanity_walk_follow_links() {
+        let (_tmp, root) = fixture();
+        let (_tmp_outside, outside) = fixture();
+        let b = PathDir::new(root.join("b")).unwrap();
+        outside.symlink(root.join("link")).unwrap();
+        // Loops to the root and to a directory in between
+        root.symlink(b.join("d/loop")).unwrap();
+        b.symlink(b.join("d/f/loop")).unwrap();
+
+        let walked = relative(&root, root.walk());
+        assert!(walked.contains(&"link".to_string()));
+        assert!(!walked.contains(&"link/a.txt".to_string()));
+
+        let walked = relative(&root, root.walk_opts().follow_links(true).walk());
+        assert!(walked.contains(&"link/b/d/f/g.txt".to_string()));
+        assert!(walked.contains(&"b/d/loop".to_string()));
+        assert!(walked.contains(&"b/d/f/loop".to_string()));
+        assert!(!walked.iter().any(|p| p.contains("loop/")));
+        assert_eq!(23, walked.len());
+    }
+
+    #[test]
+    fn sanity_walk_rel() {
+        let (_tmp, root) = fixture();
+        let (_tmp_outside, outside) = fixture();
+        outside.symlink(root.join("link")).unwrap();
+
+        let entries: Vec<_> = root.walk_rel().map(|e| e.unwrap()).collect();
+        assert_eq!(relative(&root, root.walk()).len(), entries.len());
+        for entry in &entries {
+            assert_eq!(root.join(&entry.rel).as_path(), entry.abs.as_path());
+        }
+        let rel: HashSet<_> = entries.iter().map(|e| e.rel.to_path_buf()).collect();
+        assert!(rel.contains(Path::new("b/d/f/g.txt")));
+        assert!(rel.contains(Path::new("link")));
+
+        let walk = root
+            .walk_opts()
+            .follow_links(true)
+            .sort_by_file_name(true)
+            .min_depth(0)
+            .walk_rel();
+        let rel: Vec<_> = walk.map(|e| e.unwrap().rel).collect();
+        assert_eq!(Path::new(""), rel[0]);
+        assert_eq!(Path::new("a.txt"), rel[1]);
+        assert!(rel.contains(&PathBuf::from("link/b/d/f/g.txt")));
+        assert_eq!(22, rel.len());
+    }
+
+    #[test]
+    fn sanity_walk_meta() {
+        let (_tmp, root) = fixture();
+        PathFile::new(root.join("b/d/e.txt"))
+            .unwrap()
+            .write_str("contents")
+            .unwrap();
+
+        let walked: Vec<_> = root.walk().map(|e| e.unwrap()).collect();
+        let entries: Vec<_> = root.walk_meta().map(|e| e.unwrap()).collect();
+        assert_eq!(walked.len(), entries.len());
+        for entry in &entries {
+            assert!(walked.contains(&entry.abs));
+            assert_eq!(entry.abs.is_dir(), entry.metadata.is_dir());
+            assert_eq!(entry.abs.metadata().unwrap().len(), entry.metadata.len());
+        }
+        let size: u64 = entries
+            .iter()
+            .filter(|e| e.abs.is_file())
+            .map(|e| e.metadata.len())
+            .sum();
+        assert_eq!(8, size);
+
+        let mut walk = root.walk_opts().min_depth(0).max_depth(0).walk_meta();
+        let entry = walk.next().unwrap().unwrap();
+        assert_eq!(PathType::Dir(root.clone()), entry.abs);
+        assert!(entry.metadata.is_dir());
+        assert!(walk.next().is_none());
+    }
+
+    #[test]
+    fn sanity_walk_skip_hidden() {
+        let (_tmp, root) = fixture();
+        let expected: HashSet<_> = relative(&root, root.walk()).into_iter().collect();
+        for file in &[".git/HEAD", ".git/refs/heads/master", ".hidden", "b/.hidden", "h/.d/j"] {
+            PathFile::create_all(root.join(file)).unwrap();
+        }
+        assert_eq!(19, root.walk().count());
+
+        let walked = relative(&root, root.walk_opts().skip_hidden(true).walk());
+        assert_eq!(10, walked.len());
+        assert_eq!(expected, walked.into_iter().collect());
+
+        // The root itself can be hidden
+        let hidden = PathDir::new(root.join(".git")).unwrap();
+        let walk = hidden.walk_opts().skip_hidden(true).min_depth(0).walk();
+        assert_eq!(5, walk.count());
+    }
+
+    #[test]
+    fn sanity_walk_filter_entry() {
+        let (_tmp, root) = fixture();
+        for i in 0..100 {
+            PathFile::create_all(root.join(format!("target/{}/{}", i % 10, i))).unwrap();
+        }
+        assert_eq!(121, root.walk().count());
+
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let walked = {
+            let seen = seen.clone();
+            let walk = root
+                .walk_opts()
+                .filter_entry(move |entry| {
+                    seen.borrow_mut().push(entry.to_path_buf());
+                    !entry.ends_with("target") && !entry.ends_with("c.txt")
+                })
+                .walk();
+            relative(&root, walk)
+        };
+        assert_eq!(9, walked.len());
+        assert!(!walked.contains(&"b/c.txt".to_string()));
+        assert!(!walked.iter().any(|p| p.starts_with("target")));
+
+        // Nothing inside of the pruned directory was read
+        let seen = RefCell::borrow(&seen);
+        assert_eq!(11, seen.len());
+        let target = root.join("target");
+        assert!(!seen
+            .iter()
+            .any(|p| p.starts_with(&target) && p.as_path() != target.as_path()));
+    }
+
+    #[test]
+    fn sanity_walk_sorted() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let root = PathDir::new(tmp_dir.path()).unwrap();
+        for file in &["b", "B/z", "B/a", "B/Y", "a", "C", "_"] {
+            PathFile::create_all(root.join(file)).unwrap();
+        }
+
+        let walk = root.walk_opts().sort_by_file_name(true).walk();
+        let expected = vec!["B", "B/Y", "B/a", "B/z", "C", "_", "a", "b"];
+        assert_eq!(expected, relative(&root, walk));
+
+        let listed: Vec<_> = root
+            .list_sorted()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(vec!["B", "C", "_", "a", "b"], listed);
+    }
+}
@@ -0,0 +1,264 @@
+/* Copyright (c) 2018 Garrett Berg, vitiral@gmail.com
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+//! Watching directories for changes, enabled with the `watch` feature.
+
+use notify::{self, EventKind, RecursiveMode, Watcher};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use std_prelude::*;
+
+use super::{Error, Result};
+use super::{PathAbs, PathArc, PathDir, PathType};
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// A change to an entry of a watched directory, received from a `DirWatcher`.
+pub enum WatchEvent {
+    /// The entry was created, or renamed to this path.
+    Created(PathType),
+    /// The contents or metadata of the entry changed.
+    Modified(PathType),
+    /// The entry was removed, or renamed away from this path. It no longer exists, so it is
+    /// only a `PathAbs`.
+    Removed(PathAbs),
+}
+
+/// Receives the changes to a directory, returned by `PathDir::watch`.
+///
+/// The directory is watched until the `DirWatcher` is dropped.
+pub struct DirWatcher {
+    dir: PathDir,
+    rx: mpsc::Receiver<notify::Result<notify::Event>>,
+    /// Events which were received together but not returned yet.
+    pending: VecDeque<Result<WatchEvent>>,
+    _watcher: notify::RecommendedWatcher,
+}
+
+impl PathDir {
+    /// Watch the directory and everything inside of it for changes.
+    ///
+    /// Events are converted to a `WatchEvent` when they are received, so an entry which was
+    /// already removed again is reported as `WatchEvent::Removed`. Which events are reported
+    /// for an operation (i.e. how many `Modified` for a write) depends on the platform.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use std::time::Duration;
+    /// use path_abs::{PathDir, PathFile, PathType, WatchEvent};
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let dir = PathDir::create(example)?;
+    /// let mut watcher = dir.watch()?;
+    ///
+    /// let file = PathFile::create(dir.join("file.txt"))?;
+    /// let event = watcher.recv_timeout(Duration::from_secs(10)).unwrap()?;
+    /// assert_eq!(WatchEvent::Created(PathType::File(file)), event);
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn watch(&self) -> Result<DirWatcher> {
+        DirWatcher::new(self, RecursiveMode::Recursive)
+    }
+
+    /// Watch only the entries of the directory for changes, like `watch` but without watching
+    /// its subdirectories.
+    pub fn watch_shallow(&self) -> Result<DirWatcher> {
+        DirWatcher::new(self, RecursiveMode::NonRecursive)
+    }
+}
+
+impl DirWatcher {
+    fn new(dir: &PathDir, mode: RecursiveMode) -> Result<DirWatcher> {
+        let (tx, rx) = mpsc::channel();
+        let watcher = notify::recommended_watcher(tx)
+            .and_then(|mut watcher| watcher.watch(dir, mode).map(|_| watcher))
+            .map_err(|err| Error::new(io_error(err), "watching", dir.clone().into()));
+        Ok(DirWatcher {
+            dir: dir.clone(),
+            rx,
+            pending: VecDeque::new(),
+            _watcher: watcher?,
+        })
+    }
+
+    /// The directory being watched.
+    pub fn dir(&self) -> &PathDir {
+        &self.dir
+    }
+
+    /// Block until the next event is received.
+    pub fn recv(&mut self) -> Result<WatchEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return event;
+            }
+            let received = self.rx.recv().map_err(|_| self.disconnected())?;
+            self.push(received);
+        }
+    }
+
+    /// Return the next event if one was already received, without blocking.
+    pub fn try_recv(&mut self) -> Option<Result<WatchEvent>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+            match self.rx.try_recv() {
+                Ok(received) => self.push(received),
+                Err(mpsc::TryRecvError::Empty) => return None,
+                Err(mpsc::TryRecvError::Disconnected) => return Some(Err(self.disconnected())),
+            }
+        }
+    }
+
+    /// Block until the next event is received or `timeout` elapses, returning `None` on
+    /// timeout.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<Result<WatchEvent>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.rx.recv_timeout(remaining) {
+                Ok(received) => self.push(received),
+                Err(mpsc::RecvTimeoutError::Timeout) => return None,
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    return Some(Err(self.disconnected()))
+                }
+            }
+        }
+    }
+
+    /// Convert a received notify event into the pending `WatchEvent`s.
+    fn push(&mut self, received: notify::Result<notify::Event>) {
+        let event = match received {
+            Ok(event) => event,
+            Err(err) => {
+                let err = Error::new(io_error(err), "watching", self.dir.clone().into());
+                self.pending.push_back(Err(err));
+                return;
+            }
+        };
+        let removed = match event.kind {
+            EventKind::Remove(_) => true,
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Any => false,
+            EventKind::Access(_) | EventKind::Other => return,
+        };
+        let created = matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(notify::event::ModifyKind::Name(_))
+        );
+        for path in event.paths {
+            let abs = PathAbs(PathArc::new(path));
+            let converted = if removed {
+                Ok(WatchEvent::Removed(abs))
+            } else {
+                match PathType::from_abs(abs.clone()) {
+                    Ok(ty) if created => Ok(WatchEvent::Created(ty)),
+                    Ok(ty) => Ok(WatchEvent::Modified(ty)),
+                    Err(ref err) if err.io_error().kind() == io::ErrorKind::NotFound => {
+                        Ok(WatchEvent::Removed(abs))
+                    }
+                    Err(err) => Err(err),
+                }
+            };
+            self.pending.push_back(converted);
+        }
+    }
+
+    fn disconnected(&self) -> Error {
+        Error::new(
+            io::Error::new(io::ErrorKind::BrokenPipe, "the watcher stopped"),
+            "watching",
+            self.dir.clone().into(),
+        )
+    }
+}
+
+impl fmt::Debug for DirWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DirWatcher({})", self.dir.display())
+    }
+}
+
+/// Convert a notify error to an `io::Error`, keeping the underlying one if there is one.
+fn io_error(err: notify::Error) -> io::Error {
+    match err.kind {
+        notify::ErrorKind::Io(err) => err,
+        notify::ErrorKind::PathNotFound => {
+            io::Error::new(io::ErrorKind::NotFound, "path not found")
+        }
+        kind => io::Error::other(format!("{:?}", kind)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{PathDir, PathFile, PathType};
+    use super::WatchEvent;
+    use std::time::Duration;
+    use tempdir::TempDir;
+
+    /// Receive events until `expected` is received, failing after a generous timeout.
+    fn expect(watcher: &mut super::DirWatcher, expected: WatchEvent) {
+        let mut received = Vec::new();
+        while let Some(event) = watcher.recv_timeout(Duration::from_secs(10)) {
+            let event = event.unwrap();
+            if event == expected {
+                return;
+            }
+            received.push(event);
+        }
+        panic!("{:?} was not received, only {:?}", expected, received);
+    }
+
+    #[test]
+    fn sanity_watch() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let nested = PathDir::create(tmp_abs.join("nested")).unwrap();
+
+        let mut watcher = tmp_abs.watch().unwrap();
+        assert_eq!(&tmp_abs, watcher.dir());
+        assert!(watcher.try_recv().is_none());
+
+        let file = PathFile::create(nested.join("file.txt")).unwrap();
+        expect(&mut watcher, WatchEvent::Created(PathType::File(file.clone())));
+
+        file.write_str("changed").unwrap();
+        expect(&mut watcher, WatchEvent::Modified(PathType::File(file.clone())));
+
+        let abs = file.clone().into();
+        file.remove().unwrap();
+        expect(&mut watcher, WatchEvent::Removed(abs));
+    }
+
+    #[test]
+    fn sanity_watch_shallow() {
+        let tmp_dir = TempDir::new("example").expect("create temp dir");
+        let tmp_abs = PathDir::new(tmp_dir.path()).unwrap();
+        let nested = PathDir::create(tmp_abs.join("nested")).unwrap();
+
+        let mut watcher = tmp_abs.watch_shallow().unwrap();
+        PathFile::create(nested.join("ignored.txt")).unwrap();
+        let file = PathFile::create(tmp_abs.join("file.txt")).unwrap();
+        expect(&mut watcher, WatchEvent::Created(PathType::File(file)));
+        while let Some(event) = watcher.recv_timeout(Duration::from_millis(200)) {
+            if let WatchEvent::Created(ty) = event.unwrap() {
+                assert!(!ty.ends_with("ignored.txt"));
+            }
+        }
+    }
+}
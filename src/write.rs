@@ -14,6 +14,7 @@ use std_prelude::*;
 
 use super::{Error, Result};
 use super::PathFile;
+use super::file::CHUNK_SIZE;
 use super::open::FileOpen;
 
 /// A write-only file handle with `path()` attached and improved error messages. Contains only the
@@ -148,6 +149,45 @@ impl FileWrite {
             .map_err(|err| Error::new(err, "writing", self.path.clone().into()))
     }
 
+    /// Shortcut to `self.write_all(bytes)` with slightly improved error message.
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.0
+            .file
+            .write_all(bytes)
+            .map_err(|err| Error::new(err, "writing", self.path.clone().into()))
+    }
+
+    /// Copy the entire contents of `reader` into the file, returning the number of bytes
+    /// written.
+    ///
+    /// Errors from the reader are reported with the action `reading source for`.
+    pub fn write_from<R: Read + ?Sized>(&mut self, reader: &mut R) -> Result<u64> {
+        let mut buf = vec![0; CHUNK_SIZE];
+        let mut written = 0;
+        loop {
+            let len = match reader.read(&mut buf) {
+                Ok(0) => return Ok(written),
+                Ok(len) => len,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    return Err(Error::new(err, "reading source for", self.path.clone().into()))
+                }
+            };
+            self.write_bytes(&buf[..len])?;
+            written += len as u64;
+        }
+    }
+
+    /// Write all of `buf` to the file at `offset`, extending the file if necessary.
+    ///
+    /// This does not use (or change) the cursor of the file on unix, but it may on Windows.
+    ///
+    /// > On unix, files opened in append mode always write to the end of the file regardless
+    /// > of `offset`.
+    pub fn write_at(&self, buf: &[u8], offset: u64) -> Result<()> {
+        self.0.write_at(buf, offset)
+    }
+
     /// `std::io::File::flush` buth with the new error type.
     pub fn flush(&mut self) -> Result<()> {
         self.0
@@ -155,6 +195,32 @@ impl FileWrite {
             .flush()
             .map_err(|err| Error::new(err, "flushing", self.path.clone().into()))
     }
+
+    /// Flush and sync the file to disk before closing it, returning any error.
+    ///
+    /// Dropping a `FileWrite` also closes it, but ignores all errors. Some filesystems (i.e.
+    /// NFS) only report failed writes when the file is synced or closed, so the file is synced
+    /// with `sync_all` which can be slow.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # extern crate path_abs;
+    /// # extern crate tempdir;
+    /// use path_abs::FileWrite;
+    ///
+    /// # fn try_main() -> ::std::io::Result<()> {
+    /// let example = "example.txt";
+    /// # let tmp = tempdir::TempDir::new("ex")?;
+    /// # let example = &tmp.path().join(example);
+    /// let mut write = FileWrite::create(example)?;
+    /// write.write_str("important")?;
+    /// write.flush_and_close()?;
+    /// # Ok(()) } fn main() { try_main().unwrap() }
+    /// ```
+    pub fn flush_and_close(mut self) -> Result<()> {
+        self.flush()?;
+        self.sync_all()
+    }
 }
 
 impl fmt::Debug for FileWrite {